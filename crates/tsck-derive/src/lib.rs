@@ -1,37 +1,96 @@
 use proc_macro::TokenStream;
+use proc_macro2::TokenStream as TokenStream2;
 use quote::quote;
-use syn::{Data, DeriveInput, Fields, parse_macro_input};
+use syn::{
+    Attribute, Data, DeriveInput, Expr, ExprLit, Field, Fields, Lit, LitStr, Meta, PathArguments,
+    Type, Variant, parse_macro_input,
+};
 
-#[proc_macro_derive(ScopeParser)]
+#[proc_macro_derive(ScopeParser, attributes(scope))]
 pub fn derive_scope_parser(input: TokenStream) -> TokenStream {
     let input = parse_macro_input!(input as DeriveInput);
-    let name = &input.ident;
+    scope_parser(&input)
+        .unwrap_or_else(syn::Error::into_compile_error)
+        .into()
+}
 
-    let variants = match &input.data {
-        Data::Enum(data) => &data.variants,
-        _ => panic!("ScopeParser only works on enums"),
-    };
+#[proc_macro_derive(FuncParser, attributes(func))]
+pub fn derive_func_parser(input: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(input as DeriveInput);
+    func_parser(&input)
+        .unwrap_or_else(syn::Error::into_compile_error)
+        .into()
+}
 
-    let parse_arms = variants.iter().map(|variant| {
-        let variant_name = &variant.ident;
-        let variant_str = variant_name.to_string().to_lowercase();
+fn scope_parser(input: &DeriveInput) -> syn::Result<TokenStream2> {
+    let name = &input.ident;
+    let variants = parse_variants(input, "ScopeParser", "scope", "name")?;
 
-        match &variant.fields {
+    // every variant wraps the `FuncParser` enum of its scope, `App(AppFunc)`
+    let mut errors = Errors::default();
+    let mut scopes = Vec::new();
+    for parsed in &variants {
+        match &parsed.variant.fields {
             Fields::Unnamed(fields) if fields.unnamed.len() == 1 => {
-                let field_type = &fields.unnamed.first().unwrap().ty;
+                scopes.push((parsed, &fields.unnamed.first().unwrap().ty));
+            }
+            Fields::Unit => errors.push(syn::Error::new_spanned(
+                &parsed.variant.ident,
+                "ScopeParser variants wrap a FuncParser enum, like `App(AppFunc)`",
+            )),
+            fields => errors.push(syn::Error::new_spanned(
+                fields,
+                "ScopeParser variants wrap a single FuncParser enum, like `App(AppFunc)`",
+            )),
+        }
+    }
+    errors.finish()?;
 
-                quote! {
-                    #variant_str => {
-                        let func = #field_type::from_func(&parsed)?;
-                        Ok(#name::#variant_name(func))
-                    },
-                }
+    let parse_arms = scopes.iter().map(|(parsed, field_type)| {
+        let variant_name = &parsed.variant.ident;
+        let matched = parsed.names.matched();
+        quote! {
+            #(#matched)|* => {
+                let func = <#field_type>::from_func(&parsed)?;
+                Ok(#name::#variant_name(func))
+            },
+        }
+    });
+
+    let help_arms = scopes.iter().map(|(parsed, field_type)| {
+        let matched = parsed.names.matched();
+        let doc = option_tokens(parsed.doc.clone());
+        quote! {
+            #(#matched)|* => match func {
+                Some(func) => <#field_type>::help(func),
+                None => #doc,
+            },
+        }
+    });
+
+    let schemas = scopes.iter().map(|(parsed, field_type)| {
+        let VariantNames { name, aliases } = &parsed.names;
+        let name = name.to_lowercase();
+        let doc = option_tokens(parsed.doc.clone());
+        quote! {
+            ScopeSchema {
+                name: #name,
+                aliases: &[#(#aliases),*],
+                doc: #doc,
+                funcs: <#field_type>::schema(),
             }
-            _ => quote! {},
         }
     });
 
-    let expanded = quote! {
+    let display_arms = scopes.iter().map(|(parsed, _)| {
+        let variant_name = &parsed.variant.ident;
+        let variant_str = parsed.names.name.to_lowercase();
+        quote! {
+            #name::#variant_name(func) => write!(f, "{}::{}", #variant_str, func),
+        }
+    });
+
+    Ok(quote! {
         impl #name {
             pub fn parse(s: &str) -> Result<Self, String> {
                 let parsed = FuncLexer::parse_func(s)
@@ -39,9 +98,33 @@ pub fn derive_scope_parser(input: TokenStream) -> TokenStream {
 
                 match parsed.entry.to_lowercase().as_str() {
                     #(#parse_arms)*
-                    _ => Err(format!("Unknown scope: {}", parsed.entry))
+                    _ => {
+                        let hint = ScopeSchema::suggest(Self::schema(), parsed.entry)
+                            .map(|name| format!(" (did you mean {}?)", name))
+                            .unwrap_or_default();
+                        Err(format!("Unknown scope: {}{}", parsed.entry, hint))
+                    }
+                }
+            }
+
+            /// Doc comment of `scope` or of `scope::FUNC`, arguments are ignored
+            pub fn help(s: &str) -> Option<&'static str> {
+                let s = s.split('(').next().unwrap_or_default().trim();
+                let (scope, func) = match s.split_once("::") {
+                    Some((scope, func)) => (scope, Some(func)),
+                    None => (s, None),
+                };
+                match scope.to_lowercase().as_str() {
+                    #(#help_arms)*
+                    _ => None,
                 }
             }
+
+            /// Every scope with its functions and their arguments
+            pub const fn schema() -> &'static [ScopeSchema] {
+                const SCHEMA: &[ScopeSchema] = &[#(#schemas),*];
+                SCHEMA
+            }
         }
 
         impl std::str::FromStr for #name {
@@ -51,94 +134,518 @@ pub fn derive_scope_parser(input: TokenStream) -> TokenStream {
                 Self::parse(s)
             }
         }
-    };
 
-    TokenStream::from(expanded)
+        /// `scope::FUNC(args)`, parses back to the same value
+        impl std::fmt::Display for #name {
+            fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+                match self {
+                    #(#display_arms)*
+                }
+            }
+        }
+    })
 }
 
-#[proc_macro_derive(FuncParser)]
-pub fn derive_func_parser(input: TokenStream) -> TokenStream {
-    let input = parse_macro_input!(input as DeriveInput);
+fn func_parser(input: &DeriveInput) -> syn::Result<TokenStream2> {
     let name = &input.ident;
+    let variants = parse_variants(input, "FuncParser", "func", "rename")?;
 
-    let variants = match &input.data {
-        Data::Enum(data) => &data.variants,
-        _ => panic!("FuncParser only works on enums"),
-    };
+    let mut errors = Errors::default();
+    let mut fields = Vec::new();
+    for parsed in &variants {
+        match func_fields(parsed.variant) {
+            Ok(variant_fields) => fields.push(variant_fields),
+            Err(err) => errors.push(err),
+        }
+    }
+    errors.finish()?;
 
-    let parse_branches = variants.iter().map(|variant| {
-          let variant_name = &variant.ident;
-          let variant_str = variant_name.to_string().to_lowercase();
-
-          match &variant.fields {
-              Fields::Unit => {
-                  quote! {
-                      if parsed.func.eq_ignore_ascii_case(#variant_str) {
-                          return Ok(#name::#variant_name);
-                      }
-                  }
-              }
-              Fields::Unnamed(fields) if fields.unnamed.len() == 1 => {
-                  let field_type = &fields.unnamed.first().unwrap().ty;
-                  let type_str = quote!(#field_type).to_string();
-
-                  if type_str == "String" {
-                      quote! {
-                          if parsed.func.eq_ignore_ascii_case(#variant_str) {
-                              if let Some(FuncExpr::String(s)) = &parsed.args {
-                                  return Ok(#name::#variant_name(s.to_string()));
-                              }
-                              return Err(format!("{} expects a string argument", #variant_str));
-                          }
-                      }
-                  } else if type_str == "i32" {
-                      quote! {
-                          if parsed.func.eq_ignore_ascii_case(#variant_str) {
-                              if let Some(FuncExpr::Number(n)) = &parsed.args {
-                                  return Ok(#name::#variant_name(*n as i32));
-                              }
-                              return Err(format!("{} expects a number argument", #variant_str));
-                          }
-                      }
-                  } else {
-                      quote! {
-                          if parsed.func.eq_ignore_ascii_case(#variant_str) {
-                              if let Some(FuncExpr::Ident(ident)) = &parsed.args {
-                                  return ident.parse::<#field_type>()
-                                      .map(|v| #name::#variant_name(v))
-                                      .map_err(|e| format!("Failed to parse {}: {:?}", #variant_str, e));
-                              }
-                              // if let Some(FuncExpr::TupleString(a,b)) = &parsed.args {
-                              //     return Ok(#name::#variant_name(a.to_string(),b.to_string()));
-                              // }
-                              return Err(format!("{} expects an identifier argument", #variant_str));
-                          }
-                      }
-                  }
-              }
-              Fields::Unnamed(fields) if fields.unnamed.len() == 2 => {
+    let parse_branches = variants.iter().zip(&fields).map(|(parsed, func_fields)| {
+        let variant_name = &parsed.variant.ident;
+        let variant_str = parsed.names.name.to_lowercase();
+        let matched = parsed.names.matched();
+
+        match &parsed.variant.fields {
+            Fields::Unit => {
+                quote! {
+                    if #(parsed.func.eq_ignore_ascii_case(#matched))||* {
+                        return Ok(#name::#variant_name);
+                    }
+                }
+            }
+            Fields::Unnamed(_) => {
+                let FieldBinding { names, values } = bind_fields(&variant_str, func_fields);
+                // `(('a','b'))` is the old spelling of `('a','b')`
+                let unwrap_tuple = if func_fields.len() > 1 {
+                    quote! { [FuncExpr::Tuple(items)] => items.as_slice(), }
+                } else {
+                    quote! {}
+                };
+                quote! {
+                    if #(parsed.func.eq_ignore_ascii_case(#matched))||* {
+                        let args = match parsed.args.as_slice() {
+                            #unwrap_tuple
+                            args => args,
+                        };
+                        let slots = FuncExpr::bind(args, &[#(#names),*])
+                            .map_err(|e| format!("{}: {}", #variant_str, e))?;
+                        return Ok(#name::#variant_name(#(#values),*));
+                    }
+                }
+            }
+            Fields::Named(named) => {
+                let FieldBinding { names, values } = bind_fields(&variant_str, func_fields);
+                let idents = named.named.iter().map(|field| &field.ident);
                 quote! {
-                  if parsed.func.eq_ignore_ascii_case(#variant_str) {
-                      if let Some(FuncExpr::TupleString(a,b)) = &parsed.args {
-                          return Ok(#name::#variant_name(a.to_string(),b.to_string()));
-                      }
-                      return Err(format!("{} expects a number argument", #variant_str));
-                  }
+                    if #(parsed.func.eq_ignore_ascii_case(#matched))||* {
+                        let slots = FuncExpr::bind(&parsed.args, &[#(#names),*])
+                            .map_err(|e| format!("{}: {}", #variant_str, e))?;
+                        return Ok(#name::#variant_name { #(#idents: #values),* });
+                    }
                 }
-              }
+            }
+        }
+    });
 
-              _ => quote! {}
-          }
-      });
+    let help_branches = variants.iter().map(|parsed| {
+        let matched = parsed.names.matched();
+        let doc = option_tokens(parsed.doc.clone());
+        quote! {
+            if #(func.eq_ignore_ascii_case(#matched))||* {
+                return #doc;
+            }
+        }
+    });
+
+    let schemas = variants.iter().zip(&fields).map(|(parsed, func_fields)| {
+        let VariantNames { name, aliases } = &parsed.names;
+        let doc = option_tokens(parsed.doc.clone());
+        let args = func_fields.iter().map(arg_schema);
+        quote! {
+            FuncSchema {
+                name: #name,
+                aliases: &[#(#aliases),*],
+                doc: #doc,
+                args: &[#(#args),*],
+            }
+        }
+    });
+
+    let display_arms = variants.iter().map(|parsed| {
+        let variant = parsed.variant;
+        let variant_name = &variant.ident;
+        let func_str = &parsed.names.name;
+        let bindings: Vec<_> = (0..variant.fields.len())
+            .map(|i| quote::format_ident!("arg{}", i))
+            .collect();
+        // trailing `None`s are left out, `MOVE('UP')` rather than `MOVE('UP', none)`
+        let args = variant
+            .fields
+            .iter()
+            .zip(&bindings)
+            .map(|(field, binding)| match is_option(&field.ty) {
+                true => quote! { #binding.as_ref().map(FuncExpr::of) },
+                false => quote! { Some(FuncExpr::of(#binding)) },
+            });
+        let pattern = match &variant.fields {
+            Fields::Unit => quote! { #name::#variant_name },
+            Fields::Unnamed(_) => quote! { #name::#variant_name(#(#bindings),*) },
+            Fields::Named(fields) => {
+                let idents = fields.named.iter().map(|field| &field.ident);
+                quote! { #name::#variant_name { #(#idents: #bindings),* } }
+            }
+        };
+        quote! {
+            #pattern => {
+                f.write_str(#func_str)?;
+                let mut args: Vec<Option<FuncExpr>> = vec![#(#args),*];
+                while args.last().is_some_and(Option::is_none) {
+                    args.pop();
+                }
+                if !args.is_empty() {
+                    let args: Vec<String> = args
+                        .iter()
+                        .map(|arg| arg.as_ref().map_or("none".to_string(), |a| a.to_string()))
+                        .collect();
+                    write!(f, "({})", args.join(", "))?;
+                }
+                Ok(())
+            }
+        }
+    });
 
-    let expanded = quote! {
+    Ok(quote! {
         impl #name {
             pub fn from_func(parsed: &Func) -> Result<Self, String> {
                 #(#parse_branches)*
-                Err(format!("Unknown {} variant: {}", stringify!(#name), parsed.func))
+                let hint = FuncSchema::suggest(Self::schema(), parsed.func)
+                    .map(|name| format!(" (did you mean {}?)", name))
+                    .unwrap_or_default();
+                Err(format!("Unknown {} variant: {}{}", stringify!(#name), parsed.func, hint))
             }
+
+            /// Doc comment of the variant `func` names, by name or alias
+            pub fn help(func: &str) -> Option<&'static str> {
+                #(#help_branches)*
+                None
+            }
+
+            /// Every function with its arguments
+            pub const fn schema() -> &'static [FuncSchema] {
+                const SCHEMA: &[FuncSchema] = &[#(#schemas),*];
+                SCHEMA
+            }
+        }
+
+        /// `FUNC(args)` with every argument positional
+        impl std::fmt::Display for #name {
+            fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+                match self {
+                    #(#display_arms)*
+                }
+            }
+        }
+    })
+}
+
+/// Errors of every variant at once rather than only the first one
+#[derive(Default)]
+struct Errors(Option<syn::Error>);
+
+impl Errors {
+    fn push(&mut self, err: syn::Error) {
+        match &mut self.0 {
+            Some(errors) => errors.combine(err),
+            None => self.0 = Some(err),
+        }
+    }
+
+    fn finish(self) -> syn::Result<()> {
+        self.0.map_or(Ok(()), Err)
+    }
+}
+
+struct ParsedVariant<'a> {
+    variant: &'a Variant,
+    names: VariantNames,
+    doc: Option<String>,
+}
+
+/// Variants of a non generic enum, with names that don't collide
+fn parse_variants<'a>(
+    input: &'a DeriveInput,
+    derive: &str,
+    attr_name: &str,
+    rename_key: &str,
+) -> syn::Result<Vec<ParsedVariant<'a>>> {
+    let variants = match &input.data {
+        Data::Enum(data) => &data.variants,
+        Data::Struct(data) => {
+            return Err(syn::Error::new_spanned(
+                data.struct_token,
+                format!("{} only works on enums", derive),
+            ));
+        }
+        Data::Union(data) => {
+            return Err(syn::Error::new_spanned(
+                data.union_token,
+                format!("{} only works on enums", derive),
+            ));
         }
     };
+    if !input.generics.params.is_empty() {
+        return Err(syn::Error::new_spanned(
+            &input.generics,
+            format!("{} doesn't support generic enums", derive),
+        ));
+    }
+
+    let mut errors = Errors::default();
+    let mut parsed: Vec<ParsedVariant> = Vec::new();
+    for variant in variants {
+        let names = match VariantNames::of(variant, attr_name, rename_key) {
+            Ok(names) => names,
+            Err(err) => {
+                errors.push(err);
+                continue;
+            }
+        };
+        for matched in names.matched() {
+            let taken = parsed
+                .iter()
+                .find(|other| other.names.matched().contains(&matched));
+            if let Some(other) = taken {
+                errors.push(syn::Error::new_spanned(
+                    &variant.ident,
+                    format!(
+                        "`{}` is already taken by `{}`, rename or alias one of them",
+                        matched, other.variant.ident
+                    ),
+                ));
+            }
+        }
+        parsed.push(ParsedVariant {
+            variant,
+            names,
+            doc: doc_attr(&variant.attrs),
+        });
+    }
+    errors.finish()?;
+    Ok(parsed)
+}
+
+struct FieldBinding {
+    /// Names accepted as `name=value`, empty for positional only fields
+    names: Vec<String>,
+    /// Expression reading each field out of `slots`
+    values: Vec<TokenStream2>,
+}
+
+/// Names a variant is matched by, `#[func(rename = "APPTOFRONT", alias = "front")]`
+/// or `#[scope(name = "ws", alias = "w")]`
+struct VariantNames {
+    /// Printed name, the variant itself unless renamed
+    name: String,
+    aliases: Vec<String>,
+}
 
-    TokenStream::from(expanded)
+impl VariantNames {
+    fn of(variant: &Variant, attr_name: &str, rename_key: &str) -> syn::Result<Self> {
+        let mut name = variant.ident.to_string();
+        if attr_name == "func" {
+            name = name.to_uppercase();
+        }
+        let mut aliases = Vec::new();
+        for attr in variant
+            .attrs
+            .iter()
+            .filter(|a| a.path().is_ident(attr_name))
+        {
+            attr.parse_nested_meta(|meta| {
+                if meta.path.is_ident(rename_key) {
+                    name = meta.value()?.parse::<LitStr>()?.value();
+                    Ok(())
+                } else if meta.path.is_ident("alias") {
+                    aliases.push(meta.value()?.parse::<LitStr>()?.value());
+                    Ok(())
+                } else {
+                    Err(meta.error(format!("expected `{}` or `alias`", rename_key)))
+                }
+            })?;
+        }
+        Ok(Self { name, aliases })
+    }
+
+    /// Lowercase name and aliases, every one of them parses
+    fn matched(&self) -> Vec<String> {
+        std::iter::once(&self.name)
+            .chain(&self.aliases)
+            .map(|name| name.to_lowercase())
+            .collect()
+    }
+}
+
+/// `///` lines joined, `None` when undocumented
+fn doc_attr(attrs: &[Attribute]) -> Option<String> {
+    let lines: Vec<String> = attrs
+        .iter()
+        .filter_map(|attr| match &attr.meta {
+            Meta::NameValue(meta) if meta.path.is_ident("doc") => match &meta.value {
+                Expr::Lit(ExprLit {
+                    lit: Lit::Str(lit), ..
+                }) => Some(lit.value().trim().to_string()),
+                _ => None,
+            },
+            _ => None,
+        })
+        .collect();
+    Some(lines.join("\n").trim().to_string()).filter(|doc| !doc.is_empty())
+}
+
+fn option_tokens(value: Option<String>) -> TokenStream2 {
+    match value {
+        Some(value) => quote! { Some(#value) },
+        None => quote! { None },
+    }
+}
+
+/// `Option<T>` fields may be left out
+fn is_option(ty: &Type) -> bool {
+    match ty {
+        Type::Path(path) => path.path.segments.last().is_some_and(|segment| {
+            segment.ident == "Option"
+                && matches!(segment.arguments, PathArguments::AngleBracketed(_))
+        }),
+        _ => false,
+    }
+}
+
+struct FuncField<'a> {
+    field: &'a Field,
+    /// `#[func(name = "dir")]`, else the field name
+    name: Option<String>,
+    /// `#[func(default = 80)]`
+    default: Option<Expr>,
+}
+
+/// `#[func(name = "dir", default = 80)]` on the fields of a variant
+fn func_fields(variant: &Variant) -> syn::Result<Vec<FuncField<'_>>> {
+    let mut errors = Errors::default();
+    let mut fields: Vec<FuncField> = Vec::new();
+    for field in &variant.fields {
+        if let Type::Reference(reference) = &field.ty {
+            errors.push(syn::Error::new_spanned(
+                reference,
+                "FuncParser fields are parsed into owned values, use `String` rather than `&str`",
+            ));
+        }
+        let mut name = field.ident.as_ref().map(|ident| ident.to_string());
+        let mut default = None;
+        for attr in field.attrs.iter().filter(|a| a.path().is_ident("func")) {
+            let parsed = attr.parse_nested_meta(|meta| {
+                if meta.path.is_ident("name") {
+                    name = Some(meta.value()?.parse::<LitStr>()?.value());
+                    Ok(())
+                } else if meta.path.is_ident("default") {
+                    default = Some(meta.value()?.parse::<Expr>()?);
+                    Ok(())
+                } else {
+                    Err(meta.error("expected `name` or `default`"))
+                }
+            });
+            if let Err(err) = parsed {
+                errors.push(err);
+            }
+        }
+        if let Some(name) = &name
+            && fields.iter().any(|other| other.name.as_ref() == Some(name))
+        {
+            errors.push(syn::Error::new_spanned(
+                field,
+                format!("argument `{}` is already taken", name),
+            ));
+        }
+        fields.push(FuncField {
+            field,
+            name,
+            default,
+        });
+    }
+    errors.finish()?;
+    Ok(fields)
+}
+
+/// `i32`, `Option<String>`, `(i32,i32)`
+fn type_str(ty: &Type) -> String {
+    quote!(#ty).to_string().replace(' ', "")
+}
+
+fn arg_schema(func_field: &FuncField) -> TokenStream2 {
+    let FuncField {
+        field,
+        name,
+        default,
+    } = func_field;
+    let ty = type_str(&field.ty);
+    let optional = default.is_some() || is_option(&field.ty);
+    let default = option_tokens(default.as_ref().map(|default| match default {
+        Expr::Lit(ExprLit {
+            lit: Lit::Str(lit), ..
+        }) => format!("'{}'", lit.value()),
+        expr => quote!(#expr).to_string().replace(' ', ""),
+    }));
+    let name = option_tokens(name.clone());
+    let doc = option_tokens(doc_attr(&field.attrs));
+    quote! {
+        ArgSchema {
+            name: #name,
+            ty: #ty,
+            default: #default,
+            optional: #optional,
+            doc: #doc,
+        }
+    }
+}
+
+fn bind_fields(variant_str: &str, fields: &[FuncField]) -> FieldBinding {
+    let mut names = Vec::new();
+    let mut values = Vec::new();
+    for (
+        i,
+        FuncField {
+            field,
+            name,
+            default,
+        },
+    ) in fields.iter().enumerate()
+    {
+        let field_type = &field.ty;
+        // `2 `by` (i32)`: position, name if any and the expected type
+        let type_str = type_str(field_type);
+        let label = match name {
+            Some(name) => format!("{} `{}` ({})", i + 1, name, type_str),
+            None => format!("{} ({})", i + 1, type_str),
+        };
+        let missing = match default {
+            Some(Expr::Lit(ExprLit {
+                lit: Lit::Str(lit), ..
+            })) => quote! { #lit.to_string() },
+            Some(expr) => quote! { #expr },
+            None if is_option(field_type) => quote! { None },
+            None => quote! {
+                return Err(format!("{} missing argument {}", #variant_str, #label))
+            },
+        };
+        values.push(quote! {
+            match slots[#i] {
+                Some(arg) => arg.parse_as::<#field_type>()
+                    .map_err(|e| format!("{} argument {}: {}", #variant_str, #label, e))?,
+                None => #missing,
+            }
+        });
+        names.push(name.clone().unwrap_or_default());
+    }
+    FieldBinding { names, values }
+}
+
+#[cfg(test)]
+mod expand {
+    use super::*;
+    use syn::parse_quote;
+
+    fn pretty(tokens: TokenStream2) -> String {
+        prettyplease::unparse(&syn::parse2(tokens).unwrap())
+    }
+
+    #[test]
+    fn func_parser() {
+        let input: DeriveInput = parse_quote! {
+            enum WindowFunc {
+                /// Move the focused window
+                Move {
+                    dir: String,
+                    #[func(default = 80)]
+                    by: i32,
+                    scale: Option<f32>,
+                },
+                Resize(#[func(name = "prop")] String, #[func(default = "WIDTH")] String),
+                #[func(rename = "CYCLE", alias = "next")]
+                CycleWorkspace,
+            }
+        };
+        insta::assert_snapshot!(pretty(super::func_parser(&input).unwrap()));
+    }
+
+    #[test]
+    fn scope_parser() {
+        let input: DeriveInput = parse_quote! {
+            enum FuncEntries {
+                /// tsck and Photoshop
+                App(AppFunc),
+                #[scope(name = "ws", alias = "workspace")]
+                Workspace(WorkspaceFunc),
+            }
+        };
+        insta::assert_snapshot!(pretty(super::scope_parser(&input).unwrap()));
+    }
 }
@@ -0,0 +1,30 @@
+use tsck_derive::{FuncParser, ScopeParser};
+use tsck_utils::{ArgSchema, Func, FuncExpr, FuncLexer, FuncSchema, ScopeSchema};
+
+#[derive(Debug, PartialEq, FuncParser)]
+enum WorkspaceFunc {
+    /// Switch to workspace N
+    Activate(i32),
+    MoveActiveWindow {
+        dir: String,
+        #[func(default = 80)]
+        by: i32,
+        scale: Option<f32>,
+    },
+    #[func(rename = "CYCLE", alias = "next")]
+    CycleWorkspace,
+    Place(String, (i32, i32), Vec<String>),
+}
+
+#[derive(Debug, ScopeParser)]
+enum Entries {
+    #[scope(name = "ws", alias = "workspace")]
+    Workspace(WorkspaceFunc),
+}
+
+fn main() {
+    let entries = Entries::parse("workspace::next").unwrap();
+    assert_eq!(entries.to_string(), "ws::CYCLE");
+    assert_eq!(Entries::help("ws::ACTIVATE"), Some("Switch to workspace N"));
+    assert_eq!(Entries::schema()[0].funcs.len(), 4);
+}
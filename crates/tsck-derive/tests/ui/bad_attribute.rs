@@ -0,0 +1,21 @@
+use tsck_derive::{FuncParser, ScopeParser};
+
+#[derive(FuncParser)]
+enum AppFunc {
+    #[func(name = "script")]
+    Script(String),
+}
+
+#[derive(FuncParser)]
+enum PageFunc {
+    Page(#[func(defualt = 1)] i32),
+    Title(#[func(name = 1)] String),
+}
+
+#[derive(ScopeParser)]
+enum Entries {
+    #[scope(rename = "a")]
+    App(AppFunc),
+}
+
+fn main() {}
@@ -0,0 +1,8 @@
+use tsck_derive::FuncParser;
+
+#[derive(FuncParser)]
+enum AppFunc {
+    Script(&'static str),
+}
+
+fn main() {}
@@ -0,0 +1,8 @@
+use tsck_derive::FuncParser;
+
+#[derive(FuncParser)]
+enum AppFunc<T> {
+    Script(T),
+}
+
+fn main() {}
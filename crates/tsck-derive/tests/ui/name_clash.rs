@@ -0,0 +1,27 @@
+use tsck_derive::{FuncParser, ScopeParser};
+
+#[derive(FuncParser)]
+enum AppFunc {
+    #[func(alias = "page")]
+    FocusPage(i32),
+    Page(i32),
+}
+
+#[derive(FuncParser)]
+enum WindowFunc {
+    Move {
+        dir: String,
+        #[func(name = "dir")]
+        direction: String,
+    },
+}
+
+#[derive(ScopeParser)]
+enum Entries {
+    #[scope(name = "ws")]
+    App(AppFunc),
+    #[scope(alias = "WS")]
+    Workspace(AppFunc),
+}
+
+fn main() {}
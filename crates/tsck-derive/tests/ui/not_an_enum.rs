@@ -0,0 +1,13 @@
+use tsck_derive::{FuncParser, ScopeParser};
+
+#[derive(FuncParser)]
+struct AppFunc {
+    script: String,
+}
+
+#[derive(ScopeParser)]
+union Entries {
+    app: u32,
+}
+
+fn main() {}
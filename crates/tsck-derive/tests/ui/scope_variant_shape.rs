@@ -0,0 +1,17 @@
+use tsck_derive::{FuncParser, ScopeParser};
+use tsck_utils::{Func, FuncExpr, FuncSchema};
+
+#[derive(FuncParser)]
+enum AppFunc {
+    ReloadConfig,
+}
+
+#[derive(ScopeParser)]
+enum Entries {
+    App(AppFunc),
+    Workspace,
+    Both(AppFunc, AppFunc),
+    Named { func: AppFunc },
+}
+
+fn main() {}
@@ -94,7 +94,7 @@ fn main() -> anyhow::Result<()> {
         }
         _ => {}
     })
-    .run(kees);
+    .run(kees)?;
 
     Ok(())
 }
@@ -0,0 +1,304 @@
+use std::str::FromStr;
+
+use anyhow::bail;
+use serde::Serialize;
+
+use crate::{
+    kee_sequence::TsckKeeSequence,
+    tokenizer::{
+        cst::{KeeDocument, KeeNode},
+        lexer::KeeParser,
+    },
+};
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct CheatEntry {
+    /// Human-readable keys, `Win+Shift+←`
+    pub keys: String,
+    /// Canonical kee.kee form, `M-S-left`
+    pub kee: String,
+    pub func: String,
+    /// App the binding is limited to, `M-d [PHOTOSHOP]`
+    pub app: Option<String>,
+    /// Trailing comment of the binding, or the comment lines right above it
+    pub description: Option<String>,
+    pub line: usize,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct CheatSection {
+    /// `[name]` the bindings are under
+    pub name: Option<String>,
+    /// `mode name { ... }` block the bindings are in
+    pub mode: Option<String>,
+    /// Comment right above the header, or trailing it
+    pub description: Option<String>,
+    pub entries: Vec<CheatEntry>,
+}
+
+impl CheatSection {
+    pub fn title(&self) -> String {
+        match (&self.name, &self.mode) {
+            (_, Some(mode)) => format!("mode {}", mode),
+            (Some(name), None) => name.clone(),
+            (None, None) => "general".into(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CheatSheetFormat {
+    Markdown,
+    Html,
+    Json,
+}
+
+impl FromStr for CheatSheetFormat {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "md" | "markdown" => Ok(CheatSheetFormat::Markdown),
+            "html" | "htm" => Ok(CheatSheetFormat::Html),
+            "json" => Ok(CheatSheetFormat::Json),
+            other => bail!(
+                "Unknown cheat sheet format `{}`, expected md, html or json",
+                other
+            ),
+        }
+    }
+}
+
+/// Bindings of kee.kee grouped by `[section]` and mode, with the comments
+/// around them as descriptions.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct CheatSheet {
+    pub sections: Vec<CheatSection>,
+}
+
+/// `# move it` -> `move it`
+fn comment_text(comment: &str) -> &str {
+    match comment.strip_prefix("//") {
+        Some(text) => text.trim(),
+        None => comment.trim_start_matches('#').trim(),
+    }
+}
+
+/// Trailing comment of line `index`, else the comment lines right above it
+fn describe(document: &KeeDocument, index: usize, trailing: Option<&str>) -> Option<String> {
+    if let Some(comment) = trailing {
+        return Some(comment_text(comment).to_string()).filter(|c| !c.is_empty());
+    }
+    let above: Vec<_> = document.lines[..index]
+        .iter()
+        .rev()
+        .map_while(|line| match line.node {
+            KeeNode::Comment(comment) => Some(comment_text(comment)),
+            _ => None,
+        })
+        .collect();
+    let text = above.into_iter().rev().collect::<Vec<_>>().join(" ");
+    Some(text).filter(|t| !t.trim().is_empty())
+}
+
+impl CheatSheet {
+    pub fn parse(source: &str) -> Self {
+        let document = KeeDocument::parse(source);
+        let mut sections: Vec<CheatSection> = Vec::new();
+        for kee in KeeParser::new(source).parse() {
+            let Ok(sequence) = TsckKeeSequence::parse(kee.kee) else {
+                continue;
+            };
+            let name = kee.section.map(str::to_string);
+            let mode = kee.mode.map(str::to_string);
+            let index = match sections
+                .iter()
+                .position(|s| s.name == name && s.mode == mode)
+            {
+                Some(index) => index,
+                None => {
+                    let description = Self::header_description(&document, &name, &mode);
+                    sections.push(CheatSection {
+                        name,
+                        mode,
+                        description,
+                        entries: Vec::new(),
+                    });
+                    sections.len() - 1
+                }
+            };
+            let comment = match document.lines.get(kee.line - 1).map(|line| &line.node) {
+                Some(KeeNode::Binding { comment, .. }) => *comment,
+                _ => None,
+            };
+            sections[index].entries.push(CheatEntry {
+                keys: sequence.label(),
+                kee: sequence.to_string(),
+                func: kee.func.to_string(),
+                app: sequence.app,
+                description: describe(&document, kee.line - 1, comment),
+                line: kee.line,
+            });
+        }
+        Self { sections }
+    }
+
+    /// Description of the first `[name]` header, or of the `mode name {` line
+    fn header_description(
+        document: &KeeDocument,
+        name: &Option<String>,
+        mode: &Option<String>,
+    ) -> Option<String> {
+        document
+            .lines
+            .iter()
+            .enumerate()
+            .find_map(|(index, line)| match (&line.node, mode) {
+                (KeeNode::Mode { name, comment }, Some(mode)) if name == mode => {
+                    Some(describe(document, index, *comment))
+                }
+                (KeeNode::Section(section), None) if name.as_deref() == Some(*section) => {
+                    Some(describe(document, index, None))
+                }
+                _ => None,
+            })
+            .flatten()
+    }
+
+    pub fn render(&self, format: CheatSheetFormat) -> String {
+        match format {
+            CheatSheetFormat::Markdown => self.to_markdown(),
+            CheatSheetFormat::Html => self.to_html(),
+            CheatSheetFormat::Json => self.to_json(),
+        }
+    }
+
+    pub fn to_markdown(&self) -> String {
+        let cell = |text: &str| text.replace('|', "\\|");
+        let mut out = String::from("# Hotkeys\n");
+        for section in &self.sections {
+            out.push_str(&format!("\n## {}\n\n", section.title()));
+            if let Some(description) = &section.description {
+                out.push_str(&format!("{}\n\n", description));
+            }
+            out.push_str("| Keys | Function | App | Description |\n");
+            out.push_str("| --- | --- | --- | --- |\n");
+            for entry in &section.entries {
+                out.push_str(&format!(
+                    "| {} | {} | {} | {} |\n",
+                    cell(&entry.keys),
+                    cell(&entry.func),
+                    cell(entry.app.as_deref().unwrap_or_default()),
+                    cell(entry.description.as_deref().unwrap_or_default()),
+                ));
+            }
+        }
+        out
+    }
+
+    /// Standalone page, laid out for printing
+    pub fn to_html(&self) -> String {
+        let escape = |text: &str| {
+            text.replace('&', "&amp;")
+                .replace('<', "&lt;")
+                .replace('>', "&gt;")
+                .replace('"', "&quot;")
+        };
+        let mut out = String::from(
+            "<!DOCTYPE html>\n<html>\n<head>\n<meta charset=\"utf-8\">\n<title>Hotkeys</title>\n<style>\nbody { font-family: system-ui, sans-serif; font-size: 12px; margin: 2em; }\nsection { break-inside: avoid; margin-bottom: 1.5em; }\ntable { border-collapse: collapse; width: 100%; }\nth, td { border-bottom: 1px solid #ddd; padding: 4px 8px; text-align: left; }\nkbd { border: 1px solid #999; border-radius: 3px; padding: 0 4px; white-space: nowrap; }\ncode { color: #555; }\n</style>\n</head>\n<body>\n<h1>Hotkeys</h1>\n",
+        );
+        for section in &self.sections {
+            out.push_str(&format!(
+                "<section>\n<h2>{}</h2>\n",
+                escape(&section.title())
+            ));
+            if let Some(description) = &section.description {
+                out.push_str(&format!("<p>{}</p>\n", escape(description)));
+            }
+            out.push_str(
+                "<table>\n<tr><th>Keys</th><th>Function</th><th>App</th><th>Description</th></tr>\n",
+            );
+            for entry in &section.entries {
+                out.push_str(&format!(
+                    "<tr><td><kbd>{}</kbd></td><td><code>{}</code></td><td>{}</td><td>{}</td></tr>\n",
+                    escape(&entry.keys),
+                    escape(&entry.func),
+                    escape(entry.app.as_deref().unwrap_or_default()),
+                    escape(entry.description.as_deref().unwrap_or_default()),
+                ));
+            }
+            out.push_str("</table>\n</section>\n");
+        }
+        out.push_str("</body>\n</html>\n");
+        out
+    }
+
+    pub fn to_json(&self) -> String {
+        serde_json::to_string_pretty(self).unwrap_or_default()
+    }
+}
+
+#[cfg(test)]
+mod cheat_sheet {
+    use super::*;
+
+    const INPUT: &str = "# kee.kee header\n\n# window placement\n[workspace]\nM-S-left = workspace::MOVEACTIVEWINDOW('LEFT') # nudge left\n# resize with hjkl\nM-A-r = mode(resize)\nmode resize { # i3 like\n    h = workspace::RESIZEACTIVEWINDOW('DEC', 'WIDTH')\n}\n[photoshop]\nM-d [PHOTOSHOP] = app::SCRIPT('drop|shadow.js')\n";
+
+    #[test]
+    fn test_parse() {
+        let sheet = CheatSheet::parse(INPUT);
+        let sections: Vec<_> = sheet
+            .sections
+            .iter()
+            .map(|s| (s.title(), s.description.as_deref(), s.entries.len()))
+            .collect();
+        assert_eq!(
+            sections,
+            vec![
+                ("workspace".into(), Some("window placement"), 2),
+                ("mode resize".into(), Some("i3 like"), 1),
+                ("photoshop".into(), None, 1),
+            ]
+        );
+        let entry = &sheet.sections[0].entries[0];
+        assert_eq!(entry.keys, "Win+Shift+←");
+        assert_eq!(entry.kee, "M-S-left");
+        assert_eq!(entry.description.as_deref(), Some("nudge left"));
+        assert_eq!(
+            sheet.sections[0].entries[1].description.as_deref(),
+            Some("resize with hjkl")
+        );
+        assert_eq!(sheet.sections[1].entries[0].keys, "H");
+        assert_eq!(
+            sheet.sections[2].entries[0].app.as_deref(),
+            Some("PHOTOSHOP")
+        );
+    }
+
+    #[test]
+    fn test_render() {
+        let sheet = CheatSheet::parse(INPUT);
+        let markdown = sheet.to_markdown();
+        assert!(markdown.starts_with("# Hotkeys\n\n## workspace\n\nwindow placement\n\n"));
+        assert!(
+            markdown.contains(
+                "| Win+Shift+← | workspace::MOVEACTIVEWINDOW('LEFT') |  | nudge left |\n"
+            )
+        );
+        assert!(markdown.contains("| Win+D | app::SCRIPT('drop\\|shadow.js') | PHOTOSHOP |  |\n"));
+
+        let html = sheet.to_html();
+        assert!(html.contains("<h2>mode resize</h2>\n<p>i3 like</p>"));
+        assert!(html.contains("<td><kbd>Win+Alt+R</kbd></td>"));
+
+        let json: serde_json::Value = serde_json::from_str(&sheet.to_json()).unwrap();
+        assert_eq!(json["sections"][1]["mode"], "resize");
+        assert_eq!(json["sections"][0]["entries"][0]["keys"], "Win+Shift+←");
+
+        assert_eq!(
+            "markdown".parse::<CheatSheetFormat>().ok(),
+            Some(CheatSheetFormat::Markdown)
+        );
+        assert!("pdf".parse::<CheatSheetFormat>().is_err());
+    }
+}
@@ -0,0 +1,254 @@
+use std::collections::HashMap;
+
+use crate::{
+    kee_sequence::{KeeStroke, TsckKeeSequence},
+    kee_trigger::KeeTrigger,
+    tokenizer::{
+        diagnostic::{KeeDiagnostic, Span},
+        lexer::KeeFunc,
+    },
+};
+
+/// Combos Windows handles itself, or that users rely on everywhere.
+const RESERVED: &[(&str, &str)] = &[
+    ("M-l", "locks the workstation and never reaches the hook"),
+    (
+        "C-A-delete",
+        "opens the security screen and never reaches the hook",
+    ),
+    ("C-S-escape", "opens the Task Manager"),
+    ("C-escape", "opens the Start menu"),
+    ("A-tab", "switches windows"),
+    ("A-f4", "closes the active window"),
+    ("M-tab", "opens Task View"),
+];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum KeeConflictKind {
+    /// Same keys and app as an earlier binding, the later one wins
+    Duplicate,
+    /// A shorter binding fires before this sequence can complete
+    Shadowed,
+    /// Collides with an OS shortcut
+    Reserved,
+}
+
+impl std::fmt::Display for KeeConflictKind {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(match self {
+            KeeConflictKind::Duplicate => "duplicate",
+            KeeConflictKind::Shadowed => "shadowed",
+            KeeConflictKind::Reserved => "reserved",
+        })
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct KeeConflict {
+    pub kind: KeeConflictKind,
+    /// Canonical form of the binding, `S-M-a` -> `M-S-a`
+    pub kee: String,
+    pub line: usize,
+    pub span: Span,
+    /// Canonical form and line of the binding it collides with
+    pub other: Option<(String, usize)>,
+    pub message: String,
+}
+
+impl KeeConflict {
+    pub fn to_diagnostic(&self, source: &str) -> KeeDiagnostic {
+        KeeDiagnostic::warning(source, self.span, self.message.clone())
+    }
+}
+
+struct Entry {
+    sequence: TsckKeeSequence,
+    strokes: Vec<KeeStroke>,
+    /// Keys of a mode block only collide within that mode
+    mode: Option<String>,
+    line: usize,
+    span: Span,
+}
+
+impl Entry {
+    fn app(&self) -> Option<String> {
+        self.sequence.app.as_ref().map(|app| app.to_lowercase())
+    }
+}
+
+/// Duplicate, shadowed and OS-reserved bindings. Bindings that don't parse
+/// are left to `KeeParser::check`.
+pub fn find_conflicts(kees: &[KeeFunc]) -> Vec<KeeConflict> {
+    let reserved: Vec<_> = RESERVED
+        .iter()
+        .filter_map(|(kee, reason)| {
+            let sequence = TsckKeeSequence::parse(kee).ok()?;
+            Some((sequence.to_strokes()[0], sequence.to_string(), *reason))
+        })
+        .collect();
+    let entries: Vec<_> = kees
+        .iter()
+        .filter_map(|k| {
+            let sequence = TsckKeeSequence::parse(k.kee).ok()?;
+            Some(Entry {
+                strokes: sequence.to_strokes(),
+                sequence,
+                mode: k.mode.map(str::to_string),
+                line: k.line,
+                span: k.kee_span,
+            })
+        })
+        .collect();
+
+    let mut conflicts = Vec::new();
+    type Slot<'e> = (&'e [KeeStroke], Option<String>, KeeTrigger, Option<&'e str>);
+    let mut seen: HashMap<Slot, &Entry> = HashMap::new();
+    for entry in &entries {
+        let kee = entry.sequence.to_string();
+        let conflict = |kind, other: Option<&Entry>, message| KeeConflict {
+            kind,
+            kee: kee.clone(),
+            line: entry.line,
+            span: entry.span,
+            other: other.map(|o| (o.sequence.to_string(), o.line)),
+            message,
+        };
+
+        let slot = (
+            entry.strokes.as_slice(),
+            entry.app(),
+            entry.sequence.trigger,
+            entry.mode.as_deref(),
+        );
+        if let Some(earlier) = seen.insert(slot, entry) {
+            conflicts.push(conflict(
+                KeeConflictKind::Duplicate,
+                Some(earlier),
+                format!(
+                    "`{}` is already bound on line {}, this binding replaces it",
+                    kee, earlier.line
+                ),
+            ));
+        }
+
+        // the prefix only wins when it's usable wherever this binding is
+        if let Some(prefix) = entries.iter().find(|other| {
+            other.strokes.len() < entry.strokes.len()
+                && entry.strokes.starts_with(&other.strokes)
+                && other.mode == entry.mode
+                && (other.app().is_none() || other.app() == entry.app())
+        }) {
+            conflicts.push(conflict(
+                KeeConflictKind::Shadowed,
+                Some(prefix),
+                format!(
+                    "`{}` can never complete, `{}` on line {} fires first",
+                    kee, prefix.sequence, prefix.line
+                ),
+            ));
+        }
+
+        if entry.mode.is_some() {
+            continue;
+        }
+        for (name, reason) in entry.strokes.iter().filter_map(|stroke| {
+            reserved
+                .iter()
+                .find(|(reserved, ..)| reserved == stroke)
+                .map(|(_, name, reason)| (name, reason))
+        }) {
+            conflicts.push(conflict(
+                KeeConflictKind::Reserved,
+                None,
+                format!("`{}` is reserved by Windows, it {}", name, reason),
+            ));
+        }
+    }
+    conflicts
+}
+
+#[cfg(test)]
+mod binding_conflicts {
+    use super::*;
+    use crate::tokenizer::lexer::KeeParser;
+
+    type Row = (KeeConflictKind, String, usize, Option<(String, usize)>);
+
+    fn conflicts(input: &str) -> Vec<Row> {
+        find_conflicts(&KeeParser::new(input).parse())
+            .into_iter()
+            .map(|c| (c.kind, c.kee, c.line, c.other))
+            .collect()
+    }
+
+    #[test]
+    fn test_duplicates() {
+        let input = "M-S-a = app::PAGE(1)\nM-b = app::PAGE(2)\nS-M-a = app::PAGE(3)\nM-b [PHOTOSHOP] = app::PAGE(4)\nW-b = app::PAGE(5)\nM-b:double = app::PAGE(6)\n";
+        assert_eq!(
+            conflicts(input),
+            vec![
+                (
+                    KeeConflictKind::Duplicate,
+                    "M-S-a".into(),
+                    3,
+                    Some(("M-S-a".into(), 1))
+                ),
+                (
+                    KeeConflictKind::Duplicate,
+                    "M-b".into(),
+                    5,
+                    Some(("M-b".into(), 2))
+                ),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_shadowed() {
+        let input = "M-k = app::PAGE(1)\nM-k c = app::PAGE(2)\nM-j [PHOTOSHOP] = app::PAGE(3)\nM-j c = app::PAGE(4)\nM-j d [photoshop] = app::PAGE(5)\n";
+        assert_eq!(
+            conflicts(input),
+            vec![
+                (
+                    KeeConflictKind::Shadowed,
+                    "M-k c".into(),
+                    2,
+                    Some(("M-k".into(), 1))
+                ),
+                (
+                    KeeConflictKind::Shadowed,
+                    "M-j d [photoshop]".into(),
+                    5,
+                    Some(("M-j [PHOTOSHOP]".into(), 3))
+                ),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_modes() {
+        let input = "h = app::PAGE(1)\nmode resize {\nh = app::PAGE(2)\nl = app::PAGE(3)\nh = app::PAGE(4)\n}\nmode move {\nh = app::PAGE(5)\n}\n";
+        assert_eq!(
+            conflicts(input),
+            vec![(
+                KeeConflictKind::Duplicate,
+                "h".into(),
+                5,
+                Some(("h".into(), 3))
+            )]
+        );
+    }
+
+    #[test]
+    fn test_reserved() {
+        let input = "A-C-del = app::PAGE(1)\nM-l = app::PAGE(2)\nM-S-l = app::PAGE(3)\n";
+        let found = find_conflicts(&KeeParser::new(input).parse());
+        assert_eq!(found.len(), 2);
+        assert_eq!(found[0].kind, KeeConflictKind::Reserved);
+        assert_eq!(found[0].kee, "C-A-delete");
+        assert_eq!(
+            found[1].to_diagnostic(input).render("kee.kee", input),
+            "warning: `M-l` is reserved by Windows, it locks the workstation and never reaches the hook\n --> kee.kee:2:1\n  |\n2 | M-l = app::PAGE(2)\n  | ^^^"
+        );
+    }
+}
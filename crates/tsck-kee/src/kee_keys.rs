@@ -7,12 +7,28 @@ use serde::{Deserialize, Deserializer, Serialize, Serializer};
 pub struct TKeePair {
     pub key: String,
     pub func: String,
+    /// Mode block the key belongs to, only active inside `mode(name)`
+    pub mode: Option<String>,
 }
 impl TKeePair {
     pub fn new(key: impl Into<String>, func: impl Into<String>) -> TKeePair {
         TKeePair {
             key: key.into(),
             func: func.into(),
+            mode: None,
+        }
+    }
+
+    pub fn in_mode(mut self, mode: Option<impl Into<String>>) -> TKeePair {
+        self.mode = mode.map(Into::into);
+        self
+    }
+
+    /// Name the hook reports the binding by: `M-1`, or `resize::h` in a mode
+    pub fn id(&self) -> String {
+        match &self.mode {
+            Some(mode) => format!("{}::{}", mode, self.key),
+            None => self.key.clone(),
         }
     }
 }
@@ -27,12 +43,15 @@ impl<'de> Deserialize<'de> for TKeePair {
         struct TKeePairHelper {
             key: String,
             func: String,
+            #[serde(default)]
+            mode: Option<String>,
         }
 
         let helper = TKeePairHelper::deserialize(deserializer)?;
         Ok(TKeePair {
             key: helper.key,
             func: helper.func,
+            mode: helper.mode,
         })
     }
 }
@@ -46,11 +65,14 @@ impl Serialize for TKeePair {
         struct TKeePairHelper<'a> {
             key: &'a str,
             func: &'a str,
+            #[serde(skip_serializing_if = "Option::is_none")]
+            mode: Option<&'a str>,
         }
 
         TKeePairHelper {
             key: &self.key,
             func: &self.func,
+            mode: self.mode.as_deref(),
         }
         .serialize(serializer)
     }
@@ -67,7 +89,7 @@ impl<'de> Deserialize<'de> for TKeePairList {
         let map = HashMap::<String, String>::deserialize(deserializer)?;
         let pairs: Vec<TKeePair> = map
             .into_iter()
-            .map(|(key, func)| TKeePair { key, func })
+            .map(|(key, func)| TKeePair::new(key, func))
             .collect();
 
         Ok(TKeePairList(pairs))
@@ -231,7 +253,219 @@ pub enum TKey {
     Function,
 }
 
-#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+impl TKey {
+    /// Canonical kee.kee spelling, the first name `TsckKeeBinding::parse` accepts
+    pub fn name(&self) -> &'static str {
+        match self {
+            // Letters
+            TKey::A => "a",
+            TKey::B => "b",
+            TKey::C => "c",
+            TKey::D => "d",
+            TKey::E => "e",
+            TKey::F => "f",
+            TKey::G => "g",
+            TKey::H => "h",
+            TKey::I => "i",
+            TKey::J => "j",
+            TKey::K => "k",
+            TKey::L => "l",
+            TKey::M => "m",
+            TKey::N => "n",
+            TKey::O => "o",
+            TKey::P => "p",
+            TKey::Q => "q",
+            TKey::R => "r",
+            TKey::S => "s",
+            TKey::T => "t",
+            TKey::U => "u",
+            TKey::V => "v",
+            TKey::W => "w",
+            TKey::X => "x",
+            TKey::Y => "y",
+            TKey::Z => "z",
+            // Numbers
+            TKey::Num0 => "0",
+            TKey::Num1 => "1",
+            TKey::Num2 => "2",
+            TKey::Num3 => "3",
+            TKey::Num4 => "4",
+            TKey::Num5 => "5",
+            TKey::Num6 => "6",
+            TKey::Num7 => "7",
+            TKey::Num8 => "8",
+            TKey::Num9 => "9",
+            // Numpad
+            TKey::Kp0 => "kp0",
+            TKey::Kp1 => "kp1",
+            TKey::Kp2 => "kp2",
+            TKey::Kp3 => "kp3",
+            TKey::Kp4 => "kp4",
+            TKey::Kp5 => "kp5",
+            TKey::Kp6 => "kp6",
+            TKey::Kp7 => "kp7",
+            TKey::Kp8 => "kp8",
+            TKey::Kp9 => "kp9",
+            TKey::KpReturn => "kpreturn",
+            TKey::KpMinus => "kpminus",
+            TKey::KpPlus => "kpplus",
+            TKey::KpMultiply => "kpmultiply",
+            TKey::KpDivide => "kpdivide",
+            TKey::KpDelete => "kpdelete",
+            // Function keys
+            TKey::F1 => "f1",
+            TKey::F2 => "f2",
+            TKey::F3 => "f3",
+            TKey::F4 => "f4",
+            TKey::F5 => "f5",
+            TKey::F6 => "f6",
+            TKey::F7 => "f7",
+            TKey::F8 => "f8",
+            TKey::F9 => "f9",
+            TKey::F10 => "f10",
+            TKey::F11 => "f11",
+            TKey::F12 => "f12",
+            TKey::F13 => "f13",
+            TKey::F14 => "f14",
+            TKey::F15 => "f15",
+            TKey::F16 => "f16",
+            TKey::F17 => "f17",
+            TKey::F18 => "f18",
+            TKey::F19 => "f19",
+            TKey::F20 => "f20",
+            TKey::F21 => "f21",
+            TKey::F22 => "f22",
+            TKey::F23 => "f23",
+            TKey::F24 => "f24",
+            // Modifiers
+            TKey::Alt => "alt",
+            TKey::AltGr => "altgr",
+            TKey::ControlLeft => "ctrl",
+            TKey::ControlRight => "ctrlright",
+            TKey::ShiftLeft => "shift",
+            TKey::ShiftRight => "shiftright",
+            TKey::MetaLeft => "meta",
+            TKey::MetaRight => "metaright",
+            // Special
+            TKey::Return => "return",
+            TKey::Space => "space",
+            TKey::Escape => "escape",
+            TKey::Tab => "tab",
+            TKey::Backspace => "backspace",
+            TKey::Delete => "delete",
+            TKey::Insert => "insert",
+            // Arrows
+            TKey::UpArrow => "up",
+            TKey::DownArrow => "down",
+            TKey::LeftArrow => "left",
+            TKey::RightArrow => "right",
+            // Navigation
+            TKey::Home => "home",
+            TKey::End => "end",
+            TKey::PageUp => "pageup",
+            TKey::PageDown => "pagedown",
+            // Locks
+            TKey::CapsLock => "capslock",
+            TKey::NumLock => "numlock",
+            TKey::ScrollLock => "scrolllock",
+            TKey::Pause => "pause",
+            TKey::PrintScreen => "printscreen",
+            // Symbols
+            TKey::BackQuote => "backquote",
+            TKey::Minus => "minus",
+            TKey::Equal => "equal",
+            TKey::LeftBracket => "[",
+            TKey::RightBracket => "]",
+            TKey::BackSlash => "\\",
+            TKey::IntlBackslash => "intlbackslash",
+            TKey::SemiColon => ";",
+            TKey::Quote => "'",
+            TKey::Comma => ",",
+            TKey::Dot => ".",
+            TKey::Slash => "/",
+            // Media
+            TKey::VolumeUp => "volumeup",
+            TKey::VolumeDown => "volumedown",
+            TKey::VolumeMute => "volumemute",
+            TKey::BrightnessUp => "brightnessup",
+            TKey::BrightnessDown => "brightnessdown",
+            TKey::PreviousTrack => "previoustrack",
+            TKey::PlayPause => "playpause",
+            TKey::PlayCd => "playcd",
+            TKey::NextTrack => "nexttrack",
+            TKey::Function => "function",
+        }
+    }
+
+    /// Human-readable name for cheat sheets: `A`, `F5`, `←`, `PgUp`
+    pub fn label(&self) -> String {
+        let label = match self {
+            TKey::Kp0 => "Num 0",
+            TKey::Kp1 => "Num 1",
+            TKey::Kp2 => "Num 2",
+            TKey::Kp3 => "Num 3",
+            TKey::Kp4 => "Num 4",
+            TKey::Kp5 => "Num 5",
+            TKey::Kp6 => "Num 6",
+            TKey::Kp7 => "Num 7",
+            TKey::Kp8 => "Num 8",
+            TKey::Kp9 => "Num 9",
+            TKey::KpReturn => "Num Enter",
+            TKey::KpMinus => "Num -",
+            TKey::KpPlus => "Num +",
+            TKey::KpMultiply => "Num *",
+            TKey::KpDivide => "Num /",
+            TKey::KpDelete => "Num Del",
+            TKey::Alt => "Alt",
+            TKey::AltGr => "AltGr",
+            TKey::ControlLeft => "Ctrl",
+            TKey::ControlRight => "Right Ctrl",
+            TKey::ShiftLeft => "Shift",
+            TKey::ShiftRight => "Right Shift",
+            TKey::MetaLeft => "Win",
+            TKey::MetaRight => "Right Win",
+            TKey::Return => "Enter",
+            TKey::Space => "Space",
+            TKey::Escape => "Esc",
+            TKey::Tab => "Tab",
+            TKey::Backspace => "Backspace",
+            TKey::Delete => "Del",
+            TKey::Insert => "Ins",
+            TKey::UpArrow => "↑",
+            TKey::DownArrow => "↓",
+            TKey::LeftArrow => "←",
+            TKey::RightArrow => "→",
+            TKey::Home => "Home",
+            TKey::End => "End",
+            TKey::PageUp => "PgUp",
+            TKey::PageDown => "PgDn",
+            TKey::CapsLock => "Caps Lock",
+            TKey::NumLock => "Num Lock",
+            TKey::ScrollLock => "Scroll Lock",
+            TKey::Pause => "Pause",
+            TKey::PrintScreen => "PrtSc",
+            TKey::BackQuote => "`",
+            TKey::Minus => "-",
+            TKey::Equal => "=",
+            TKey::IntlBackslash => "\\",
+            TKey::VolumeUp => "Volume Up",
+            TKey::VolumeDown => "Volume Down",
+            TKey::VolumeMute => "Mute",
+            TKey::BrightnessUp => "Brightness Up",
+            TKey::BrightnessDown => "Brightness Down",
+            TKey::PreviousTrack => "Previous Track",
+            TKey::PlayPause => "Play/Pause",
+            TKey::PlayCd => "Play",
+            TKey::NextTrack => "Next Track",
+            TKey::Function => "Fn",
+            // letters, digits, function keys and the remaining symbols read as written
+            other => return other.name().to_uppercase(),
+        };
+        label.to_string()
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
 pub struct Modifiers {
     pub ctrl: bool,
     pub shift: bool,
@@ -257,9 +491,40 @@ impl Modifiers {
             + (self.alt as u32) * 0x0001
             + (self.meta as u32) * 0x0008
     }
+
+    /// `Win+Shift+`, same order as `Display`
+    pub fn label(&self) -> String {
+        [
+            (self.meta, "Win+"),
+            (self.shift, "Shift+"),
+            (self.ctrl, "Ctrl+"),
+            (self.alt, "Alt+"),
+        ]
+        .iter()
+        .filter(|(on, _)| *on)
+        .map(|(_, name)| *name)
+        .collect()
+    }
+}
+
+/// Modifiers in the fixed `M-S-C-A` order of the kee.kee legend
+impl std::fmt::Display for Modifiers {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        for (on, name) in [
+            (self.meta, "M-"),
+            (self.shift, "S-"),
+            (self.ctrl, "C-"),
+            (self.alt, "A-"),
+        ] {
+            if on {
+                f.write_str(name)?;
+            }
+        }
+        Ok(())
+    }
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
 pub struct TsckKeeBinding {
     pub key: TKey,
     pub modifiers: Modifiers,
@@ -295,6 +560,11 @@ impl TsckKeeBinding {
         Ok(Self { key, modifiers })
     }
 
+    /// `M-S-left` -> `Win+Shift+←`
+    pub fn label(&self) -> String {
+        format!("{}{}", self.modifiers.label(), self.key.label())
+    }
+
     #[inline]
     fn parse_key(key_str: &str) -> anyhow::Result<TKey> {
         match key_str.as_bytes() {
@@ -571,3 +841,10 @@ impl TsckKeeBinding {
         }
     }
 }
+
+/// Canonical form: `S-M-a` and `W-S-a` both print as `M-S-a`
+impl std::fmt::Display for TsckKeeBinding {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}{}", self.modifiers, self.key.name())
+    }
+}
@@ -1,11 +1,15 @@
 use crate::{
-    kee_keys::TsckKeeBinding,
-    kee_windows::{WindowInfo, spawn_active_window_listener},
+    kee_mode::{DEFAULT_MODE_TIMEOUT, ModeMatcher, ModeStep},
+    kee_sequence::{
+        DEFAULT_SEQUENCE_TIMEOUT, KeeStroke, SequenceMatcher, SequenceStep, TsckKeeSequence,
+    },
+    kee_trigger::{DEFAULT_DOUBLE_TAP, TriggerMatcher},
+    kee_windows::{WindowInfo, get_current_active_window, spawn_active_window_listener},
 };
 use parking_lot::Mutex;
 use std::{
-    collections::HashMap,
     sync::{Arc, OnceLock},
+    time::{Duration, Instant},
 };
 use windows::Win32::{
     Foundation::{LPARAM, LRESULT, WPARAM},
@@ -20,6 +24,8 @@ pub enum KeeEvent {
     OnKey(String),
     OnModifier(Modifier, bool),
     OnWindowChange(WindowInfo),
+    /// Entered a mode, or left it with `None`
+    OnModeChange(Option<String>),
 }
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
@@ -33,13 +39,54 @@ pub enum Modifier {
 type KeeEventCallback = std::sync::Arc<dyn Fn(KeeEvent) + Send + Sync>;
 
 struct HotkeyState {
-    hotkey_names: HashMap<(u16, u32), String>,
+    matcher: SequenceMatcher,
+    triggers: TriggerMatcher,
+    modes: ModeMatcher,
     event_callbacks: Vec<KeeEventCallback>,
     key_states: [bool; 256],
 }
 
 static KEE_STATE: OnceLock<Mutex<HotkeyState>> = OnceLock::new();
 pub(crate) static CALLBACK_CHANNEL: OnceLock<flume::Sender<KeeEvent>> = OnceLock::new();
+/// Deadlines of hold / double-tap bindings and mode timeouts for the ticker thread
+static TICK_CHANNEL: OnceLock<flume::Sender<Instant>> = OnceLock::new();
+
+fn send_keys(names: Vec<String>) {
+    if let Some(tx) = CALLBACK_CHANNEL.get() {
+        for name in names {
+            let _ = tx.try_send(KeeEvent::OnKey(name));
+        }
+    }
+}
+
+fn send_mode(mode: Option<String>) {
+    if let Some(tx) = CALLBACK_CHANNEL.get() {
+        let _ = tx.try_send(KeeEvent::OnModeChange(mode));
+    }
+}
+
+/// What a `mode(name)` step does, false when no such mode is registered
+pub(crate) fn enter_mode(mode: &str) -> bool {
+    let Some(state) = KEE_STATE.get() else {
+        return false;
+    };
+    let deadline = {
+        let mut hotkey_state = state.lock();
+        if !hotkey_state.modes.enter(mode, Instant::now()) {
+            return false;
+        }
+        hotkey_state.modes.deadline()
+    };
+    schedule_tick(deadline);
+    send_mode(Some(mode.to_string()));
+    true
+}
+
+fn schedule_tick(deadline: Option<Instant>) {
+    if let (Some(deadline), Some(tx)) = (deadline, TICK_CHANNEL.get()) {
+        let _ = tx.try_send(deadline);
+    }
+}
 
 #[derive(Debug)]
 pub struct TsckKeeManager;
@@ -48,7 +95,9 @@ impl TsckKeeManager {
     pub fn new() -> Self {
         KEE_STATE.get_or_init(|| {
             Mutex::new(HotkeyState {
-                hotkey_names: HashMap::new(),
+                matcher: SequenceMatcher::new(DEFAULT_SEQUENCE_TIMEOUT),
+                triggers: TriggerMatcher::new(DEFAULT_DOUBLE_TAP),
+                modes: ModeMatcher::new(DEFAULT_MODE_TIMEOUT),
                 event_callbacks: Vec::new(),
                 key_states: [false; 256],
             })
@@ -63,7 +112,12 @@ impl TsckKeeManager {
                 while let Ok(event) = rx.recv() {
                     let state = KEE_STATE.get().expect("HOTKEY_STATE initialized");
                     let event_callbacks = {
-                        let hotkey_state = state.lock();
+                        let mut hotkey_state = state.lock();
+                        if let KeeEvent::OnWindowChange(info) = &event {
+                            hotkey_state
+                                .matcher
+                                .set_active_app(&info.name(), info.raw_title());
+                        }
                         hotkey_state.event_callbacks.clone()
                     };
 
@@ -73,6 +127,46 @@ impl TsckKeeManager {
                 }
             });
 
+        let (tick_tx, tick_rx) = flume::unbounded::<Instant>();
+        TICK_CHANNEL.get_or_init(|| tick_tx);
+
+        let _ = std::thread::Builder::new()
+            .name("hotkey-trigger-ticker".to_string())
+            .spawn(move || {
+                let mut next: Option<Instant> = None;
+                loop {
+                    let received = match next {
+                        Some(deadline) => tick_rx.recv_deadline(deadline),
+                        None => tick_rx
+                            .recv()
+                            .map_err(|_| flume::RecvTimeoutError::Disconnected),
+                    };
+                    match received {
+                        Ok(deadline) => next = Some(next.map_or(deadline, |n| n.min(deadline))),
+                        Err(flume::RecvTimeoutError::Timeout) => {
+                            let state = KEE_STATE.get().expect("HOTKEY_STATE initialized");
+                            let now = Instant::now();
+                            let (fired, left, deadline) = {
+                                let mut hotkey_state = state.lock();
+                                let fired = hotkey_state.triggers.tick(now);
+                                let left = hotkey_state.modes.tick(now);
+                                let deadline = [
+                                    hotkey_state.triggers.deadline(),
+                                    hotkey_state.modes.deadline(),
+                                ];
+                                (fired, left, deadline.into_iter().flatten().min())
+                            };
+                            send_keys(fired);
+                            if left.is_some() {
+                                send_mode(None);
+                            }
+                            next = deadline;
+                        }
+                        Err(flume::RecvTimeoutError::Disconnected) => break,
+                    }
+                }
+            });
+
         let _ = std::thread::Builder::new()
             .name("hotkey-hook".to_string())
             .spawn(|| unsafe {
@@ -102,14 +196,9 @@ impl TsckKeeManager {
     where
         M: Fn(KeeEvent) + Send + Sync + 'static,
     {
-        let bindings: Vec<(u16, u32, String)> = hotkeys
+        let bindings: Vec<(TsckKeeSequence, String)> = hotkeys
             .iter()
-            .map(|hotkey_str| {
-                let binding = TsckKeeBinding::parse(hotkey_str)?;
-                let tk = binding.to_tk();
-                let flags = binding.modifiers.to_flags();
-                Ok((tk, flags, hotkey_str.to_string()))
-            })
+            .map(|hotkey_str| Ok((TsckKeeSequence::parse(hotkey_str)?, hotkey_str.to_string())))
             .collect::<anyhow::Result<Vec<_>>>()?;
 
         let state = KEE_STATE
@@ -118,14 +207,19 @@ impl TsckKeeManager {
 
         {
             let mut hotkeys_map = state.lock();
-            for (tk, flags, hotkey_str) in bindings {
+            if let Some(info) = get_current_active_window() {
                 hotkeys_map
-                    .hotkey_names
-                    .insert((tk, flags), hotkey_str.clone());
-                println!(
-                    "Registered:{} (TK=0x{:02X}, mods=0x{:04X})",
-                    hotkey_str, tk, flags
-                );
+                    .matcher
+                    .set_active_app(&info.name(), info.raw_title());
+            }
+            for (sequence, hotkey_str) in bindings {
+                for stroke in sequence.to_strokes() {
+                    println!(
+                        "Registered:{} (TK=0x{:02X}, mods=0x{:04X})",
+                        hotkey_str, stroke.vk, stroke.flags
+                    );
+                }
+                hotkeys_map.matcher.insert(&sequence, hotkey_str);
             }
         }
         {
@@ -137,6 +231,31 @@ impl TsckKeeManager {
         Ok(())
     }
 
+    /// Keys of `mode name { ... }` blocks as (mode, key), reported as `mode::key`
+    pub fn register_mode_keys(&self, keys: Vec<(&str, &str)>) -> anyhow::Result<()> {
+        let bindings: Vec<(&str, TsckKeeSequence, String)> = keys
+            .iter()
+            .map(|(mode, key)| {
+                Ok((
+                    *mode,
+                    TsckKeeSequence::parse(key)?,
+                    format!("{}::{}", mode, key),
+                ))
+            })
+            .collect::<anyhow::Result<Vec<_>>>()?;
+
+        let state = KEE_STATE
+            .get()
+            .expect("HOTKEY_STATE should be initialized in new()");
+
+        let mut hotkeys_map = state.lock();
+        for (mode, sequence, name) in bindings {
+            println!("Registered:{} (mode)", name);
+            hotkeys_map.modes.insert(mode, &sequence, name);
+        }
+        Ok(())
+    }
+
     pub fn register_event_callback<F>(&self, callback: F)
     where
         F: Fn(KeeEvent) + Send + Sync + 'static,
@@ -155,10 +274,55 @@ impl TsckKeeManager {
             .expect("HOTKEY_STATE should be initialized in new()");
 
         let mut hotkeys_map = state.lock();
-        hotkeys_map.hotkey_names.clear();
+        hotkeys_map.matcher.clear();
+        hotkeys_map.triggers.reset();
+        if hotkeys_map.modes.active().is_some() {
+            send_mode(None);
+        }
+        hotkeys_map.modes.clear();
         println!("Cleared all registered hotkeys");
     }
 
+    /// Max delay between two strokes of a sequence like `M-k c`
+    pub fn set_sequence_timeout(&self, timeout: Duration) {
+        let state = KEE_STATE
+            .get()
+            .expect("HOTKEY_STATE should be initialized in new()");
+
+        state.lock().matcher.set_timeout(timeout);
+    }
+
+    /// Idle time after which an active mode is left
+    pub fn set_mode_timeout(&self, timeout: Duration) {
+        let state = KEE_STATE
+            .get()
+            .expect("HOTKEY_STATE should be initialized in new()");
+
+        state.lock().modes.set_timeout(timeout);
+    }
+
+    pub fn enter_mode(&self, mode: &str) -> bool {
+        enter_mode(mode)
+    }
+
+    pub fn leave_mode(&self) {
+        let state = KEE_STATE
+            .get()
+            .expect("HOTKEY_STATE should be initialized in new()");
+
+        if state.lock().modes.leave().is_some() {
+            send_mode(None);
+        }
+    }
+
+    pub fn active_mode(&self) -> Option<String> {
+        let state = KEE_STATE
+            .get()
+            .expect("HOTKEY_STATE should be initialized in new()");
+
+        state.lock().modes.active().map(str::to_string)
+    }
+
     pub fn clear_event_callbacks(&self) {
         let state = KEE_STATE
             .get()
@@ -200,7 +364,7 @@ unsafe extern "system" fn keyboard_hook(code: i32, wparam: WPARAM, lparam: LPARA
     };
 
     let should_block = {
-        let hotkey_state = state.lock();
+        let mut hotkey_state = state.lock();
 
         match msg {
             WM_KEYDOWN | WM_SYSKEYDOWN => {
@@ -229,49 +393,62 @@ unsafe extern "system" fn keyboard_hook(code: i32, wparam: WPARAM, lparam: LPARA
                 let alt_pressed = key_states[0xA4 as usize] || key_states[0xA5 as usize];
                 let meta_pressed = key_states[0x5B as usize] || key_states[0x5C as usize];
 
-                let mut hotkey_matched = None;
-
-                for (&(registered_vk, mod_flags), _callback) in hotkey_state.hotkey_names.iter() {
-                    if registered_vk == vk_code {
-                        let ctrl = (mod_flags & 0x0002) != 0;
-                        let shift = (mod_flags & 0x0004) != 0;
-                        let alt = (mod_flags & 0x0001) != 0;
-                        let meta = (mod_flags & 0x0008) != 0;
-
-                        if ctrl == ctrl_pressed
-                            && shift == shift_pressed
-                            && alt == alt_pressed
-                            && meta == meta_pressed
-                        {
-                            let matched_name = hotkey_state
-                                .hotkey_names
-                                .get(&(registered_vk, mod_flags))
-                                .cloned()
-                                .unwrap_or_default();
-
-                            hotkey_matched = Some(matched_name);
-                            break;
-                        }
-                    }
+                let flags = (alt_pressed as u32)
+                    | (ctrl_pressed as u32) << 1
+                    | (shift_pressed as u32) << 2
+                    | (meta_pressed as u32) << 3;
+
+                let stroke = KeeStroke::new(vk_code, flags);
+                let now = Instant::now();
+                if hotkey_state.modes.tick(now).is_some() {
+                    send_mode(None);
                 }
+                // keys of the active mode come first, whatever modifiers are held
+                let step = match hotkey_state.modes.feed(vk_code, now) {
+                    ModeStep::Matched(name) => {
+                        schedule_tick(hotkey_state.modes.deadline());
+                        SequenceStep::Matched(name)
+                    }
+                    ModeStep::Left(_) => {
+                        send_mode(None);
+                        SequenceStep::Pending
+                    }
+                    // modifiers pressed between the strokes of a sequence are not strokes
+                    ModeStep::Pass
+                        if modifier_event.is_some() && hotkey_state.matcher.is_pending() =>
+                    {
+                        SequenceStep::NoMatch
+                    }
+                    ModeStep::Pass if was_pressed && hotkey_state.triggers.is_active(vk_code) => {
+                        SequenceStep::Pending
+                    }
+                    ModeStep::Pass if was_pressed => hotkey_state.matcher.feed_repeat(stroke),
+                    ModeStep::Pass => hotkey_state.matcher.feed(stroke, now),
+                };
+                let step = match step {
+                    SequenceStep::Triggered(triggers) => {
+                        let fired = hotkey_state.triggers.down(stroke, triggers, now);
+                        schedule_tick(hotkey_state.triggers.deadline());
+                        send_keys(fired);
+                        SequenceStep::Pending
+                    }
+                    step => step,
+                };
 
-                drop(hotkey_state);
-                let mut hotkey_state = state.lock();
                 hotkey_state.key_states = key_states;
                 drop(hotkey_state);
 
-                if let Some(name) = hotkey_matched {
-                    if let Some(tx) = CALLBACK_CHANNEL.get() {
-                        let _ = tx.try_send(KeeEvent::OnKey(name.clone()));
+                let is_system_key = vk_code == 0x5B
+                    || vk_code == 0x5C
+                    || vk_code == 0x09
+                    || (vk_code == 0x1B && alt_pressed);
+                match step {
+                    SequenceStep::Matched(name) => {
+                        send_keys(vec![name]);
+                        !is_system_key
                     }
-                    let is_system_key = vk_code == 0x5B
-                        || vk_code == 0x5C
-                        || vk_code == 0x09
-                        || (vk_code == 0x1B && alt_pressed);
-
-                    !is_system_key
-                } else {
-                    false
+                    SequenceStep::Pending | SequenceStep::Triggered(_) => !is_system_key,
+                    SequenceStep::NoMatch => false,
                 }
             }
             WM_KEYUP | WM_SYSKEYUP => {
@@ -289,10 +466,11 @@ unsafe extern "system" fn keyboard_hook(code: i32, wparam: WPARAM, lparam: LPARA
                     }
                 }
 
-                drop(hotkey_state);
-                let mut hotkey_state = state.lock();
                 hotkey_state.key_states[vk_code as usize] = false;
+                let fired = hotkey_state.triggers.up(vk_code, Instant::now());
+                schedule_tick(hotkey_state.triggers.deadline());
                 drop(hotkey_state);
+                send_keys(fired);
 
                 false
             }
@@ -0,0 +1,164 @@
+use std::{
+    collections::HashMap,
+    time::{Duration, Instant},
+};
+
+use crate::kee_sequence::TsckKeeSequence;
+
+/// Idle time after which an active mode is left on its own
+pub const DEFAULT_MODE_TIMEOUT: Duration = Duration::from_millis(3000);
+
+const ESCAPE: u16 = 0x1B;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ModeStep {
+    /// A key of the active mode, modifiers ignored
+    Matched(String),
+    /// Escape left the named mode, swallow it
+    Left(String),
+    /// No mode active or not one of its keys, let the key through
+    Pass,
+}
+
+/// Keys of the `mode name { ... }` blocks of kee.kee. While a mode is active
+/// its keys are matched on their own, whatever modifiers are held. Time is
+/// passed in by the caller, `tick` has to be called at `deadline`.
+#[derive(Debug)]
+pub struct ModeMatcher {
+    /// mode -> vk -> binding name
+    modes: HashMap<String, HashMap<u16, String>>,
+    /// Active mode and its last use
+    active: Option<(String, Instant)>,
+    timeout: Duration,
+}
+
+impl ModeMatcher {
+    pub fn new(timeout: Duration) -> Self {
+        Self {
+            modes: HashMap::new(),
+            active: None,
+            timeout,
+        }
+    }
+
+    pub fn set_timeout(&mut self, timeout: Duration) {
+        self.timeout = timeout;
+    }
+
+    /// Only the key of the first stroke counts, modifiers are dropped
+    pub fn insert(&mut self, mode: &str, sequence: &TsckKeeSequence, name: String) {
+        let Some(stroke) = sequence.to_strokes().first().copied() else {
+            return;
+        };
+        self.modes
+            .entry(mode.to_string())
+            .or_default()
+            .insert(stroke.vk, name);
+    }
+
+    pub fn clear(&mut self) {
+        self.modes.clear();
+        self.active = None;
+    }
+
+    pub fn active(&self) -> Option<&str> {
+        self.active.as_ref().map(|(mode, _)| mode.as_str())
+    }
+
+    /// Enter `mode`, false when kee.kee doesn't declare it
+    pub fn enter(&mut self, mode: &str, now: Instant) -> bool {
+        if !self.modes.contains_key(mode) {
+            return false;
+        }
+        self.active = Some((mode.to_string(), now));
+        true
+    }
+
+    /// Leave the active mode, returns its name
+    pub fn leave(&mut self) -> Option<String> {
+        self.active.take().map(|(mode, _)| mode)
+    }
+
+    pub fn feed(&mut self, vk: u16, now: Instant) -> ModeStep {
+        let Some((mode, last_used)) = self.active.as_mut() else {
+            return ModeStep::Pass;
+        };
+        if vk == ESCAPE {
+            let mode = mode.clone();
+            self.active = None;
+            return ModeStep::Left(mode);
+        }
+        match self.modes.get(mode.as_str()).and_then(|keys| keys.get(&vk)) {
+            Some(name) => {
+                *last_used = now;
+                ModeStep::Matched(name.clone())
+            }
+            None => ModeStep::Pass,
+        }
+    }
+
+    /// Leave the active mode once it has been idle for the timeout
+    pub fn tick(&mut self, now: Instant) -> Option<String> {
+        let deadline = self.deadline()?;
+        if now < deadline {
+            return None;
+        }
+        self.leave()
+    }
+
+    /// When `tick` has something to do next
+    pub fn deadline(&self) -> Option<Instant> {
+        self.active
+            .as_ref()
+            .map(|(_, last_used)| *last_used + self.timeout)
+    }
+}
+
+#[cfg(test)]
+mod mode_matcher {
+    use super::*;
+
+    const H: u16 = 0x48;
+    const L: u16 = 0x4C;
+    const SHIFT: u16 = 0xA0;
+
+    fn matcher() -> ModeMatcher {
+        let mut m = ModeMatcher::new(DEFAULT_MODE_TIMEOUT);
+        for (key, name) in [("h", "resize::h"), ("l", "resize::l")] {
+            let sequence = TsckKeeSequence::parse(key).unwrap();
+            m.insert("resize", &sequence, name.to_string());
+        }
+        m
+    }
+
+    #[test]
+    fn test_enter_and_escape() {
+        let mut m = matcher();
+        let now = Instant::now();
+        assert_eq!(m.feed(H, now), ModeStep::Pass);
+        assert!(!m.enter("move", now));
+        assert!(m.enter("resize", now));
+        assert_eq!(m.active(), Some("resize"));
+        assert_eq!(m.feed(H, now), ModeStep::Matched("resize::h".into()));
+        assert_eq!(m.feed(L, now), ModeStep::Matched("resize::l".into()));
+        // modifiers go through untouched, held or not they don't change the match
+        assert_eq!(m.feed(SHIFT, now), ModeStep::Pass);
+        assert_eq!(m.feed(ESCAPE, now), ModeStep::Left("resize".into()));
+        assert_eq!(m.active(), None);
+        assert_eq!(m.feed(H, now), ModeStep::Pass);
+    }
+
+    #[test]
+    fn test_timeout() {
+        let mut m = matcher();
+        let now = Instant::now();
+        let ms = Duration::from_millis;
+        m.enter("resize", now);
+        assert_eq!(m.deadline(), Some(now + DEFAULT_MODE_TIMEOUT));
+        // every key of the mode pushes the timeout back
+        assert!(matches!(m.feed(H, now + ms(2000)), ModeStep::Matched(_)));
+        assert_eq!(m.tick(now + ms(3000)), None);
+        assert_eq!(m.tick(now + ms(5000)), Some("resize".into()));
+        assert_eq!(m.deadline(), None);
+    }
+}
@@ -0,0 +1,464 @@
+use std::time::{Duration, Instant};
+
+use anyhow::bail;
+
+use crate::{
+    kee_keys::TsckKeeBinding,
+    kee_trigger::{KeeTrigger, KeeTriggers},
+};
+
+pub const DEFAULT_SEQUENCE_TIMEOUT: Duration = Duration::from_millis(1000);
+
+/// A single key press: virtual key code + modifier flags (`Modifiers::to_flags`)
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct KeeStroke {
+    pub vk: u16,
+    pub flags: u32,
+}
+
+impl KeeStroke {
+    pub fn new(vk: u16, flags: u32) -> Self {
+        Self { vk, flags }
+    }
+}
+
+impl From<&TsckKeeBinding> for KeeStroke {
+    fn from(binding: &TsckKeeBinding) -> Self {
+        Self::new(binding.to_tk(), binding.modifiers.to_flags())
+    }
+}
+
+/// Whitespace separated strokes, e.g. `M-k c` or `M-space g b`, optionally
+/// with a trigger suffix `M-space:hold(300)` and followed by the app it is
+/// limited to: `M-d [PHOTOSHOP]`
+#[derive(Debug, Clone)]
+pub struct TsckKeeSequence {
+    pub strokes: Vec<TsckKeeBinding>,
+    pub app: Option<String>,
+    pub trigger: KeeTrigger,
+    /// Fire again on autorepeat, `:repeat`
+    pub repeat: bool,
+}
+
+impl TsckKeeSequence {
+    pub fn parse(input: &str) -> anyhow::Result<Self> {
+        let (keys, app) = Self::split_app(input);
+        if app.is_some_and(str::is_empty) {
+            bail!("Empty app condition in `{}`", input)
+        }
+        let (keys, trigger, repeat) = KeeTrigger::split(keys)?;
+        let strokes = keys
+            .split_whitespace()
+            .map(TsckKeeBinding::parse)
+            .collect::<anyhow::Result<Vec<_>>>()?;
+        if strokes.is_empty() {
+            bail!("Empty hotkey string")
+        }
+        if trigger != KeeTrigger::Press && strokes.len() > 1 {
+            bail!("`{}` only works on single key bindings", trigger)
+        }
+        Ok(Self {
+            strokes,
+            app: app.map(str::to_string),
+            trigger,
+            repeat,
+        })
+    }
+
+    /// `M-d [PHOTOSHOP]` -> (`M-d`, Some(`PHOTOSHOP`))
+    pub fn split_app(input: &str) -> (&str, Option<&str>) {
+        let input = input.trim();
+        let app = input.strip_suffix(']').and_then(|rest| {
+            let open = rest.rfind('[')?;
+            let preceded_by_space = rest[..open].ends_with(char::is_whitespace);
+            preceded_by_space.then(|| (rest[..open].trim_end(), rest[open + 1..].trim()))
+        });
+        match app {
+            Some((keys, app)) => (keys, Some(app)),
+            None => (input, None),
+        }
+    }
+
+    pub fn to_strokes(&self) -> Vec<KeeStroke> {
+        self.strokes.iter().map(KeeStroke::from).collect()
+    }
+
+    /// Human-readable keys, `M-k c:double` -> `Win+K, C (double tap)`.
+    /// The app condition is left out.
+    pub fn label(&self) -> String {
+        let strokes: Vec<_> = self.strokes.iter().map(TsckKeeBinding::label).collect();
+        let mut label = strokes.join(", ");
+        let notes: Vec<_> = self
+            .trigger
+            .label()
+            .into_iter()
+            .chain(self.repeat.then(|| "repeats".to_string()))
+            .collect();
+        if !notes.is_empty() {
+            label.push_str(&format!(" ({})", notes.join(", ")));
+        }
+        label
+    }
+}
+
+/// Canonical form, e.g. `S-M-k  c [PHOTOSHOP]` -> `M-S-k c [PHOTOSHOP]`
+impl std::fmt::Display for TsckKeeSequence {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        for (i, stroke) in self.strokes.iter().enumerate() {
+            if i > 0 {
+                f.write_str(" ")?;
+            }
+            write!(f, "{}", stroke)?;
+        }
+        write!(f, "{}", self.trigger)?;
+        if self.repeat {
+            f.write_str(":repeat")?;
+        }
+        match &self.app {
+            Some(app) => write!(f, " [{}]", app),
+            None => Ok(()),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum SequenceStep {
+    /// The stroke completed the named binding
+    Matched(String),
+    /// The stroke is a prefix of a longer binding, swallow it and wait
+    Pending,
+    /// The key has up, hold or double-tap bindings, see `TriggerMatcher`
+    Triggered(KeeTriggers),
+    /// Nothing matches, let the key through
+    NoMatch,
+}
+
+#[derive(Debug)]
+struct SequenceBinding {
+    strokes: Vec<KeeStroke>,
+    app: Option<String>,
+    trigger: KeeTrigger,
+    repeat: bool,
+    name: String,
+}
+
+/// Matches incoming strokes against single and multi-stroke bindings.
+/// Time and the active window are passed in by the caller so it can be driven
+/// by synthetic events.
+#[derive(Debug)]
+pub struct SequenceMatcher {
+    bindings: Vec<SequenceBinding>,
+    pending: Vec<KeeStroke>,
+    last_stroke: Option<Instant>,
+    /// Last matched stroke and whether its autorepeat fires again
+    last_match: Option<(KeeStroke, String, bool)>,
+    timeout: Duration,
+    /// exe name and title of the window in front
+    active_app: (String, String),
+}
+
+impl SequenceMatcher {
+    pub fn new(timeout: Duration) -> Self {
+        Self {
+            bindings: Vec::new(),
+            pending: Vec::new(),
+            last_stroke: None,
+            last_match: None,
+            timeout,
+            active_app: Default::default(),
+        }
+    }
+
+    /// Register a binding, replacing any previous one with the same strokes,
+    /// trigger and app.
+    pub fn insert(&mut self, sequence: &TsckKeeSequence, name: impl Into<String>) {
+        let binding = SequenceBinding {
+            strokes: sequence.to_strokes(),
+            app: sequence.app.clone(),
+            trigger: sequence.trigger,
+            repeat: sequence.repeat,
+            name: name.into(),
+        };
+        match self.bindings.iter_mut().find(|b| {
+            b.strokes == binding.strokes && b.app == binding.app && b.trigger == binding.trigger
+        }) {
+            Some(existing) => *existing = binding,
+            None => self.bindings.push(binding),
+        }
+    }
+
+    pub fn set_active_app(&mut self, exe_name: &str, title: &str) {
+        self.active_app = (exe_name.to_string(), title.to_lowercase());
+    }
+
+    /// App bindings match the exe name (`photoshop`) or a part of the title.
+    fn is_active(&self, app: &str) -> bool {
+        let (exe_name, title) = &self.active_app;
+        exe_name.eq_ignore_ascii_case(app) || title.contains(&app.to_lowercase())
+    }
+
+    /// Bindings usable in the active window, app specific ones first.
+    fn candidates(&self) -> impl Iterator<Item = &SequenceBinding> {
+        let app_bindings = self
+            .bindings
+            .iter()
+            .filter(|b| b.app.as_deref().is_some_and(|app| self.is_active(app)));
+        let global = self.bindings.iter().filter(|b| b.app.is_none());
+        app_bindings.chain(global)
+    }
+
+    pub fn clear(&mut self) {
+        self.bindings.clear();
+        self.reset();
+    }
+
+    pub fn reset(&mut self) {
+        self.pending.clear();
+        self.last_stroke = None;
+        self.last_match = None;
+    }
+
+    pub fn set_timeout(&mut self, timeout: Duration) {
+        self.timeout = timeout;
+    }
+
+    pub fn is_pending(&self) -> bool {
+        !self.pending.is_empty()
+    }
+
+    pub fn feed(&mut self, stroke: KeeStroke, now: Instant) -> SequenceStep {
+        if self
+            .last_stroke
+            .is_some_and(|last| now.duration_since(last) > self.timeout)
+        {
+            self.pending.clear();
+        }
+        self.pending.push(stroke);
+        self.last_match = None;
+
+        // every trigger of the keys, for the app of the first exact match
+        let mut triggers = KeeTriggers::default();
+        let mut repeat = false;
+        let matched_app = self
+            .candidates()
+            .find(|b| b.strokes == self.pending)
+            .map(|b| b.app.clone());
+        if let Some(app) = matched_app {
+            for b in self
+                .candidates()
+                .filter(|b| b.strokes == self.pending && b.app == app)
+            {
+                triggers.set(b.trigger, b.name.clone());
+                repeat |= b.trigger == KeeTrigger::Press && b.repeat;
+            }
+            self.reset();
+            return match triggers.press.clone() {
+                Some(name) if triggers.is_press_only() => {
+                    self.last_match = Some((stroke, name.clone(), repeat));
+                    SequenceStep::Matched(name)
+                }
+                _ => SequenceStep::Triggered(triggers),
+            };
+        }
+        if self
+            .candidates()
+            .any(|b| b.strokes.starts_with(&self.pending))
+        {
+            self.last_stroke = Some(now);
+            return SequenceStep::Pending;
+        }
+
+        // a broken sequence: give the stroke a second chance on its own
+        let restart = self.pending.len() > 1;
+        self.reset();
+        if restart {
+            self.feed(stroke, now)
+        } else {
+            SequenceStep::NoMatch
+        }
+    }
+
+    /// Autorepeat of the key held down: fires the last match again if it's a
+    /// `:repeat` binding, swallowed otherwise.
+    pub fn feed_repeat(&mut self, stroke: KeeStroke) -> SequenceStep {
+        match &self.last_match {
+            Some((last, name, true)) if *last == stroke => SequenceStep::Matched(name.clone()),
+            Some((last, ..)) if *last == stroke => SequenceStep::Pending,
+            _ => SequenceStep::NoMatch,
+        }
+    }
+}
+
+#[cfg(test)]
+mod sequence_matcher {
+    use super::*;
+
+    fn stroke(input: &str) -> KeeStroke {
+        KeeStroke::from(&TsckKeeBinding::parse(input).unwrap())
+    }
+
+    fn matcher(kees: &[&str]) -> SequenceMatcher {
+        let mut matcher = SequenceMatcher::new(DEFAULT_SEQUENCE_TIMEOUT);
+        for kee in kees {
+            let sequence = TsckKeeSequence::parse(kee).unwrap();
+            matcher.insert(&sequence, *kee);
+        }
+        matcher
+    }
+
+    #[test]
+    fn test_label() {
+        let label = |kee| TsckKeeSequence::parse(kee).unwrap().label();
+        assert_eq!(label("S-M-left"), "Win+Shift+←");
+        assert_eq!(label("A-C-kp5"), "Ctrl+Alt+Num 5");
+        assert_eq!(label("M-k c [PHOTOSHOP]"), "Win+K, C");
+        assert_eq!(label("M-space:hold(300)"), "Win+Space (hold 300ms)");
+        assert_eq!(label("M-f5:repeat"), "Win+F5 (repeats)");
+        assert_eq!(label("M-pageup:double"), "Win+PgUp (double tap)");
+        assert_eq!(label("/"), "/");
+    }
+
+    #[test]
+    fn test_single_and_sequence() {
+        let mut m = matcher(&["M-1", "M-k c", "M-space g b"]);
+        let now = Instant::now();
+        assert_eq!(
+            m.feed(stroke("M-1"), now),
+            SequenceStep::Matched("M-1".into())
+        );
+        assert_eq!(m.feed(stroke("M-k"), now), SequenceStep::Pending);
+        assert_eq!(
+            m.feed(stroke("c"), now),
+            SequenceStep::Matched("M-k c".into())
+        );
+        assert_eq!(m.feed(stroke("M-space"), now), SequenceStep::Pending);
+        assert_eq!(m.feed(stroke("g"), now), SequenceStep::Pending);
+        assert_eq!(
+            m.feed(stroke("b"), now),
+            SequenceStep::Matched("M-space g b".into())
+        );
+        assert_eq!(m.feed(stroke("c"), now), SequenceStep::NoMatch);
+    }
+
+    #[test]
+    fn test_broken_sequence_restarts() {
+        let mut m = matcher(&["M-1", "M-k c"]);
+        let now = Instant::now();
+        assert_eq!(m.feed(stroke("M-k"), now), SequenceStep::Pending);
+        assert_eq!(m.feed(stroke("x"), now), SequenceStep::NoMatch);
+        assert!(!m.is_pending());
+        assert_eq!(m.feed(stroke("M-k"), now), SequenceStep::Pending);
+        assert_eq!(
+            m.feed(stroke("M-1"), now),
+            SequenceStep::Matched("M-1".into())
+        );
+    }
+
+    #[test]
+    fn test_timeout() {
+        let mut m = matcher(&["M-k c"]);
+        let now = Instant::now();
+        assert_eq!(m.feed(stroke("M-k"), now), SequenceStep::Pending);
+        let later = now + DEFAULT_SEQUENCE_TIMEOUT + Duration::from_millis(1);
+        assert_eq!(m.feed(stroke("c"), later), SequenceStep::NoMatch);
+
+        m.set_timeout(Duration::from_millis(100));
+        assert_eq!(m.feed(stroke("M-k"), now), SequenceStep::Pending);
+        let within = now + Duration::from_millis(99);
+        assert_eq!(
+            m.feed(stroke("c"), within),
+            SequenceStep::Matched("M-k c".into())
+        );
+    }
+
+    #[test]
+    fn test_split_app() {
+        assert_eq!(
+            TsckKeeSequence::split_app("M-d [PHOTOSHOP]"),
+            ("M-d", Some("PHOTOSHOP"))
+        );
+        assert_eq!(
+            TsckKeeSequence::split_app("M-k c [ Adobe Photoshop ]"),
+            ("M-k c", Some("Adobe Photoshop"))
+        );
+        assert_eq!(TsckKeeSequence::split_app("M-]"), ("M-]", None));
+        assert!(TsckKeeSequence::parse("M-d []").is_err());
+    }
+
+    #[test]
+    fn test_canonical() {
+        let canonical = |kee: &str| TsckKeeSequence::parse(kee).unwrap().to_string();
+        assert_eq!(canonical("S-M-a"), "M-S-a");
+        assert_eq!(canonical("A-C-S-W-esc"), "M-S-C-A-escape");
+        assert_eq!(canonical("S-M-k  c [PHOTOSHOP]"), "M-S-k c [PHOTOSHOP]");
+        assert_eq!(canonical("W-left:norepeat"), "M-left");
+        assert_eq!(
+            canonical("S-W-left:repeat [Code]"),
+            "M-S-left:repeat [Code]"
+        );
+        assert_eq!(canonical("M-space:hold( 300 )"), "M-space:hold(300)");
+        assert!(TsckKeeSequence::parse("M-k c:double").is_err());
+        assert_eq!(
+            TsckKeeBinding::parse("M-S-a").unwrap(),
+            TsckKeeBinding::parse("S-M-a").unwrap()
+        );
+    }
+
+    #[test]
+    fn test_app_bindings() {
+        let mut m = matcher(&["M-d", "M-d [PHOTOSHOP]", "M-b [Visual Studio Code]"]);
+        let now = Instant::now();
+        assert_eq!(
+            m.feed(stroke("M-d"), now),
+            SequenceStep::Matched("M-d".into())
+        );
+        assert_eq!(m.feed(stroke("M-b"), now), SequenceStep::NoMatch);
+
+        m.set_active_app("Photoshop", "Untitled-1 @ 100% (RGB/8)");
+        assert_eq!(
+            m.feed(stroke("M-d"), now),
+            SequenceStep::Matched("M-d [PHOTOSHOP]".into())
+        );
+
+        m.set_active_app("Code", "main.rs - tsck - Visual Studio Code");
+        assert_eq!(
+            m.feed(stroke("M-d"), now),
+            SequenceStep::Matched("M-d".into())
+        );
+        assert_eq!(
+            m.feed(stroke("M-b"), now),
+            SequenceStep::Matched("M-b [Visual Studio Code]".into())
+        );
+    }
+
+    #[test]
+    fn test_triggers_and_repeat() {
+        let mut m = matcher(&["M-left:repeat", "M-right", "M-space", "M-space:hold(300)"]);
+        let now = Instant::now();
+        assert_eq!(
+            m.feed(stroke("M-left"), now),
+            SequenceStep::Matched("M-left:repeat".into())
+        );
+        assert_eq!(
+            m.feed_repeat(stroke("M-left")),
+            SequenceStep::Matched("M-left:repeat".into())
+        );
+        assert_eq!(
+            m.feed(stroke("M-right"), now),
+            SequenceStep::Matched("M-right".into())
+        );
+        assert_eq!(m.feed_repeat(stroke("M-right")), SequenceStep::Pending);
+        assert_eq!(m.feed_repeat(stroke("M-left")), SequenceStep::NoMatch);
+
+        let mut expected = KeeTriggers::default();
+        expected.set(KeeTrigger::Press, "M-space".into());
+        expected.set(
+            KeeTrigger::Hold(Duration::from_millis(300)),
+            "M-space:hold(300)".into(),
+        );
+        assert_eq!(
+            m.feed(stroke("M-space"), now),
+            SequenceStep::Triggered(expected)
+        );
+    }
+}
@@ -0,0 +1,358 @@
+use std::time::{Duration, Instant};
+
+use anyhow::bail;
+
+use crate::kee_sequence::KeeStroke;
+
+/// Max delay between the release of the first tap and the second press
+pub const DEFAULT_DOUBLE_TAP: Duration = Duration::from_millis(300);
+
+/// When a binding fires, set with a `:suffix` on the keys: `M-space:hold(300)`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub enum KeeTrigger {
+    /// On key down, the default
+    #[default]
+    Press,
+    /// On key up
+    Up,
+    /// Once the key has been held down that long
+    Hold(Duration),
+    /// Two taps within `DEFAULT_DOUBLE_TAP`
+    Double,
+}
+
+impl KeeTrigger {
+    /// `M-left:repeat` -> (`M-left`, Press, true)
+    pub fn split(keys: &str) -> anyhow::Result<(&str, KeeTrigger, bool)> {
+        let Some((keys, suffixes)) = keys.split_once(':') else {
+            return Ok((keys, KeeTrigger::Press, false));
+        };
+        let mut trigger = None;
+        let mut repeat = None;
+        for suffix in suffixes.split(':').map(str::trim) {
+            let parsed = match suffix.to_lowercase().as_str() {
+                "repeat" => {
+                    repeat = Some(true);
+                    continue;
+                }
+                "norepeat" => {
+                    repeat = Some(false);
+                    continue;
+                }
+                "up" => KeeTrigger::Up,
+                "double" => KeeTrigger::Double,
+                other => match other
+                    .strip_prefix("hold(")
+                    .and_then(|ms| ms.strip_suffix(')'))
+                {
+                    Some(ms) => match ms.trim().parse::<u64>() {
+                        Ok(ms) => KeeTrigger::Hold(Duration::from_millis(ms)),
+                        Err(_) => {
+                            bail!("Invalid hold time in `:{}`, expected milliseconds", suffix)
+                        }
+                    },
+                    None => bail!(
+                        "Unknown trigger `:{}`, expected :up, :double, :hold(ms), :repeat or :norepeat",
+                        suffix
+                    ),
+                },
+            };
+            if trigger.replace(parsed).is_some() {
+                bail!("Only one of :up, :double or :hold(ms) per binding")
+            }
+        }
+        let trigger = trigger.unwrap_or_default();
+        if repeat == Some(true) && trigger != KeeTrigger::Press {
+            bail!("`:repeat` only applies to key press bindings")
+        }
+        Ok((keys.trim_end(), trigger, repeat.unwrap_or(false)))
+    }
+}
+
+impl KeeTrigger {
+    /// How the trigger reads on a cheat sheet, nothing for a plain press
+    pub fn label(&self) -> Option<String> {
+        match self {
+            KeeTrigger::Press => None,
+            KeeTrigger::Up => Some("on release".into()),
+            KeeTrigger::Hold(duration) => Some(format!("hold {}ms", duration.as_millis())),
+            KeeTrigger::Double => Some("double tap".into()),
+        }
+    }
+}
+
+impl std::fmt::Display for KeeTrigger {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            KeeTrigger::Press => Ok(()),
+            KeeTrigger::Up => f.write_str(":up"),
+            KeeTrigger::Hold(duration) => write!(f, ":hold({})", duration.as_millis()),
+            KeeTrigger::Double => f.write_str(":double"),
+        }
+    }
+}
+
+/// Every binding of one key, by trigger
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct KeeTriggers {
+    pub press: Option<String>,
+    pub up: Option<String>,
+    pub hold: Option<(Duration, String)>,
+    pub double: Option<String>,
+}
+
+impl KeeTriggers {
+    pub fn set(&mut self, trigger: KeeTrigger, name: String) {
+        match trigger {
+            KeeTrigger::Press => self.press = Some(name),
+            KeeTrigger::Up => self.up = Some(name),
+            KeeTrigger::Hold(duration) => self.hold = Some((duration, name)),
+            KeeTrigger::Double => self.double = Some(name),
+        }
+    }
+
+    /// Only a press binding, nothing to wait for
+    pub fn is_press_only(&self) -> bool {
+        self.up.is_none() && self.hold.is_none() && self.double.is_none()
+    }
+
+    /// What a plain tap fires: the press binding, then the up one
+    fn tap(&self) -> Vec<String> {
+        self.press.iter().chain(&self.up).cloned().collect()
+    }
+}
+
+#[derive(Debug)]
+struct ActiveKey {
+    stroke: KeeStroke,
+    triggers: KeeTriggers,
+    down_at: Instant,
+    released_at: Option<Instant>,
+    /// Fired already, the rest of this press is swallowed
+    resolved: bool,
+    /// Fire the up binding on release
+    up_pending: bool,
+}
+
+/// Resolves up, hold and double-tap bindings of the key currently pressed.
+/// Time is passed in by the caller, `tick` has to be called at `deadline`.
+#[derive(Debug)]
+pub struct TriggerMatcher {
+    active: Option<ActiveKey>,
+    double_tap: Duration,
+}
+
+impl TriggerMatcher {
+    pub fn new(double_tap: Duration) -> Self {
+        Self {
+            active: None,
+            double_tap,
+        }
+    }
+
+    /// Whether `vk` belongs to the key being resolved, its autorepeat is swallowed
+    pub fn is_active(&self, vk: u16) -> bool {
+        self.active.as_ref().is_some_and(|a| a.stroke.vk == vk)
+    }
+
+    /// A fresh press of a key with `triggers`, returns what fires right away.
+    pub fn down(&mut self, stroke: KeeStroke, triggers: KeeTriggers, now: Instant) -> Vec<String> {
+        let mut fired = Vec::new();
+        if let Some(active) = self.active.take() {
+            let second_tap = active.stroke == stroke
+                && !active.resolved
+                && active
+                    .released_at
+                    .is_some_and(|at| now.duration_since(at) <= self.double_tap);
+            match (&active.triggers.double, second_tap) {
+                (Some(double), true) => {
+                    fired.push(double.clone());
+                    self.active = Some(ActiveKey {
+                        resolved: true,
+                        up_pending: false,
+                        ..active
+                    });
+                    return fired;
+                }
+                _ if !active.resolved => fired.extend(active.triggers.tap()),
+                _ => {}
+            }
+        }
+
+        let waits = triggers.hold.is_some() || triggers.double.is_some();
+        if !waits {
+            fired.extend(triggers.press.clone());
+        }
+        self.active = Some(ActiveKey {
+            stroke,
+            up_pending: !waits && triggers.up.is_some(),
+            triggers,
+            down_at: now,
+            released_at: None,
+            resolved: !waits,
+        });
+        fired
+    }
+
+    pub fn up(&mut self, vk: u16, now: Instant) -> Vec<String> {
+        let Some(active) = self.active.as_mut().filter(|a| a.stroke.vk == vk) else {
+            return Vec::new();
+        };
+        if active.resolved {
+            let fired = match active.up_pending {
+                true => active.triggers.up.iter().cloned().collect(),
+                false => Vec::new(),
+            };
+            self.active = None;
+            return fired;
+        }
+        if active.triggers.double.is_some() {
+            // wait for a second tap
+            active.released_at = Some(now);
+            return Vec::new();
+        }
+        let fired = active.triggers.tap();
+        self.active = None;
+        fired
+    }
+
+    /// Fire the hold binding or give up waiting for the second tap.
+    pub fn tick(&mut self, now: Instant) -> Vec<String> {
+        let Some(active) = self.active.as_mut().filter(|a| !a.resolved) else {
+            return Vec::new();
+        };
+        match (&active.triggers.hold, active.released_at) {
+            (Some((hold, name)), None) if now.duration_since(active.down_at) >= *hold => {
+                let name = name.clone();
+                active.resolved = true;
+                vec![name]
+            }
+            (_, Some(at)) if now.duration_since(at) > self.double_tap => {
+                let fired = active.triggers.tap();
+                self.active = None;
+                fired
+            }
+            _ => Vec::new(),
+        }
+    }
+
+    /// When `tick` has something to do next
+    pub fn deadline(&self) -> Option<Instant> {
+        let active = self.active.as_ref().filter(|a| !a.resolved)?;
+        match active.released_at {
+            Some(at) => Some(at + self.double_tap + Duration::from_millis(1)),
+            None => active
+                .triggers
+                .hold
+                .as_ref()
+                .map(|(hold, _)| active.down_at + *hold),
+        }
+    }
+
+    pub fn reset(&mut self) {
+        self.active = None;
+    }
+}
+
+#[cfg(test)]
+mod trigger_matcher {
+    use super::*;
+
+    const SPACE: KeeStroke = KeeStroke { vk: 0x20, flags: 8 };
+
+    fn triggers(bindings: &[(KeeTrigger, &str)]) -> KeeTriggers {
+        let mut triggers = KeeTriggers::default();
+        for (trigger, name) in bindings {
+            triggers.set(*trigger, name.to_string());
+        }
+        triggers
+    }
+
+    fn ms(n: u64) -> Duration {
+        Duration::from_millis(n)
+    }
+
+    #[test]
+    fn test_split() {
+        let split = |keys| KeeTrigger::split(keys).map_err(|e| e.to_string());
+        assert_eq!(split("M-space"), Ok(("M-space", KeeTrigger::Press, false)));
+        assert_eq!(
+            split("M-space:hold(300)"),
+            Ok(("M-space", KeeTrigger::Hold(ms(300)), false))
+        );
+        assert_eq!(
+            split("M-k c:double"),
+            Ok(("M-k c", KeeTrigger::Double, false))
+        );
+        assert_eq!(
+            split("M-left:repeat"),
+            Ok(("M-left", KeeTrigger::Press, true))
+        );
+        assert_eq!(
+            split("M-left:up:norepeat"),
+            Ok(("M-left", KeeTrigger::Up, false))
+        );
+        assert!(split("M-left:up:double").is_err());
+        assert!(split("M-left:up:repeat").is_err());
+        assert!(split("M-left:hold(soon)").is_err());
+        assert!(split("M-left:triple").is_err());
+    }
+
+    #[test]
+    fn test_press_and_up() {
+        let mut m = TriggerMatcher::new(DEFAULT_DOUBLE_TAP);
+        let now = Instant::now();
+        let t = triggers(&[(KeeTrigger::Press, "press"), (KeeTrigger::Up, "up")]);
+        assert_eq!(m.down(SPACE, t, now), vec!["press"]);
+        assert_eq!(m.deadline(), None);
+        assert!(m.is_active(SPACE.vk));
+        assert_eq!(m.up(SPACE.vk, now + ms(50)), vec!["up"]);
+        assert!(!m.is_active(SPACE.vk));
+    }
+
+    #[test]
+    fn test_hold() {
+        let mut m = TriggerMatcher::new(DEFAULT_DOUBLE_TAP);
+        let now = Instant::now();
+        let t = triggers(&[
+            (KeeTrigger::Press, "press"),
+            (KeeTrigger::Hold(ms(300)), "hold"),
+        ]);
+        // held long enough
+        assert!(m.down(SPACE, t.clone(), now).is_empty());
+        assert_eq!(m.deadline(), Some(now + ms(300)));
+        assert!(m.tick(now + ms(299)).is_empty());
+        assert_eq!(m.tick(now + ms(300)), vec!["hold"]);
+        assert!(m.up(SPACE.vk, now + ms(400)).is_empty());
+
+        // released before
+        assert!(m.down(SPACE, t, now).is_empty());
+        assert_eq!(m.up(SPACE.vk, now + ms(100)), vec!["press"]);
+        assert!(m.tick(now + ms(300)).is_empty());
+    }
+
+    #[test]
+    fn test_double() {
+        let mut m = TriggerMatcher::new(DEFAULT_DOUBLE_TAP);
+        let now = Instant::now();
+        let t = triggers(&[(KeeTrigger::Press, "press"), (KeeTrigger::Double, "double")]);
+        assert!(m.down(SPACE, t.clone(), now).is_empty());
+        assert!(m.up(SPACE.vk, now + ms(50)).is_empty());
+        assert_eq!(m.down(SPACE, t.clone(), now + ms(200)), vec!["double"]);
+        assert!(m.up(SPACE.vk, now + ms(250)).is_empty());
+
+        // a single tap fires once the window is over
+        assert!(m.down(SPACE, t.clone(), now).is_empty());
+        assert!(m.up(SPACE.vk, now + ms(50)).is_empty());
+        assert_eq!(m.deadline(), Some(now + ms(351)));
+        assert!(m.tick(now + ms(300)).is_empty());
+        assert_eq!(m.tick(now + ms(351)), vec!["press"]);
+
+        // another trigger key cuts the wait short
+        let other = KeeStroke { vk: 0x41, flags: 8 };
+        assert!(m.down(SPACE, t.clone(), now).is_empty());
+        assert!(m.up(SPACE.vk, now + ms(50)).is_empty());
+        let a = triggers(&[(KeeTrigger::Press, "a")]);
+        assert_eq!(m.down(other, a, now + ms(100)), vec!["press", "a"]);
+    }
+}
@@ -263,6 +263,10 @@ impl WindowInfo {
         self.title.replace(" ", "-").to_lowercase().to_string()
     }
 
+    pub fn raw_title(&self) -> &str {
+        &self.title
+    }
+
     pub fn exe_path(&self) -> &String {
         &self.exe_path
     }
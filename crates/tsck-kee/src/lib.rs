@@ -1,25 +1,39 @@
 mod beep;
+mod kee_cheatsheet;
+mod kee_conflict;
 mod kee_keys;
 mod kee_manager;
+mod kee_mode;
+mod kee_sequence;
+mod kee_trigger;
 mod kee_windows;
 mod tokenizer;
 mod win;
 use flume::{Receiver, Sender, unbounded};
 pub use kee_manager::TsckKeeManager;
 use parking_lot::{Mutex, RwLock};
-use std::sync::Arc;
+use std::{sync::Arc, time::Duration};
 mod macros;
 use crate::{beep::BeepController, kee_manager::Modifier};
-pub use kee_keys::{TKeePair, TKeePairList};
+pub use kee_cheatsheet::{CheatEntry, CheatSection, CheatSheet, CheatSheetFormat};
+pub use kee_conflict::{KeeConflict, KeeConflictKind};
+pub use kee_keys::{TKeePair, TKeePairList, TsckKeeBinding};
+pub use kee_mode::DEFAULT_MODE_TIMEOUT;
+pub use kee_sequence::{DEFAULT_SEQUENCE_TIMEOUT, TsckKeeSequence};
+pub use kee_trigger::{DEFAULT_DOUBLE_TAP, KeeTrigger};
 pub use kee_windows::list_windows;
 pub use kee_windows::{SafeHWND, WinPos, WinSize, WindowInfo, get_current_active_window};
+pub use tokenizer::cst::{KeeDocument, KeeLine, KeeNode};
+pub use tokenizer::diagnostic::{KeeDiagnostic, Severity, Span};
 pub use tokenizer::lexer::{KeeFunc, KeeParser};
 type EventHandler = Arc<dyn Fn(&Event) + Send + Sync + 'static>;
-pub use tokenizer::func_lexer::{Func, FuncExpr, FuncLexer};
+pub use tsck_utils::{ArgSchema, Func, FuncExpr, FuncLexer, FuncSchema, ScopeSchema};
 #[derive(Debug, Clone)]
 pub enum Event {
     Keys(String, String),
     WindowChange(WindowInfo),
+    /// Name of the mode just entered, `None` once it's left
+    ModeChange(Option<String>),
     Shutdown,
 }
 
@@ -53,87 +67,105 @@ impl Kee {
         self
     }
 
-    fn register_hotkeys(&self, kees: Vec<TKeePair>) -> anyhow::Result<()> {
-        {
-            let mut current = self.current_keypairs.write();
-            *current = kees;
-        }
+    /// Forwards what the hook sees to `on_message`, a binding's `mode(name)`
+    /// steps are left to whoever runs its chain
+    fn callback(&self) -> impl Fn(kee_manager::KeeEvent) + Send + Sync + 'static {
         let keypairs_ref = self.current_keypairs.clone();
         let beep_controller = self.beep_controller.clone();
-        let keys = self.current_keypairs.read();
-        let keys = keys.iter().map(|kp| kp.key.as_str()).collect();
         let sender = self.sender.clone();
-        self.hotkey_manager
-            .register_hotkeys(keys, move |cb| match cb {
-                kee_manager::KeeEvent::OnKey(k) => {
-                    let keypairs = keypairs_ref.read();
-                    if let Some(pair) = keypairs.iter().find(|p| p.key == k) {
-                        _ = sender.send(Event::Keys(pair.key.clone(), pair.func.clone()));
-                    }
+        move |cb| match cb {
+            kee_manager::KeeEvent::OnKey(k) => {
+                let keypairs = keypairs_ref.read();
+                if let Some(pair) = keypairs.iter().rev().find(|p| p.id() == k) {
+                    _ = sender.send(Event::Keys(pair.key.clone(), pair.func.clone()));
                 }
-                kee_manager::KeeEvent::OnModifier(modifier, state) => {
-                    if modifier == Modifier::Win {
-                        if let Some(controller) = beep_controller.as_ref() {
-                            let mut guard = controller.lock();
-                            if state {
-                                guard.start();
-                            } else {
-                                guard.stop();
-                            }
+            }
+            kee_manager::KeeEvent::OnModifier(modifier, state) => {
+                if modifier == Modifier::Win {
+                    if let Some(controller) = beep_controller.as_ref() {
+                        let mut guard = controller.lock();
+                        if state {
+                            guard.start();
+                        } else {
+                            guard.stop();
                         }
                     }
                 }
-                kee_manager::KeeEvent::OnWindowChange(safe_window_info) => {
-                    _ = sender.send(Event::WindowChange(safe_window_info));
-                }
-            })?;
+            }
+            kee_manager::KeeEvent::OnWindowChange(safe_window_info) => {
+                _ = sender.send(Event::WindowChange(safe_window_info));
+            }
+            kee_manager::KeeEvent::OnModeChange(mode) => {
+                _ = sender.send(Event::ModeChange(mode));
+            }
+        }
+    }
+
+    fn register_hotkeys(&self, kees: Vec<TKeePair>) -> anyhow::Result<()> {
+        {
+            let mut current = self.current_keypairs.write();
+            *current = kees;
+        }
+        let pairs = self.current_keypairs.read();
+        let (mode_pairs, pairs): (Vec<_>, Vec<_>) = pairs.iter().partition(|kp| kp.mode.is_some());
+        let keys = pairs.iter().map(|kp| kp.key.as_str()).collect();
+        let mode_keys = mode_pairs
+            .iter()
+            .filter_map(|kp| Some((kp.mode.as_deref()?, kp.key.as_str())))
+            .collect();
+        self.hotkey_manager
+            .register_hotkeys(keys, self.callback())?;
+        self.hotkey_manager.register_mode_keys(mode_keys)?;
 
         Ok(())
     }
 
+    /// Max delay between the strokes of a multi-stroke binding like `M-k c`
+    pub fn set_sequence_timeout(&self, timeout: Duration) {
+        self.hotkey_manager.set_sequence_timeout(timeout);
+    }
+
+    /// Idle time after which an active `mode name { ... }` block is left
+    pub fn set_mode_timeout(&self, timeout: Duration) {
+        self.hotkey_manager.set_mode_timeout(timeout);
+    }
+
+    /// Enter a mode like a `mode(name)` step would, false if it isn't declared
+    pub fn enter_mode(&self, mode: &str) -> bool {
+        self.hotkey_manager.enter_mode(mode)
+    }
+
+    pub fn leave_mode(&self) {
+        self.hotkey_manager.leave_mode();
+    }
+
+    pub fn active_mode(&self) -> Option<String> {
+        self.hotkey_manager.active_mode()
+    }
+
     /// Update hotkeys at runtime
     pub fn update_hotkeys(&self, kees: Vec<TKeePair>) -> anyhow::Result<()> {
         {
             let mut current = self.current_keypairs.write();
             *current = kees;
         }
-        let keypairs_ref = self.current_keypairs.clone();
-        let beep_controller = self.beep_controller.clone();
-        let keys = self.current_keypairs.read();
-        let keys = keys.iter().map(|kp| kp.key.as_str()).collect();
-        let sender = self.sender.clone();
-        self.hotkey_manager
-            .update_hotkeys(keys, move |cb| match cb {
-                kee_manager::KeeEvent::OnKey(k) => {
-                    let keypairs = keypairs_ref.read();
-                    if let Some(pair) = keypairs.iter().find(|p| p.key == k) {
-                        _ = sender.send(Event::Keys(pair.key.clone(), pair.func.clone()));
-                    }
-                }
-                kee_manager::KeeEvent::OnModifier(modifier, state) => {
-                    if modifier == Modifier::Win {
-                        if let Some(controller) = beep_controller.as_ref() {
-                            let mut guard = controller.lock();
-                            if state {
-                                guard.start();
-                            } else {
-                                guard.stop();
-                            }
-                        }
-                    }
-                }
-                kee_manager::KeeEvent::OnWindowChange(safe_window_info) => {
-                    _ = sender.send(Event::WindowChange(safe_window_info));
-                }
-            })?;
+        let pairs = self.current_keypairs.read();
+        let (mode_pairs, pairs): (Vec<_>, Vec<_>) = pairs.iter().partition(|kp| kp.mode.is_some());
+        let keys = pairs.iter().map(|kp| kp.key.as_str()).collect();
+        let mode_keys = mode_pairs
+            .iter()
+            .filter_map(|kp| Some((kp.mode.as_deref()?, kp.key.as_str())))
+            .collect();
+        self.hotkey_manager.update_hotkeys(keys, self.callback())?;
+        self.hotkey_manager.register_mode_keys(mode_keys)?;
         println!("Hotkeys updated successfully");
         Ok(())
     }
 
-    pub fn run(&self, kees: Vec<TKeePair>) {
-        if let Err(_) = self.register_hotkeys(kees) {
-            panic!("Failed to registering hotkey");
-        }
+    /// Register `kees` and hand the events to `on_message` on a thread of
+    /// their own
+    pub fn run(&self, kees: Vec<TKeePair>) -> anyhow::Result<()> {
+        self.register_hotkeys(kees)?;
 
         let receiver = self.receiver.clone();
         let handler = self.handler.clone();
@@ -148,6 +180,7 @@ impl Kee {
                 }
             }
         });
+        Ok(())
     }
 
     pub fn run_blocking(&self, kees: Vec<TKeePair>) {
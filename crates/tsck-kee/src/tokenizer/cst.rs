@@ -0,0 +1,259 @@
+use tsck_utils::FuncLexer;
+
+use crate::{kee_sequence::TsckKeeSequence, tokenizer::lexer::KeeFuncLexer};
+
+/// What a single kee.kee line holds
+#[derive(Debug, Clone, PartialEq)]
+pub enum KeeNode<'a> {
+    Blank,
+    Comment(&'a str),
+    /// `[name]`
+    Section(&'a str),
+    /// `mode name { # comment`
+    Mode {
+        name: &'a str,
+        comment: Option<&'a str>,
+    },
+    /// `} # comment` closing a mode
+    ModeEnd(Option<&'a str>),
+    /// `let name = value # comment`
+    Let {
+        name: &'a str,
+        value: &'a str,
+        comment: Option<&'a str>,
+    },
+    /// `keys = function # comment`
+    Binding {
+        kee: &'a str,
+        func: &'a str,
+        comment: Option<&'a str>,
+    },
+    /// Anything else, kept as written
+    Unknown(&'a str),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct KeeLine<'a> {
+    /// The line as written, including its line break
+    pub raw: &'a str,
+    pub node: KeeNode<'a>,
+}
+
+/// Lossless syntax tree of kee.kee, printing it gives back the exact source.
+#[derive(Debug, Clone)]
+pub struct KeeDocument<'a> {
+    pub lines: Vec<KeeLine<'a>>,
+    newline: &'static str,
+}
+
+/// Trailing comment of `line`, found after `part`.
+fn comment_after<'a>(line: &'a str, part: &'a str) -> Option<&'a str> {
+    let end = part.as_ptr() as usize - line.as_ptr() as usize + part.len();
+    Some(line[end..].trim()).filter(|c| !c.is_empty())
+}
+
+impl<'a> KeeDocument<'a> {
+    pub fn parse(source: &'a str) -> Self {
+        let lines = source
+            .split_inclusive('\n')
+            .map(|raw| KeeLine {
+                raw,
+                node: Self::node(raw.trim()),
+            })
+            .collect();
+        let newline = if source.contains("\r\n") {
+            "\r\n"
+        } else {
+            "\n"
+        };
+        Self { lines, newline }
+    }
+
+    fn node(line: &'a str) -> KeeNode<'a> {
+        if line.is_empty() {
+            return KeeNode::Blank;
+        }
+        if KeeFuncLexer::is_comment(line) {
+            return KeeNode::Comment(line);
+        }
+        if let Some(name) = KeeFuncLexer::section_header(line) {
+            return KeeNode::Section(name);
+        }
+        let code = KeeFuncLexer::strip_comment(line);
+        if let Some(name) = KeeFuncLexer::mode_header(code) {
+            return KeeNode::Mode {
+                name,
+                comment: comment_after(line, code),
+            };
+        }
+        if code == "}" {
+            return KeeNode::ModeEnd(comment_after(line, code));
+        }
+        if let Some((name, value)) = KeeFuncLexer::let_declaration(line) {
+            if name.is_empty() || value.is_empty() {
+                return KeeNode::Unknown(line);
+            }
+            return KeeNode::Let {
+                name,
+                value,
+                comment: comment_after(line, value),
+            };
+        }
+        match line.split_once('=') {
+            Some((kee, rest)) if !kee.trim().is_empty() => {
+                let rest = rest.trim();
+                let func = KeeFuncLexer::strip_comment(rest);
+                KeeNode::Binding {
+                    kee: kee.trim(),
+                    func,
+                    comment: comment_after(rest, func),
+                }
+            }
+            _ => KeeNode::Unknown(line),
+        }
+    }
+
+    /// `S-M-a` -> `M-S-a`, left as written when it doesn't parse
+    fn canonical_kee(kee: &str) -> String {
+        TsckKeeSequence::parse(kee).map_or_else(|_| kee.to_string(), |s| s.to_string())
+    }
+
+    /// Canonical layout: modifiers in `M-S-C-A` order, bindings sorted and
+    /// their `=` aligned within each section, mode keys indented and aligned
+    /// on their own, single blank lines. Comments above a binding move with it.
+    pub fn format(&self) -> String {
+        let mut out = Vec::new();
+        let mut start = 0;
+        for end in 1..=self.lines.len() {
+            if end == self.lines.len() || matches!(self.lines[end].node, KeeNode::Section(_)) {
+                Self::format_section(&self.lines[start..end], &mut out);
+                start = end;
+            }
+        }
+        while out.last().is_some_and(String::is_empty) {
+            out.pop();
+        }
+        let mut formatted = out.join(self.newline);
+        formatted.push_str(self.newline);
+        formatted
+    }
+
+    fn format_section(lines: &[KeeLine<'a>], out: &mut Vec<String>) {
+        // widest key outside and inside mode blocks
+        let (mut width, mut mode_width) = (0, 0);
+        let mut in_mode = false;
+        for line in lines {
+            match line.node {
+                KeeNode::Mode { .. } => in_mode = true,
+                KeeNode::ModeEnd(_) => in_mode = false,
+                KeeNode::Binding { kee, .. } => {
+                    let len = Self::canonical_kee(kee).chars().count();
+                    let width = if in_mode { &mut mode_width } else { &mut width };
+                    *width = (*width).max(len);
+                }
+                _ => {}
+            }
+        }
+        in_mode = false;
+        let with_comment = |text: String, comment: Option<&str>| match comment {
+            Some(comment) => format!("{} {}", text, comment),
+            None => text,
+        };
+
+        // (sort key, comment lines + the binding)
+        let mut bindings: Vec<(String, Vec<String>)> = Vec::new();
+        let mut comments = Vec::new();
+        let flush = |bindings: &mut Vec<(String, Vec<String>)>,
+                     comments: &mut Vec<String>,
+                     out: &mut Vec<String>| {
+            bindings.sort_by(|a, b| a.0.cmp(&b.0));
+            out.extend(bindings.drain(..).flat_map(|(_, lines)| lines));
+            out.append(comments);
+        };
+        for line in lines {
+            match &line.node {
+                KeeNode::Comment(comment) => comments.push(comment.to_string()),
+                KeeNode::Binding { kee, func, comment } => {
+                    let kee = Self::canonical_kee(kee);
+                    let func = FuncLexer::split_chain(func).join(" ; ");
+                    let text = match in_mode {
+                        true => format!("    {:mode_width$} = {}", kee, func),
+                        false => format!("{:width$} = {}", kee, func),
+                    };
+                    let mut item = std::mem::take(&mut comments);
+                    item.push(with_comment(text.trim_end().to_string(), *comment));
+                    bindings.push((kee, item));
+                }
+                node => {
+                    flush(&mut bindings, &mut comments, out);
+                    match node {
+                        KeeNode::Blank => {
+                            if out.last().is_some_and(|last| !last.is_empty()) {
+                                out.push(String::new());
+                            }
+                        }
+                        KeeNode::Section(name) => out.push(format!("[{}]", name)),
+                        KeeNode::Mode { name, comment } => {
+                            in_mode = true;
+                            out.push(with_comment(format!("mode {} {{", name), *comment));
+                        }
+                        KeeNode::ModeEnd(comment) => {
+                            in_mode = false;
+                            out.push(with_comment("}".to_string(), *comment));
+                        }
+                        KeeNode::Let {
+                            name,
+                            value,
+                            comment,
+                        } => out.push(with_comment(format!("let {} = {}", name, value), *comment)),
+                        KeeNode::Unknown(text) => out.push(text.to_string()),
+                        KeeNode::Comment(_) | KeeNode::Binding { .. } => unreachable!(),
+                    }
+                }
+            }
+        }
+        flush(&mut bindings, &mut comments, out);
+    }
+}
+
+impl std::fmt::Display for KeeDocument<'_> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        self.lines.iter().try_for_each(|line| f.write_str(line.raw))
+    }
+}
+
+#[cfg(test)]
+mod kee_cst {
+    use super::*;
+
+    #[test]
+    fn test_lossless() {
+        let input = include_str!("../../kee.kee");
+        let document = KeeDocument::parse(input);
+        assert_eq!(document.to_string(), input);
+        let formatted = document.format();
+        assert_eq!(KeeDocument::parse(&formatted).format(), formatted);
+        assert_eq!(
+            KeeDocument::parse("  [ app ]\nM-1 =app::PAGE(1)").to_string(),
+            "  [ app ]\nM-1 =app::PAGE(1)"
+        );
+    }
+
+    #[test]
+    fn test_format_modes() {
+        let input = "M-r = mode(resize)\nmode   resize{ # i3 like\nl = workspace::RESIZEACTIVEWINDOW('INC','WIDTH')\n  left = workspace::RESIZEACTIVEWINDOW('DEC','WIDTH')\n} # done\n";
+        assert_eq!(
+            KeeDocument::parse(input).format(),
+            "M-r = mode(resize)\nmode resize { # i3 like\n    l    = workspace::RESIZEACTIVEWINDOW('INC','WIDTH')\n    left = workspace::RESIZEACTIVEWINDOW('DEC','WIDTH')\n} # done\n"
+        );
+    }
+
+    #[test]
+    fn test_format() {
+        let input = "# header\n\n\nlet inc  =  120 # px\n[ workspace ]\n# cycle\nS-M-up = workspace::CYCLEWORKSPACE\nM-left= workspace::MOVEACTIVEWINDOW('LEFT', by=$inc)  # move\n\nM-k c = app::PAGE(1);delay(200) ;app::PAGE(2)\nA-M-tab = app::CYCLEAPPS\n# dangling\n\n[app]\n  M-2 = app::PAGE(2)\nM-1 = app::PAGE(1)\nnonsense\n\n\n";
+        assert_eq!(
+            KeeDocument::parse(input).format(),
+            "# header\n\nlet inc = 120 # px\n[workspace]\n# cycle\nM-S-up  = workspace::CYCLEWORKSPACE\nM-left  = workspace::MOVEACTIVEWINDOW('LEFT', by=$inc) # move\n\nM-A-tab = app::CYCLEAPPS\nM-k c   = app::PAGE(1) ; delay(200) ; app::PAGE(2)\n# dangling\n\n[app]\nM-1 = app::PAGE(1)\nM-2 = app::PAGE(2)\nnonsense\n"
+        );
+    }
+}
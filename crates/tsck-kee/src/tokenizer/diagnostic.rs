@@ -0,0 +1,109 @@
+/// Byte range inside the kee.kee source.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct Span {
+    pub start: usize,
+    pub end: usize,
+}
+
+impl Span {
+    pub fn new(start: usize, end: usize) -> Self {
+        Self { start, end }
+    }
+
+    /// Span of `part`, which must be a sub-slice of `source`.
+    pub fn of(source: &str, part: &str) -> Self {
+        let start = part.as_ptr() as usize - source.as_ptr() as usize;
+        Self::new(start, start + part.len())
+    }
+
+    pub fn len(&self) -> usize {
+        self.end - self.start
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.start == self.end
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Severity {
+    /// The binding is skipped
+    #[default]
+    Error,
+    /// The binding is loaded, but probably not doing what was meant
+    Warning,
+}
+
+impl std::fmt::Display for Severity {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Severity::Error => f.write_str("error"),
+            Severity::Warning => f.write_str("warning"),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct KeeDiagnostic {
+    pub severity: Severity,
+    /// 1-based line number
+    pub line: usize,
+    /// 1-based column, counted in chars
+    pub column: usize,
+    pub span: Span,
+    pub message: String,
+}
+
+impl KeeDiagnostic {
+    pub fn new(source: &str, span: Span, message: impl Into<String>) -> Self {
+        let line_start = source[..span.start].rfind('\n').map_or(0, |p| p + 1);
+        Self {
+            severity: Severity::Error,
+            line: source[..span.start].matches('\n').count() + 1,
+            column: source[line_start..span.start].chars().count() + 1,
+            span,
+            message: message.into(),
+        }
+    }
+
+    pub fn warning(source: &str, span: Span, message: impl Into<String>) -> Self {
+        Self {
+            severity: Severity::Warning,
+            ..Self::new(source, span, message)
+        }
+    }
+
+    pub fn is_error(&self) -> bool {
+        self.severity == Severity::Error
+    }
+
+    /// Compiler-style report with the offending line and a caret underline.
+    pub fn render(&self, file_name: &str, source: &str) -> String {
+        let line_text = source.lines().nth(self.line - 1).unwrap_or_default();
+        let gutter = self.line.to_string().len();
+        let width = source
+            .get(self.span.start..self.span.end)
+            .map_or(1, |s| s.chars().count().max(1));
+        format!(
+            "{}: {}\n{:gutter$}--> {}:{}:{}\n{:gutter$} |\n{} | {}\n{:gutter$} | {}{}",
+            self.severity,
+            self.message,
+            "",
+            file_name,
+            self.line,
+            self.column,
+            "",
+            self.line,
+            line_text,
+            "",
+            " ".repeat(self.column - 1),
+            "^".repeat(width),
+        )
+    }
+}
+
+impl std::fmt::Display for KeeDiagnostic {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}:{}: {}", self.line, self.column, self.message)
+    }
+}
@@ -1,6 +1,6 @@
 #![allow(unused)]
-use crate::tokenizer::func_lexer::{Func, FuncExpr, FuncLexer};
 use tsck_derive::{FuncParser, ScopeParser};
+use tsck_utils::{ArgSchema, Func, FuncExpr, FuncLexer, FuncSchema, ScopeSchema};
 
 #[derive(Debug, FuncParser)]
 enum WorkspaceFunc {
@@ -27,6 +27,53 @@ enum TestFunc {
     SomethingUseFull(String, String),
     SomethingNone(i32),
 }
+#[derive(Debug, PartialEq, FuncParser)]
+enum WindowFunc {
+    Move {
+        dir: String,
+        #[func(default = 80)]
+        by: i32,
+        #[func(default = 150)]
+        duration: u64,
+    },
+    Resize(
+        #[func(name = "prop")] String,
+        #[func(name = "by", default = 40)] i32,
+    ),
+    Title(#[func(default = "untitled")] String),
+}
+
+#[derive(Debug, PartialEq, FuncParser)]
+enum RichFunc {
+    Place(String, i32),
+    Snap {
+        at: (i32, i32),
+        #[func(default = true)]
+        animate: bool,
+        scale: Option<f32>,
+    },
+    Cycle(Vec<String>),
+    Open(String, Option<String>),
+}
+
+#[derive(Debug, PartialEq, FuncParser)]
+enum AliasFunc {
+    /// Bring the app to the front
+    #[func(rename = "APPTOFRONT", alias = "front")]
+    BringToFront(String),
+    /// Zoom in by `by` percent
+    ///
+    /// Defaults to 50
+    Zoom(#[func(name = "by", default = 50)] i32),
+    Close,
+}
+
+#[derive(Debug, ScopeParser)]
+enum AliasEntries {
+    /// Window layout
+    #[scope(name = "ws", alias = "workspace")]
+    Workspace(AliasFunc),
+}
 
 #[derive(Debug, ScopeParser)]
 enum TestEntries {
@@ -46,9 +93,12 @@ mod kee_func {
     use std::{str::FromStr, time::Instant};
 
     use crate::{
-        FuncExpr, FuncLexer,
+        FuncExpr, FuncLexer, ScopeSchema,
         tokenizer::{
-            func::{AppFunc, FuncEntries, TestEntries, TestFunc, WorkspaceFunc},
+            func::{
+                AliasEntries, AliasFunc, AppFunc, FuncEntries, RichFunc, TestEntries, TestFunc,
+                WindowFunc, WorkspaceFunc,
+            },
             lexer::KeeParser,
         },
     };
@@ -61,7 +111,7 @@ M-2 = app::SomethingNone(300)
         let lexer = KeeParser::new(input);
         let kf = lexer.parse();
         for input in kf {
-            if let Ok(entries) = TestEntries::parse(input.func) {
+            if let Ok(entries) = TestEntries::parse(&input.func) {
                 match entries {
                     TestEntries::App(test_func) => match test_func {
                         TestFunc::SomethingUseFull(a, b) => {
@@ -85,15 +135,269 @@ M-2 = app::SomethingNone(300)
             "app::Str('createNewDocument')",
         ];
         for input in inputs {
-            if let Some(et) = FuncLexer::parse_func(input) {
-                if let Some(FuncExpr::TupleString(a, b)) = et.args {
-                    eprintln!("TUPLESTR::: {}{}", a, b);
-                }
-            }
+            assert!(FuncLexer::parse_func(input).is_some(), "{}", input);
         }
+        let tuple = FuncLexer::parse_func(inputs[0]).map(|f| f.args);
+        assert!(matches!(tuple.as_deref(), Some([FuncExpr::Tuple(items)]) if items.len() == 2));
+        let parsed = TestEntries::parse("app::SomethingUseFull('IC', \"WIDTH\")")
+            .map_err(anyhow::Error::msg)?;
+        assert!(matches!(
+            parsed,
+            TestEntries::App(TestFunc::SomethingUseFull(a, b)) if a == "IC" && b == "WIDTH"
+        ));
+        let parsed = TestEntries::parse("app::SomethingUseFull(('IC','WIDTH'))")
+            .map_err(anyhow::Error::msg)?;
+        assert!(matches!(
+            parsed,
+            TestEntries::App(TestFunc::SomethingUseFull(a, b)) if a == "IC" && b == "WIDTH"
+        ));
+        assert!(TestEntries::parse("app::SomethingNone(-40)").is_ok());
+        assert!(TestEntries::parse("app::SomethingNone('40')").is_err());
+        assert!(TestEntries::parse("app::SomethingNone(1, 2)").is_err());
         Ok(())
     }
     #[test]
+    fn test_named_args() {
+        let window = |s| FuncLexer::parse_func(s).map(|f| WindowFunc::from_func(&f));
+        assert_eq!(
+            window("w::MOVE(dir='LEFT', by=120, duration=200)"),
+            Some(Ok(WindowFunc::Move {
+                dir: "LEFT".into(),
+                by: 120,
+                duration: 200
+            }))
+        );
+        assert_eq!(
+            window("w::MOVE('UP', duration=10)"),
+            Some(Ok(WindowFunc::Move {
+                dir: "UP".into(),
+                by: 80,
+                duration: 10
+            }))
+        );
+        assert_eq!(
+            window("w::RESIZE(by=-40, prop='WIDTH')"),
+            Some(Ok(WindowFunc::Resize("WIDTH".into(), -40)))
+        );
+        assert_eq!(
+            window("w::RESIZE('HEIGHT')"),
+            Some(Ok(WindowFunc::Resize("HEIGHT".into(), 40)))
+        );
+        assert_eq!(
+            window("w::TITLE"),
+            Some(Ok(WindowFunc::Title("untitled".into())))
+        );
+        assert_eq!(
+            window("w::MOVE(by=1)"),
+            Some(Err("move missing argument 1 `dir` (String)".into()))
+        );
+        assert_eq!(
+            window("w::MOVE('UP', by='far')"),
+            Some(Err(
+                "move argument 2 `by` (i32): expected a number, found a string".into()
+            ))
+        );
+        assert_eq!(
+            window("w::MOVE('UP', speed=1)"),
+            Some(Err("move: unknown argument `speed`".into()))
+        );
+    }
+
+    #[test]
+    fn test_rich_shapes() {
+        let rich = |s: &str| FuncLexer::parse_func(s).map(|f| RichFunc::from_func(&f));
+        assert_eq!(
+            rich("r::PLACE('LEFT', 40)"),
+            Some(Ok(RichFunc::Place("LEFT".into(), 40)))
+        );
+        assert_eq!(
+            rich("r::SNAP(at=(10, 20))"),
+            Some(Ok(RichFunc::Snap {
+                at: (10, 20),
+                animate: true,
+                scale: None
+            }))
+        );
+        assert_eq!(
+            rich("r::SNAP([1, 2], false, 0.5)"),
+            Some(Ok(RichFunc::Snap {
+                at: (1, 2),
+                animate: false,
+                scale: Some(0.5)
+            }))
+        );
+        assert_eq!(
+            rich("r::CYCLE(['a', b])"),
+            Some(Ok(RichFunc::Cycle(vec!["a".into(), "b".into()])))
+        );
+        assert_eq!(
+            rich("r::OPEN('x')"),
+            Some(Ok(RichFunc::Open("x".into(), None)))
+        );
+        assert_eq!(
+            rich("r::PLACE(40, 'LEFT')"),
+            Some(Err(
+                "place argument 1 (String): expected a string, found a number".into()
+            ))
+        );
+        assert_eq!(
+            rich("r::CYCLE([1])"),
+            Some(Err(
+                "cycle argument 1 (Vec<String>): item 1: expected a string, found a number".into()
+            ))
+        );
+        assert_eq!(
+            rich("r::SNAP(at=1)"),
+            Some(Err(
+                "snap argument 1 `at` ((i32,i32)): expected a tuple, found a number".into()
+            ))
+        );
+        assert_eq!(
+            rich("r::SNAP(scale=1)"),
+            Some(Err("snap missing argument 1 `at` ((i32,i32))".into()))
+        );
+
+        for input in [
+            "PLACE('LEFT', 40)",
+            "SNAP((1, 2), true)",
+            "SNAP((1, 2), false, 0.5)",
+            "CYCLE(['a', 'b'])",
+            "OPEN('x')",
+            "OPEN('x', 'y')",
+        ] {
+            let parsed = rich(&format!("r::{}", input)).unwrap().unwrap();
+            assert_eq!(parsed.to_string(), input);
+        }
+    }
+
+    #[test]
+    fn test_attributes() {
+        let front = AliasEntries::parse("ws::APPTOFRONT('x')").unwrap();
+        assert!(matches!(
+            &front,
+            AliasEntries::Workspace(AliasFunc::BringToFront(app)) if app == "x"
+        ));
+        assert_eq!(front.to_string(), "ws::APPTOFRONT('x')");
+        assert!(AliasEntries::parse("workspace::front('x')").is_ok());
+        // the Rust name no longer parses once renamed
+        assert!(AliasEntries::parse("ws::BRINGTOFRONT('x')").is_err());
+        assert!(matches!(
+            AliasEntries::parse("WS::ZOOM"),
+            Ok(AliasEntries::Workspace(AliasFunc::Zoom(50)))
+        ));
+        assert_eq!(
+            AliasEntries::parse("ws::ZOOM(by=5)").unwrap().to_string(),
+            "ws::ZOOM(5)"
+        );
+
+        assert_eq!(AliasEntries::help("ws"), Some("Window layout"));
+        assert_eq!(
+            AliasEntries::help("workspace::FRONT('x')"),
+            Some("Bring the app to the front")
+        );
+        assert_eq!(
+            AliasFunc::help("zoom"),
+            Some("Zoom in by `by` percent\n\nDefaults to 50")
+        );
+        assert_eq!(AliasEntries::help("ws::CLOSE"), None);
+        assert_eq!(AliasEntries::help("ws::NOPE"), None);
+        assert_eq!(AliasEntries::help("app::ZOOM"), None);
+    }
+
+    #[test]
+    fn test_did_you_mean() {
+        let error = |s| FuncEntries::parse(s).unwrap_err();
+        assert_eq!(
+            error("app::CYCLEAP"),
+            "Unknown AppFunc variant: CYCLEAP (did you mean CYCLEAPPS?)"
+        );
+        assert_eq!(
+            error("ap::CYCLEAPPS"),
+            "Unknown scope: ap (did you mean app?)"
+        );
+        assert_eq!(error("app::NOPE"), "Unknown AppFunc variant: NOPE");
+        assert_eq!(
+            AliasEntries::parse("ws::FORNT('x')").unwrap_err(),
+            "Unknown AliasFunc variant: FORNT (did you mean front?)"
+        );
+    }
+
+    #[test]
+    fn test_schema() {
+        let scopes = AliasEntries::schema();
+        assert_eq!(scopes.len(), 1);
+        assert_eq!(scopes[0].name, "ws");
+        assert_eq!(scopes[0].aliases, ["workspace"]);
+        assert_eq!(scopes[0].doc, Some("Window layout"));
+        let front = scopes[0].func("front").unwrap();
+        assert_eq!(front.name, "APPTOFRONT");
+        assert_eq!(front.signature(), "APPTOFRONT(String)");
+        assert_eq!(
+            scopes[0].func("zoom").unwrap().signature(),
+            "ZOOM(by: i32 = 50)"
+        );
+        assert_eq!(scopes[0].func("close").unwrap().args, []);
+
+        let moved = WindowFunc::schema()[0];
+        assert_eq!(
+            moved.signature(),
+            "MOVE(dir: String, by: i32 = 80, duration: u64 = 150)"
+        );
+        assert!(!moved.args[0].optional && moved.args[1].optional);
+        assert_eq!(WindowFunc::schema()[2].args[0].default, Some("'untitled'"));
+        let snap = RichFunc::schema()[1].args;
+        assert_eq!(snap[0].ty, "(i32,i32)");
+        assert_eq!((snap[2].ty, snap[2].optional), ("Option<f32>", true));
+
+        let json: serde_json::Value =
+            serde_json::from_str(&ScopeSchema::to_json(AliasEntries::schema())).unwrap();
+        assert_eq!(json[0]["funcs"][1]["args"][0]["name"], "by");
+        assert_eq!(json[0]["funcs"][1]["args"][0]["default"], "50");
+    }
+
+    #[test]
+    fn test_display() {
+        let window = |s| FuncLexer::parse_func(s).map(|f| WindowFunc::from_func(&f).unwrap());
+        let moved = window("w::MOVE(by=120, dir='LEFT')").unwrap();
+        assert_eq!(moved.to_string(), "MOVE('LEFT', 120, 150)");
+        assert_eq!(window("w::TITLE").unwrap().to_string(), "TITLE('untitled')");
+
+        for input in [
+            "app::SCRIPT('it\\'s.js')",
+            "app::RELOADCONFIG",
+            "workspace::CYCLEWORKSPACE",
+        ] {
+            let entries = FuncEntries::parse(input).unwrap();
+            assert_eq!(entries.to_string(), input);
+        }
+        let entries = TestEntries::parse("APP::SomethingUseFull(('IC','WIDTH'))").unwrap();
+        assert_eq!(entries.to_string(), "app::SOMETHINGUSEFULL('IC', 'WIDTH')");
+    }
+
+    #[test]
+    fn test_chain() {
+        let input = "app::FUNCCALL('Save') ; delay(250);app::SCRIPT('a;b.js') ; ";
+        let steps = FuncLexer::split_chain(input);
+        assert_eq!(
+            steps,
+            vec![
+                "app::FUNCCALL('Save')",
+                "delay(250)",
+                "app::SCRIPT('a;b.js')",
+                ""
+            ]
+        );
+        assert_eq!(FuncLexer::parse_delay(steps[1]), Some(250));
+        assert_eq!(FuncLexer::parse_delay("DELAY( 10 )"), Some(10));
+        assert_eq!(FuncLexer::parse_delay("delay('10')"), None);
+        assert_eq!(FuncLexer::parse_delay("delay(10) x"), None);
+        assert_eq!(FuncLexer::split_chain("app::PAGE(1)"), vec!["app::PAGE(1)"]);
+        assert!(matches!(
+            FuncEntries::parse(steps[2]),
+            Ok(FuncEntries::App(AppFunc::Script(s))) if s == "a;b.js"
+        ));
+    }
+    #[test]
     fn test_lexer() -> anyhow::Result<()> {
         let input = include_str!("../../kee.kee");
         let lexer = KeeParser::new(input);
@@ -108,7 +412,7 @@ M-2 = app::SomethingNone(300)
             let tk = kf.iter().as_ref().iter().map(|c| c.kee).collect::<Vec<_>>();
             //println!("{:?}", tk);
             for input in kf {
-                if let Ok(entries) = FuncEntries::parse(input.func) {
+                if let Ok(entries) = FuncEntries::parse(&input.func) {
                     match entries {
                         FuncEntries::App(app_func) => match app_func {
                             AppFunc::Script(_) => {
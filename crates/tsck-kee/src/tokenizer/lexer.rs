@@ -1,55 +1,348 @@
 #![allow(unused)]
 
-use crate::Kee;
+use std::borrow::Cow;
+
+use tsck_utils::{FuncLexer, did_you_mean};
+
+use crate::{
+    Kee,
+    kee_conflict::{KeeConflict, find_conflicts},
+    kee_sequence::TsckKeeSequence,
+    kee_trigger::KeeTrigger,
+    tokenizer::{
+        diagnostic::{KeeDiagnostic, Span},
+        variable::KeeVars,
+    },
+};
 
 #[derive(Debug)]
 pub struct KeeFunc<'a> {
     pub kee: &'a str,
-    pub func: &'a str,
+    /// Function part with `$variables` substituted
+    pub func: Cow<'a, str>,
+    /// Name of the enclosing `[section]` header, if any
+    pub section: Option<&'a str>,
+    /// Name of the enclosing `mode name { ... }` block, if any
+    pub mode: Option<&'a str>,
+    pub line: usize,
+    pub kee_span: Span,
+    pub func_span: Span,
 }
 
 #[derive(Debug, Clone)]
-struct KeeFuncLexer<'a> {
+pub(crate) struct KeeFuncLexer<'a> {
     content: &'a str,
+    vars: KeeVars,
 }
 
 impl<'a> KeeFuncLexer<'a> {
-    fn new(content: &'a str) -> Self {
-        Self { content }
+    fn new(content: &'a str, vars: KeeVars) -> Self {
+        Self { content, vars }
+    }
+    pub(crate) fn is_comment(line: &str) -> bool {
+        line.starts_with('#') || line.starts_with("//")
+    }
+
+    /// `[name]` header, a line on its own without any `=`.
+    pub(crate) fn section_header(line: &'a str) -> Option<&'a str> {
+        if line.contains('=') {
+            return None;
+        }
+        line.strip_prefix('[')?.strip_suffix(']').map(str::trim)
+    }
+
+    /// `mode name {` opening a block of mode keys.
+    pub(crate) fn mode_header(line: &'a str) -> Option<&'a str> {
+        let rest = line.strip_prefix("mode")?;
+        if !rest.starts_with(char::is_whitespace) || line.contains('=') {
+            return None;
+        }
+        rest.strip_suffix('{').map(str::trim)
     }
-    fn parse_line(&self, line: &'a str) -> KeeFunc<'a> {
+
+    /// Cut a trailing `# ...` / `// ...` comment, ignoring anything quoted.
+    pub(crate) fn strip_comment(func: &'a str) -> &'a str {
+        let mut quote = None;
+        let mut prev = ' ';
+        for (i, c) in func.char_indices() {
+            match quote {
+                Some(q) if c == q && prev != '\\' => quote = None,
+                Some(_) => {}
+                None if c == '\'' || c == '"' => quote = Some(c),
+                None if prev.is_whitespace() && (c == '#' || func[i..].starts_with("//")) => {
+                    return func[..i].trim_end();
+                }
+                None => {}
+            }
+            prev = c;
+        }
+        func
+    }
+
+    fn parse_line(
+        &self,
+        line_no: usize,
+        line: &'a str,
+        section: Option<&'a str>,
+        mode: Option<&'a str>,
+    ) -> KeeFunc<'a> {
         if let Some(pos) = line.find("=") {
             let kee = line[..pos].trim();
-            let func = line[pos + 1..].trim();
+            let func = Self::strip_comment(line[pos + 1..].trim());
 
-            KeeFunc { kee, func }
+            KeeFunc {
+                kee,
+                func: Cow::Borrowed(func),
+                section,
+                mode,
+                line: line_no,
+                kee_span: Span::of(self.content, kee),
+                func_span: Span::of(self.content, func),
+            }
         } else {
+            let start = Span::of(self.content, line).start;
             KeeFunc {
                 kee: "",
-                func: line,
+                func: Cow::Borrowed(line),
+                section,
+                mode,
+                line: line_no,
+                kee_span: Span::new(start, start),
+                func_span: Span::of(self.content, line),
             }
         }
     }
 
-    fn parse(&self) -> Vec<KeeFunc<'a>> {
-        self.content
-            .lines()
-            .map(|line| self.parse_line(line))
-            .collect()
+    /// `let name = value`, returns the name and the raw value.
+    pub(crate) fn let_declaration(line: &'a str) -> Option<(&'a str, &'a str)> {
+        let rest = line.strip_prefix("let")?;
+        if !rest.starts_with(char::is_whitespace) {
+            return None;
+        }
+        let (name, value) = rest.split_once('=').unwrap_or((rest, ""));
+        Some((name.trim(), Self::strip_comment(value.trim())))
+    }
+
+    fn declare(
+        &mut self,
+        line: &'a str,
+        name: &'a str,
+        value: &'a str,
+    ) -> Result<(), KeeDiagnostic> {
+        let error = |part: &'a str, message: String| {
+            KeeDiagnostic::new(self.content, Span::of(self.content, part), message)
+        };
+        if value.is_empty() {
+            return Err(error(line, "expected `let <name> = <value>`".into()));
+        }
+        if !KeeVars::is_valid_name(name) {
+            return Err(error(name, format!("invalid variable name `{}`", name)));
+        }
+        let value = self.vars.expand(value).map_err(|(span, message)| {
+            let start = Span::of(self.content, value).start;
+            KeeDiagnostic::new(
+                self.content,
+                Span::new(start + span.start, start + span.end),
+                message,
+            )
+        })?;
+        self.vars.define(name, value);
+        Ok(())
+    }
+
+    fn parse(mut self) -> (Vec<KeeFunc<'a>>, Vec<KeeDiagnostic>) {
+        let mut section = None;
+        // name and header line of the open `mode name {` block
+        let mut mode: Option<(&'a str, &'a str)> = None;
+        let mut kees = Vec::new();
+        let mut diagnostics = Vec::new();
+        let content = self.content;
+        let error = |part: &'a str, message: String| {
+            KeeDiagnostic::new(content, Span::of(content, part), message)
+        };
+        for (i, line) in self.content.lines().enumerate() {
+            let trimmed = line.trim();
+            if trimmed.is_empty() || Self::is_comment(trimmed) {
+                continue;
+            }
+            if let Some(name) = Self::section_header(trimmed) {
+                if let Some((open, _)) = mode {
+                    diagnostics.push(error(
+                        trimmed,
+                        format!("`[{}]` inside `mode {}`, close the mode first", name, open),
+                    ));
+                }
+                section = Some(name);
+                continue;
+            }
+            if let Some(name) = Self::mode_header(Self::strip_comment(trimmed)) {
+                if let Some((open, _)) = mode {
+                    diagnostics.push(error(
+                        trimmed,
+                        format!(
+                            "`mode {}` is inside `mode {}`, modes can't be nested",
+                            name, open
+                        ),
+                    ));
+                } else if !KeeVars::is_valid_name(name) {
+                    diagnostics.push(error(trimmed, format!("invalid mode name `{}`", name)));
+                }
+                mode = Some((name, trimmed));
+                continue;
+            }
+            if Self::strip_comment(trimmed) == "}" {
+                if mode.take().is_none() {
+                    diagnostics.push(error(
+                        trimmed,
+                        "unexpected `}`, no `mode` block is open".into(),
+                    ));
+                }
+                continue;
+            }
+            if let Some((name, value)) = Self::let_declaration(trimmed) {
+                if let Err(diagnostic) = self.declare(trimmed, name, value) {
+                    diagnostics.push(diagnostic);
+                }
+                continue;
+            }
+            let mut kee = self.parse_line(i + 1, trimmed, section, mode.map(|(name, _)| name));
+            match self.vars.expand(&kee.func) {
+                Ok(Cow::Owned(func)) => kee.func = Cow::Owned(func),
+                Ok(Cow::Borrowed(_)) => {}
+                Err((span, message)) => {
+                    let start = kee.func_span.start;
+                    diagnostics.push(KeeDiagnostic::new(
+                        self.content,
+                        Span::new(start + span.start, start + span.end),
+                        message,
+                    ));
+                }
+            }
+            kees.push(kee);
+        }
+        if let Some((name, header)) = mode {
+            diagnostics.push(error(
+                header,
+                format!("`mode {}` is never closed, expected `}}`", name),
+            ));
+        }
+        (kees, diagnostics)
     }
 }
 
 pub struct KeeParser<'a> {
     input: &'a str,
+    vars: KeeVars,
 }
 
 impl<'a> KeeParser<'a> {
     pub fn new(input: &'a str) -> Self {
-        Self { input }
+        Self {
+            input,
+            vars: KeeVars::default(),
+        }
+    }
+
+    /// Resolve `${env:VAR}` with `env` instead of the process environment.
+    pub fn with_env(mut self, env: fn(&str) -> Option<String>) -> Self {
+        self.vars = KeeVars::new(env);
+        self
     }
+
     pub fn parse(&self) -> Vec<KeeFunc<'a>> {
-        let lexer = KeeFuncLexer::new(self.input);
-        lexer.parse()
+        KeeFuncLexer::new(self.input, self.vars.clone()).parse().0
+    }
+
+    /// Validate every binding. `validate` receives each step of the function
+    /// part (e.g. `app::PAGE(2)`) and returns the scope parser's error, if any.
+    pub fn check<F>(&self, validate: F) -> Vec<KeeDiagnostic>
+    where
+        F: Fn(&str) -> Result<(), String>,
+    {
+        let (kees, mut diagnostics) = KeeFuncLexer::new(self.input, self.vars.clone()).parse();
+        // functions whose `$variables` didn't expand, reported already
+        let unexpanded: Vec<usize> = diagnostics.iter().map(|d| d.line).collect();
+        let modes: Vec<_> = kees.iter().filter_map(|k| k.mode).collect();
+        for kf in kees {
+            if kf.kee.is_empty() {
+                diagnostics.push(KeeDiagnostic::new(
+                    self.input,
+                    kf.func_span,
+                    "expected `<keys> = <scope>::<function>`",
+                ));
+                continue;
+            }
+            match TsckKeeSequence::parse(kf.kee) {
+                Err(err) => {
+                    diagnostics.push(KeeDiagnostic::new(self.input, kf.kee_span, err.to_string()))
+                }
+                Ok(sequence) => {
+                    if let Some(mode) = kf.mode
+                        && !Self::is_plain_key(&sequence)
+                    {
+                        diagnostics.push(KeeDiagnostic::new(
+                            self.input,
+                            kf.kee_span,
+                            format!(
+                                "keys of `mode {}` are single keys without modifiers, triggers or app",
+                                mode
+                            ),
+                        ));
+                    }
+                }
+            }
+            if kf.func.is_empty() {
+                diagnostics.push(KeeDiagnostic::new(
+                    self.input,
+                    kf.func_span,
+                    format!("missing function for `{}`", kf.kee),
+                ));
+                continue;
+            }
+            if unexpanded.contains(&kf.line) {
+                continue;
+            }
+            for step in FuncLexer::split_chain(&kf.func) {
+                // substituted text no longer lives in the source, point at the whole function
+                let span = match kf.func {
+                    Cow::Borrowed(_) => Span::of(self.input, step),
+                    Cow::Owned(_) => kf.func_span,
+                };
+                if step.is_empty() {
+                    diagnostics.push(KeeDiagnostic::new(self.input, span, "empty step in chain"));
+                } else if let Some(mode) = FuncLexer::parse_mode(step) {
+                    if !modes.contains(&mode) {
+                        let hint = did_you_mean(mode, modes.iter().copied())
+                            .map(|name| format!(" (did you mean {}?)", name))
+                            .unwrap_or_default();
+                        diagnostics.push(KeeDiagnostic::new(
+                            self.input,
+                            span,
+                            format!("unknown mode `{}`{}", mode, hint),
+                        ));
+                    }
+                } else if FuncLexer::parse_delay(step).is_none()
+                    && let Err(err) = validate(step)
+                {
+                    diagnostics.push(KeeDiagnostic::new(self.input, span, err));
+                }
+            }
+        }
+        diagnostics
+    }
+
+    /// A key of a mode block: one stroke, no modifiers, trigger or app
+    fn is_plain_key(sequence: &TsckKeeSequence) -> bool {
+        sequence.strokes.len() == 1
+            && sequence.strokes[0].modifiers.to_flags() == 0
+            && sequence.trigger == KeeTrigger::Press
+            && !sequence.repeat
+            && sequence.app.is_none()
+    }
+
+    /// Duplicate, shadowed and OS-reserved bindings, see `find_conflicts`.
+    pub fn conflicts(&self) -> Vec<KeeConflict> {
+        find_conflicts(&self.parse())
     }
 }
 
@@ -66,4 +359,162 @@ mod kee_lexer {
         //     println!("{:?}", part);
         // }
     }
+
+    #[test]
+    fn test_check() {
+        let input = "M-1 = app::PAGE(1)\nM-foo = app::PAGE(2)\nM-2 app::PAGE(3)\nM-3 = nope::PAGE(4)\nM-k c = app::PAGE(5)\n";
+        let diagnostics = KeeParser::new(input).check(|func| {
+            if func.starts_with("app::") {
+                Ok(())
+            } else {
+                Err(format!("Unknown scope: {}", func))
+            }
+        });
+        assert_eq!(diagnostics.len(), 3);
+        assert_eq!((diagnostics[0].line, diagnostics[0].column), (2, 1));
+        assert_eq!(
+            &input[diagnostics[0].span.start..diagnostics[0].span.end],
+            "M-foo"
+        );
+        assert_eq!((diagnostics[1].line, diagnostics[1].column), (3, 1));
+        assert_eq!((diagnostics[2].line, diagnostics[2].column), (4, 7));
+        assert_eq!(
+            diagnostics[2].render("kee.kee", input),
+            "error: Unknown scope: nope::PAGE(4)\n --> kee.kee:4:7\n  |\n4 | M-3 = nope::PAGE(4)\n  |       ^^^^^^^^^^^^^"
+        );
+    }
+
+    #[test]
+    fn test_comments_and_sections() {
+        let input = "# global\nM-1 = app::PAGE(1) # first page\n\n  // photoshop only\n[photoshop]\nM-d = app::SCRIPT('#drop // shadow.js')  // trailing\n[ workspace ]\nM-S-up = workspace::CYCLEWORKSPACE\n";
+        let kees = KeeParser::new(input).parse();
+        let parsed: Vec<_> = kees
+            .iter()
+            .map(|k| (k.section, k.kee, k.func.as_ref(), k.line))
+            .collect();
+        assert_eq!(
+            parsed,
+            vec![
+                (None, "M-1", "app::PAGE(1)", 2),
+                (
+                    Some("photoshop"),
+                    "M-d",
+                    "app::SCRIPT('#drop // shadow.js')",
+                    6
+                ),
+                (Some("workspace"), "M-S-up", "workspace::CYCLEWORKSPACE", 8),
+            ]
+        );
+        assert!(KeeParser::new(input).check(|_| Ok(())).is_empty());
+    }
+
+    #[test]
+    fn test_check_chain() {
+        let input = "M-s = app::FUNCCALL('Save') ; delay(200) ; nope::PAGE(2)\nM-e = app::PAGE(1) ;; app::PAGE(2)\n";
+        let diagnostics = KeeParser::new(input).check(|func| {
+            if func.starts_with("app::") {
+                Ok(())
+            } else {
+                Err(format!("Unknown scope: {}", func))
+            }
+        });
+        assert_eq!(diagnostics.len(), 2);
+        assert_eq!(
+            &input[diagnostics[0].span.start..diagnostics[0].span.end],
+            "nope::PAGE(2)"
+        );
+        assert_eq!(
+            (diagnostics[1].line, diagnostics[1].message.as_str()),
+            (2, "empty step in chain")
+        );
+    }
+
+    #[test]
+    fn test_modes() {
+        let input = "M-r = mode(resize)\nmode resize {\n    h = workspace::RESIZEACTIVEWINDOW('DEC', 'WIDTH')\n    l = workspace::RESIZEACTIVEWINDOW('INC', 'WIDTH') ; mode(move)\n} # back to normal\nM-1 = app::PAGE(1)\nM-2 = mode(resise)\n";
+        let kees = KeeParser::new(input).parse();
+        let parsed: Vec<_> = kees.iter().map(|k| (k.mode, k.kee, k.line)).collect();
+        assert_eq!(
+            parsed,
+            vec![
+                (None, "M-r", 1),
+                (Some("resize"), "h", 3),
+                (Some("resize"), "l", 4),
+                (None, "M-1", 6),
+                (None, "M-2", 7),
+            ]
+        );
+        let diagnostics = KeeParser::new(input).check(|_| Ok(()));
+        let messages: Vec<_> = diagnostics
+            .iter()
+            .map(|d| (d.line, d.message.as_str()))
+            .collect();
+        assert_eq!(
+            messages,
+            vec![
+                (4, "unknown mode `move`"),
+                (7, "unknown mode `resise` (did you mean resize?)")
+            ]
+        );
+
+        let input = "}\nmode resize {\nM-h = app::PAGE(1)\nmode move {\n[app]\n";
+        let diagnostics = KeeParser::new(input).check(|_| Ok(()));
+        let messages: Vec<_> = diagnostics
+            .iter()
+            .map(|d| (d.line, d.message.as_str()))
+            .collect();
+        assert_eq!(
+            messages,
+            vec![
+                (1, "unexpected `}`, no `mode` block is open"),
+                (
+                    4,
+                    "`mode move` is inside `mode resize`, modes can't be nested"
+                ),
+                (5, "`[app]` inside `mode move`, close the mode first"),
+                (4, "`mode move` is never closed, expected `}`"),
+                (
+                    3,
+                    "keys of `mode resize` are single keys without modifiers, triggers or app"
+                ),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_variables() {
+        let input = "let inc = 120\nlet blur = 'gaussianblur.js' # comment\nlet dir = '${env:TSCK_SCRIPTS}/blur.js'\nM-b = app::SCRIPT($blur)\nM-left = workspace::MOVEACTIVEWINDOW('LEFT', by=$inc)\nM-d = app::SCRIPT($dir)\nM-1 = app::PAGE(1)\nM-2 = app::PAGE($page)\nlet 2x = 1\nlet nothing\n";
+        let parser = KeeParser::new(input)
+            .with_env(|name| (name == "TSCK_SCRIPTS").then(|| "D:/scripts".to_string()));
+        let funcs: Vec<_> = parser.parse().into_iter().map(|k| k.func).collect();
+        assert_eq!(
+            funcs,
+            vec![
+                "app::SCRIPT('gaussianblur.js')",
+                "workspace::MOVEACTIVEWINDOW('LEFT', by=120)",
+                "app::SCRIPT('D:/scripts/blur.js')",
+                "app::PAGE(1)",
+                "app::PAGE($page)",
+            ]
+        );
+        assert!(matches!(parser.parse()[3].func, Cow::Borrowed(_)));
+
+        // `app::PAGE($page)` is reported once, not again as a bad function
+        let diagnostics = parser.check(|step| match FuncLexer::parse_func(step) {
+            Some(_) => Ok(()),
+            None => Err(format!("`{}` doesn't parse", step)),
+        });
+        let messages: Vec<_> = diagnostics
+            .iter()
+            .map(|d| (d.line, d.column, d.message.as_str()))
+            .collect();
+        assert_eq!(
+            messages,
+            vec![
+                (8, 17, "undefined variable `page`"),
+                (9, 5, "invalid variable name `2x`"),
+                (10, 1, "expected `let <name> = <value>`"),
+            ]
+        );
+    }
 }
@@ -1,3 +1,5 @@
+pub mod cst;
+pub mod diagnostic;
 pub mod func;
-pub mod func_lexer;
 pub mod lexer;
+pub mod variable;
@@ -0,0 +1,225 @@
+use std::{borrow::Cow, collections::HashMap};
+
+use crate::tokenizer::diagnostic::Span;
+
+type EnvLookup = fn(&str) -> Option<String>;
+
+/// `let name = value` declarations of kee.kee, referenced as `$name`,
+/// `${name}` or `${env:VAR}`. `$$` is a literal `$`.
+#[derive(Debug, Clone)]
+pub struct KeeVars {
+    values: HashMap<String, String>,
+    env: EnvLookup,
+}
+
+impl Default for KeeVars {
+    fn default() -> Self {
+        Self::new(|name| std::env::var(name).ok())
+    }
+}
+
+impl KeeVars {
+    pub fn new(env: EnvLookup) -> Self {
+        Self {
+            values: HashMap::new(),
+            env,
+        }
+    }
+
+    pub fn is_valid_name(name: &str) -> bool {
+        let mut chars = name.chars();
+        matches!(chars.next(), Some(c) if c.is_ascii_alphabetic() || c == '_')
+            && chars.all(|c| c.is_ascii_alphanumeric() || c == '_')
+    }
+
+    pub fn define(&mut self, name: &str, value: impl Into<String>) {
+        self.values.insert(name.to_string(), value.into());
+    }
+
+    pub fn get(&self, name: &str) -> Option<&str> {
+        self.values.get(name).map(String::as_str)
+    }
+
+    /// Substitute every reference in `text`. Inside a `'...'` or `"..."`
+    /// literal the value is escaped, so `C:\Users` stays a backslash path.
+    /// Errors carry the span of the offending reference, relative to `text`.
+    pub fn expand<'t>(&self, text: &'t str) -> Result<Cow<'t, str>, (Span, String)> {
+        if !text.contains('$') {
+            return Ok(Cow::Borrowed(text));
+        }
+        let mut out = String::with_capacity(text.len());
+        let mut rest = 0;
+        // the literal `$` sits in, and whether the char before was its `\`
+        let mut quote = None;
+        let mut escaped = false;
+        let mut chars = text.char_indices().peekable();
+        while let Some((start, c)) = chars.next() {
+            if c != '$' {
+                match quote {
+                    Some(_) if escaped => escaped = false,
+                    Some(_) if c == '\\' => escaped = true,
+                    Some(q) if c == q => quote = None,
+                    None if c == '\'' || c == '"' => quote = Some(c),
+                    _ => {}
+                }
+                continue;
+            }
+            escaped = false;
+            if let Some(&(second, '$')) = chars.peek() {
+                out.push_str(&text[rest..second]);
+                rest = second + 1;
+                chars.next();
+                continue;
+            }
+            let (name, end) = match chars.peek() {
+                Some(&(open, '{')) => {
+                    let Some(close) = text[open..].find('}').map(|p| open + p) else {
+                        return Err((Span::new(start, text.len()), "unclosed `${`".into()));
+                    };
+                    (text[open + 1..close].trim(), close + 1)
+                }
+                Some(&(from, c)) if c.is_ascii_alphabetic() || c == '_' => {
+                    let end = text[from..]
+                        .find(|c: char| !(c.is_ascii_alphanumeric() || c == '_'))
+                        .map_or(text.len(), |p| from + p);
+                    (&text[from..end], end)
+                }
+                _ => continue,
+            };
+            let span = Span::new(start, end);
+            let value = match name.strip_prefix("env:") {
+                Some(var) => (self.env)(var.trim()).ok_or_else(|| {
+                    (
+                        span,
+                        format!("environment variable `{}` is not set", var.trim()),
+                    )
+                })?,
+                None => self
+                    .get(name)
+                    .ok_or_else(|| (span, format!("undefined variable `{}`", name)))?
+                    .to_string(),
+            };
+            out.push_str(&text[rest..start]);
+            match quote {
+                Some(q) => out.push_str(&escape(&value, q)),
+                None => out.push_str(&value),
+            }
+            rest = end;
+            while chars.peek().is_some_and(|&(i, _)| i < end) {
+                chars.next();
+            }
+        }
+        out.push_str(&text[rest..]);
+        Ok(Cow::Owned(out))
+    }
+}
+
+/// `value` as the inside of a `quote` literal
+fn escape(value: &str, quote: char) -> String {
+    let mut out = String::with_capacity(value.len());
+    for c in value.chars() {
+        if c == '\\' || c == quote {
+            out.push('\\');
+        }
+        out.push(c);
+    }
+    out
+}
+
+#[cfg(test)]
+mod kee_vars {
+    use super::*;
+    use tsck_utils::{FuncExpr, FuncLexer};
+
+    fn env(name: &str) -> Option<String> {
+        match name {
+            "TSCK_SCRIPTS" => Some("D:/scripts".into()),
+            "USERPROFILE" => Some(r"C:\Users\o'neil".into()),
+            _ => None,
+        }
+    }
+
+    #[test]
+    fn test_expand() {
+        let mut vars = KeeVars::new(env);
+        vars.define("inc", "120");
+        vars.define("blur", "'gaussianblur.js'");
+        assert_eq!(
+            vars.expand("workspace::MOVEACTIVEWINDOW('LEFT', by=$inc)"),
+            Ok(Cow::Owned(
+                "workspace::MOVEACTIVEWINDOW('LEFT', by=120)".into()
+            ))
+        );
+        assert_eq!(
+            vars.expand("app::SCRIPT($blur) ; app::PAGE(${inc})"),
+            Ok(Cow::Owned(
+                "app::SCRIPT('gaussianblur.js') ; app::PAGE(120)".into()
+            ))
+        );
+        assert_eq!(
+            vars.expand("app::SCRIPT('${env:TSCK_SCRIPTS}/a.js')"),
+            Ok(Cow::Owned("app::SCRIPT('D:/scripts/a.js')".into()))
+        );
+        assert_eq!(
+            vars.expand("app::SCRIPT('cost$ 5')"),
+            Ok(Cow::Owned("app::SCRIPT('cost$ 5')".into()))
+        );
+        assert_eq!(
+            vars.expand("app::SCRIPT('$$inc is $inc') ; app::PAGE('$$$${inc}')"),
+            Ok(Cow::Owned(
+                "app::SCRIPT('$inc is 120') ; app::PAGE('$${inc}')".into()
+            ))
+        );
+        assert_eq!(
+            vars.expand("app::PAGE(1)"),
+            Ok(Cow::Borrowed("app::PAGE(1)"))
+        );
+    }
+
+    #[test]
+    fn test_expand_escapes() {
+        let mut vars = KeeVars::new(env);
+        vars.define("home", r"'D:\\tsck'");
+        let script = vars
+            .expand(r"app::SCRIPT('${env:USERPROFILE}\\blur.js')")
+            .unwrap();
+        assert_eq!(script, r"app::SCRIPT('C:\\Users\\o\'neil\\blur.js')");
+        let func = FuncLexer::parse_func(&script).unwrap();
+        assert_eq!(
+            func.args,
+            [FuncExpr::String(r"C:\Users\o'neil\blur.js".into())]
+        );
+        // only `"` and `\` are special in a double-quoted literal
+        assert_eq!(
+            vars.expand(r#"app::SCRIPT("${env:USERPROFILE}")"#).unwrap(),
+            r#"app::SCRIPT("C:\\Users\\o'neil")"#
+        );
+        // bare references paste the value as written, quotes included
+        assert_eq!(
+            vars.expand("app::SCRIPT($home)").unwrap(),
+            r"app::SCRIPT('D:\\tsck')"
+        );
+    }
+
+    #[test]
+    fn test_expand_errors() {
+        let vars = KeeVars::new(env);
+        assert_eq!(
+            vars.expand("app::PAGE($page)"),
+            Err((Span::new(10, 15), "undefined variable `page`".into()))
+        );
+        assert_eq!(
+            vars.expand("app::SCRIPT('${env:NOPE}')"),
+            Err((
+                Span::new(13, 24),
+                "environment variable `NOPE` is not set".into()
+            ))
+        );
+        assert_eq!(
+            vars.expand("app::PAGE(${page)"),
+            Err((Span::new(10, 17), "unclosed `${`".into()))
+        );
+        assert!(KeeVars::is_valid_name("script_dir2"));
+        assert!(!KeeVars::is_valid_name("2inc"));
+    }
+}
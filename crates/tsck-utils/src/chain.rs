@@ -0,0 +1,111 @@
+use crate::lexer::FuncLexer;
+use std::time::Duration;
+
+/// One step of a `app::SAVE ; delay(200) ; mode(resize) ; app::PAGE(2)` chain
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ChainStep<'a> {
+    /// `delay(ms)`
+    Delay(u64),
+    /// `mode(name)`
+    Mode(&'a str),
+    /// `scope::FUNC(...)`, parsed by whoever runs it
+    Call(&'a str),
+}
+
+impl<'a> ChainStep<'a> {
+    pub fn parse(step: &'a str) -> Self {
+        if let Some(ms) = FuncLexer::parse_delay(step) {
+            ChainStep::Delay(ms)
+        } else if let Some(mode) = FuncLexer::parse_mode(step) {
+            ChainStep::Mode(mode)
+        } else {
+            ChainStep::Call(step)
+        }
+    }
+
+    /// Steps of `chain` in order
+    pub fn split(chain: &'a str) -> Vec<Self> {
+        FuncLexer::split_chain(chain)
+            .into_iter()
+            .map(Self::parse)
+            .collect()
+    }
+
+    /// True when running `chain` would sleep, so it belongs on its own thread
+    pub fn waits(chain: &str) -> bool {
+        ChainStep::split(chain)
+            .iter()
+            .any(|s| matches!(s, ChainStep::Delay(_)))
+    }
+}
+
+/// Run the steps of `chain` in order on the calling thread, `delay` steps
+/// sleep it and every other step goes to `run`. The first step `run` fails
+/// ends the chain and is returned with its error.
+pub fn run_chain<'a>(
+    chain: &'a str,
+    mut run: impl FnMut(ChainStep<'a>) -> Result<(), String>,
+) -> Result<(), (&'a str, String)> {
+    for step in FuncLexer::split_chain(chain) {
+        match ChainStep::parse(step) {
+            ChainStep::Delay(ms) => std::thread::sleep(Duration::from_millis(ms)),
+            parsed => run(parsed).map_err(|err| (step, err))?,
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod chains {
+    use super::*;
+    use std::time::Instant;
+
+    #[test]
+    fn test_split() {
+        assert_eq!(
+            ChainStep::split("app::SAVE ; delay(20) ; mode(resize) ; app::PAGE(2)"),
+            [
+                ChainStep::Call("app::SAVE"),
+                ChainStep::Delay(20),
+                ChainStep::Mode("resize"),
+                ChainStep::Call("app::PAGE(2)"),
+            ]
+        );
+        assert!(ChainStep::waits("app::SAVE ; DELAY(5)"));
+        assert!(!ChainStep::waits("app::FUNCCALL('delay(5)')"));
+    }
+
+    #[test]
+    fn test_stops_at_error() {
+        let mut ran = Vec::new();
+        let result = run_chain("app::A ; mode(resize) ; app::FAIL ; app::C", |step| {
+            ran.push(step);
+            match step {
+                ChainStep::Call("app::FAIL") => Err("no active window".into()),
+                _ => Ok(()),
+            }
+        });
+        assert_eq!(result, Err(("app::FAIL", "no active window".to_string())));
+        assert_eq!(
+            ran,
+            [
+                ChainStep::Call("app::A"),
+                ChainStep::Mode("resize"),
+                ChainStep::Call("app::FAIL"),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_delay() {
+        let start = Instant::now();
+        let mut ran = Vec::new();
+        let result = run_chain("app::A ; delay(30) ; app::B", |step| {
+            ran.push((step, start.elapsed()));
+            Ok(())
+        });
+        assert_eq!(result, Ok(()));
+        assert_eq!(ran.len(), 2);
+        assert!(ran[1].1 - ran[0].1 >= Duration::from_millis(30));
+    }
+}
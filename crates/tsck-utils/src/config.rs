@@ -1,18 +1,90 @@
 #![allow(unused)]
-use crate::{directory::Dir, generate_func_enums};
-use anyhow::Result;
+use crate::{
+    directory::Dir,
+    format::{ConfigFormat, format_for, locate},
+    generate_func_enums,
+    migrate::{Migration, migrate, stamp},
+    watch::ConfigWatcher,
+};
+use anyhow::{Context, Result};
 pub use paste;
 use serde::{Deserialize, Serialize};
+use serde_json::Value;
 use std::{
     fs,
+    io::Write,
     path::{Path, PathBuf},
     str::FromStr,
     string::ParseError,
 };
 
+/// Versions of a config kept next to it as `conf.json.bak.N`, 1 the newest
+pub const DEFAULT_BACKUPS: usize = 3;
+
 pub struct ConfigStore<T> {
     path: PathBuf,
     data: T,
+    /// Picked by the extension of `path`
+    format: &'static dyn ConfigFormat,
+    backups: usize,
+    /// Backup the data was loaded from because the file itself didn't parse
+    recovered_from: Option<PathBuf>,
+}
+
+/// `conf.json.bak.N`
+fn backup_path(path: &Path, n: usize) -> PathBuf {
+    let mut name = path.as_os_str().to_owned();
+    name.push(format!(".bak.{}", n));
+    PathBuf::from(name)
+}
+
+/// Write to `conf.json.tmp`, fsync and rename it over `path`, so a crash
+/// leaves either the old or the new file and never half of one. The old file
+/// is shifted into the backups first.
+pub fn write_atomic(path: &Path, content: &str, backups: usize) -> Result<()> {
+    let mut tmp = path.as_os_str().to_owned();
+    tmp.push(".tmp");
+    let tmp = PathBuf::from(tmp);
+    {
+        let mut file = fs::File::create(&tmp)?;
+        file.write_all(content.as_bytes())?;
+        file.sync_all()?;
+    }
+    if backups > 0 && path.exists() {
+        for n in (1..backups).rev() {
+            let from = backup_path(path, n);
+            if from.exists() {
+                fs::rename(&from, backup_path(path, n + 1))?;
+            }
+        }
+        fs::copy(path, backup_path(path, 1))?;
+    }
+    fs::rename(&tmp, path)?;
+    // the rename itself only lasts once the directory is synced
+    #[cfg(unix)]
+    if let Some(dir) = path.parent() {
+        fs::File::open(dir)?.sync_all()?;
+    }
+    Ok(())
+}
+
+/// Decode `path`, falling back to the newest backup that decodes. The error of
+/// `path` is returned when none does.
+fn load<T>(
+    path: &Path,
+    backups: usize,
+    format: &dyn ConfigFormat,
+    mut decode: impl FnMut(Value) -> Result<T>,
+) -> Result<(T, Option<PathBuf>)> {
+    let mut parse =
+        |path: &Path| -> Result<T> { decode(format.parse(&fs::read_to_string(path)?)?) };
+    match parse(path) {
+        Ok(data) => Ok((data, None)),
+        Err(err) => (1..=backups)
+            .map(|n| backup_path(path, n))
+            .find_map(|backup| Some((parse(&backup).ok()?, Some(backup))))
+            .ok_or(err),
+    }
 }
 
 impl<T> ConfigStore<T> {
@@ -26,17 +98,18 @@ impl<T> ConfigStore<T>
 where
     T: Serialize + for<'de> Deserialize<'de> + Default,
 {
+    /// Path of `config_name`, `conf.json` is `conf.json5` or `conf.toml` when
+    /// one of those exists
     pub fn get_file_path(app_name: &'static str, config_name: &str) -> Result<String> {
         let dir = Self::root_dir(app_name)?;
-        let path = dir.join(config_name);
+        let path = locate(&dir.join(config_name));
         Ok(path.to_string_lossy().to_string())
     }
     pub fn read_file(app_name: &'static str, config_name: &'static str) -> Result<String> {
         let dir = Self::root_dir(app_name)?;
-        let path = dir.join(config_name);
+        let path = locate(&dir.join(config_name));
         let data = if path.exists() {
-            let content = fs::read_to_string(&path)?;
-            content
+            fs::read_to_string(&path)?
         } else {
             "".to_string()
         };
@@ -44,16 +117,112 @@ where
     }
     pub fn new(app_name: &'static str, config_name: &'static str) -> Result<Self> {
         let dir = Self::root_dir(app_name)?;
-        let path = dir.join(config_name);
+        Self::open(locate(&dir.join(config_name)), DEFAULT_BACKUPS)
+    }
 
-        let data = if path.exists() {
-            let content = fs::read_to_string(&path)?;
-            serde_json::from_str(&content)?
+    /// Like `new`, upgrading an older file through `migrations` before it is
+    /// deserialised. What the steps carried out of the file is handed to
+    /// `carry` first, the upgraded file is only saved once that succeeded and
+    /// its previous version stays in the backups. Returns the steps that ran.
+    pub fn new_migrated<'a, C: Default>(
+        app_name: &'static str,
+        config_name: &'static str,
+        migrations: &'a [Migration<C>],
+        carry: impl FnOnce(&C) -> Result<()>,
+    ) -> Result<(Self, Vec<&'a Migration<C>>)> {
+        let dir = Self::root_dir(app_name)?;
+        let path = locate(&dir.join(config_name));
+        Self::open_migrated(path, DEFAULT_BACKUPS, migrations, carry)
+    }
+
+    fn open_migrated<C: Default>(
+        path: PathBuf,
+        backups: usize,
+        migrations: &[Migration<C>],
+        carry: impl FnOnce(&C) -> Result<()>,
+    ) -> Result<(Self, Vec<&Migration<C>>)> {
+        let exists = path.exists();
+        let mut ran = Vec::new();
+        let mut carried = C::default();
+        let mut store = Self::open_with(path, backups, |mut doc| {
+            // every backup tried starts over, only the one that loads counts
+            let mut ctx = C::default();
+            let steps = migrate(&mut doc, migrations, &mut ctx)?;
+            let data = serde_json::from_value(doc)?;
+            (ran, carried) = (steps, ctx);
+            Ok(data)
+        })?;
+        if !exists {
+            // nothing to migrate in a fresh file, it starts at the latest
+            let mut doc = serde_json::to_value(&store.data)?;
+            stamp(&mut doc, migrations);
+            store.data = serde_json::from_value(doc)?;
+        } else if !ran.is_empty() {
+            carry(&carried).with_context(|| format!("{} left unmigrated", store.path.display()))?;
+            store.save()?;
+        }
+        Ok((store, ran))
+    }
+
+    /// `T::default()` in place of a `config_name` that `new` or `new_migrated`
+    /// couldn't load. The file is left alone, a `set` moves it into the
+    /// backups before writing.
+    pub fn fallback(app_name: &'static str, config_name: &'static str) -> Self {
+        let path = Self::root_dir(app_name)
+            .map(|dir| locate(&dir.join(config_name)))
+            .unwrap_or_else(|_| PathBuf::from(config_name));
+        Self::defaults_at(path, DEFAULT_BACKUPS)
+    }
+
+    fn defaults_at(path: PathBuf, backups: usize) -> Self {
+        Self {
+            format: format_for(&path),
+            path,
+            data: T::default(),
+            backups,
+            recovered_from: None,
+        }
+    }
+
+    fn open(path: PathBuf, backups: usize) -> Result<Self> {
+        Self::open_with(path, backups, |doc| Ok(serde_json::from_value(doc)?))
+    }
+
+    fn open_with(
+        path: PathBuf,
+        backups: usize,
+        decode: impl FnMut(Value) -> Result<T>,
+    ) -> Result<Self> {
+        let format = format_for(&path);
+        let (data, recovered_from) = if path.exists() {
+            load(&path, backups, format, decode)?
         } else {
-            T::default()
+            (T::default(), None)
         };
 
-        Ok(Self { path, data })
+        Ok(Self {
+            path,
+            data,
+            format,
+            backups,
+            recovered_from,
+        })
+    }
+
+    /// Keep `backups` versions instead of `DEFAULT_BACKUPS`, 0 keeps none
+    pub fn backups(mut self, backups: usize) -> Self {
+        self.backups = backups;
+        self
+    }
+
+    /// Watcher of the file behind this store, hand it the current data to
+    /// `spawn` and `replace` the data from its `on_change`
+    pub fn watch(&self) -> ConfigWatcher<T>
+    where
+        T: Send + 'static,
+    {
+        let format = self.format;
+        ConfigWatcher::new(self.path.clone(), move |content| format.decode(content))
     }
 }
 
@@ -61,9 +230,14 @@ impl<T> ConfigStore<T>
 where
     T: Serialize,
 {
-    fn save(&self) -> Result<()> {
-        let content = serde_json::to_string_pretty(&self.data)?;
-        fs::write(&self.path, content)?;
+    /// Comments of the file being replaced are kept where the format allows.
+    /// The data is on `path` from here on, not on the backup it came from.
+    fn save(&mut self) -> Result<()> {
+        let previous = fs::read_to_string(&self.path).ok();
+        let value = serde_json::to_value(&self.data)?;
+        let content = self.format.render(&value, previous.as_deref())?;
+        write_atomic(&self.path, &content, self.backups)?;
+        self.recovered_from = None;
         Ok(())
     }
 }
@@ -72,6 +246,26 @@ impl<T> ConfigStore<T> {
     pub fn get<R>(&self, f: impl FnOnce(&T) -> R) -> R {
         f(&self.data)
     }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Set when the file was broken and the data comes from this backup
+    pub fn recovered_from(&self) -> Option<&Path> {
+        self.recovered_from.as_deref()
+    }
+
+    /// File holding the data: `path`, or the backup it was recovered from
+    /// until the next save
+    pub fn source(&self) -> &Path {
+        self.recovered_from().unwrap_or(&self.path)
+    }
+
+    /// Swap in data that is already on disk, like a reload, without saving
+    pub fn replace(&mut self, data: T) -> T {
+        std::mem::replace(&mut self.data, data)
+    }
 }
 
 impl<T> ConfigStore<T>
@@ -118,7 +312,13 @@ mod test_config {
     );
     use std::{collections::HashMap, str::FromStr};
 
-    use crate::{Func, config::ConfigStore, generate_func_enums, parse_func};
+    use crate::{
+        FuncLexer,
+        config::{ConfigStore, backup_path},
+        generate_func_enums,
+        layers::Layered,
+        migrate::{Migration, rename_key},
+    };
     use serde::{Deserialize, Serialize};
 
     #[derive(Serialize, Deserialize, Default)]
@@ -129,6 +329,12 @@ mod test_config {
     }
     #[derive(Serialize, Deserialize, Default)]
     #[serde(default)]
+    struct Versioned {
+        schema_version: u32,
+        apps: Vec<String>,
+    }
+    #[derive(Serialize, Deserialize, Default)]
+    #[serde(default)]
     pub struct TestConfig {
         monitors: Vec<(i32, i32)>,
         apps: Vec<String>,
@@ -145,13 +351,150 @@ mod test_config {
         })?;
         Ok(())
     }
+    #[test]
+    fn test_backups() -> anyhow::Result<()> {
+        let dir = std::env::temp_dir().join(format!("tsck-backups-{}", std::process::id()));
+        std::fs::create_dir_all(&dir)?;
+        let path = dir.join("conf.json");
+        let mut config = ConfigStore::<TestConfig>::open(path.clone(), 2)?;
+        for version in ["1", "2", "3", "4"] {
+            config.set(|c| c.version = version.to_string())?;
+        }
+        let version = |path| -> anyhow::Result<String> {
+            let config = ConfigStore::<TestConfig>::open(path, 0)?;
+            Ok(config.get(|c| c.version.clone()))
+        };
+        assert_eq!(version(path.clone())?, "4");
+        assert_eq!(version(backup_path(&path, 1))?, "3");
+        assert_eq!(version(backup_path(&path, 2))?, "2");
+        assert!(!backup_path(&path, 3).exists());
+        assert!(!dir.join("conf.json.tmp").exists());
+
+        // a truncated write falls back to the newest backup that parses
+        std::fs::write(&path, "{ \"version\": ")?;
+        std::fs::write(backup_path(&path, 1), "")?;
+        let config = ConfigStore::<TestConfig>::open(path.clone(), 2)?;
+        assert_eq!(config.get(|c| c.version.clone()), "2");
+        assert_eq!(
+            config.recovered_from(),
+            Some(backup_path(&path, 2).as_path())
+        );
+        assert!(ConfigStore::<TestConfig>::open(path.clone(), 1).is_err());
+
+        // what doesn't load is kept, the first save puts it in the backups
+        let mut config = ConfigStore::<TestConfig>::defaults_at(path.clone(), 1);
+        assert_eq!(config.get(|c| c.version.clone()), "");
+        assert_eq!(std::fs::read_to_string(&path)?, "{ \"version\": ");
+        config.set(|c| c.version = "5".into())?;
+        assert_eq!(
+            std::fs::read_to_string(backup_path(&path, 1))?,
+            "{ \"version\": "
+        );
+
+        // saving moves the broken file into the backup it was recovered from,
+        // the data is read from the file again
+        std::fs::write(&path, "{ \"version\": ")?;
+        std::fs::write(backup_path(&path, 1), r#"{ "version": "6" }"#)?;
+        let mut config = ConfigStore::<TestConfig>::open(path.clone(), 2)?;
+        assert_eq!(config.source(), backup_path(&path, 1));
+        config.set(|c| c.version = "7".into())?;
+        assert_eq!(config.recovered_from(), None);
+        let layered = Layered::<TestConfig>::new()?.file(config.source());
+        assert!(layered.errors().is_empty());
+        assert_eq!(layered.get().version, "7");
+
+        std::fs::remove_dir_all(&dir)?;
+        Ok(())
+    }
+
+    #[test]
+    fn test_migrated() -> anyhow::Result<()> {
+        const STEPS: &[Migration] = &[Migration {
+            to: 1,
+            description: "rename `app` to `apps`",
+            apply: |object, _| {
+                rename_key(object, "app", "apps");
+                Ok(())
+            },
+        }];
+        let dir = std::env::temp_dir().join(format!("tsck-migrated-{}", std::process::id()));
+        std::fs::create_dir_all(&dir)?;
+        let path = dir.join("conf.json");
+
+        std::fs::write(&path, r#"{ "app": ["code"] }"#)?;
+        // nothing saved when what the steps carried can't be written
+        let failed = ConfigStore::<Versioned>::open_migrated(path.clone(), 1, STEPS, |_| {
+            anyhow::bail!("read-only")
+        });
+        assert!(failed.is_err());
+        assert_eq!(std::fs::read_to_string(&path)?, r#"{ "app": ["code"] }"#);
+
+        let (config, ran) =
+            ConfigStore::<Versioned>::open_migrated(path.clone(), 1, STEPS, |_| Ok(()))?;
+        assert_eq!(ran.len(), 1);
+        assert_eq!(
+            config.get(|c| (c.schema_version, c.apps.clone())),
+            (1, vec!["code".to_string()])
+        );
+        // saved upgraded, the original kept as backup
+        let (_, ran) = ConfigStore::<Versioned>::open_migrated(path.clone(), 1, STEPS, |_| Ok(()))?;
+        assert!(ran.is_empty());
+        assert!(std::fs::read_to_string(backup_path(&path, 1))?.contains("\"app\""));
+
+        let fresh = dir.join("fresh.json");
+        let (config, _) = ConfigStore::<Versioned>::open_migrated(fresh, 1, STEPS, |_| Ok(()))?;
+        assert_eq!(config.get(|c| c.schema_version), 1);
+
+        // a broken file and its backups are migrated on their own, only what
+        // the one that loads carried is kept
+        const CARRY: &[Migration<Vec<String>>] = &[Migration {
+            to: 1,
+            description: "move `app` out",
+            apply: |object, carried| {
+                carried.extend(object.remove("app").into_iter().map(|app| app.to_string()));
+                Ok(())
+            },
+        }];
+        let carried_path = dir.join("carried.json");
+        std::fs::write(&carried_path, r#"{ "app": "code", "apps": 1 }"#)?;
+        std::fs::write(backup_path(&carried_path, 1), r#"{ "app": "photoshop" }"#)?;
+        let mut carried = Vec::new();
+        ConfigStore::<Versioned>::open_migrated(carried_path, 1, CARRY, |c| {
+            carried = c.clone();
+            Ok(())
+        })?;
+        assert_eq!(carried, ["\"photoshop\""]);
+
+        std::fs::remove_dir_all(&dir)?;
+        Ok(())
+    }
+
+    #[test]
+    fn test_commented() -> anyhow::Result<()> {
+        let dir = std::env::temp_dir().join(format!("tsck-commented-{}", std::process::id()));
+        std::fs::create_dir_all(&dir)?;
+        let path = dir.join("conf.json5");
+        std::fs::write(
+            &path,
+            "{\n  // bumped on release\n  version: '0.1',\n  apps: ['code',],\n}\n",
+        )?;
+        let mut config = ConfigStore::<TestConfig>::open(path.clone(), 0)?;
+        assert_eq!(config.get(|c| c.apps.clone()), ["code"]);
+        config.set(|c| c.version = "0.2".into())?;
+        let saved = std::fs::read_to_string(&path)?;
+        assert!(saved.contains("  // bumped on release\n  \"version\": \"0.2\""));
+
+        std::fs::remove_dir_all(&dir)?;
+        Ok(())
+    }
+
     #[test]
     fn get_config() -> anyhow::Result<()> {
         let config = ConfigStore::<TestConfig>::new("test_app", "test_config.json")?;
         let appconfig = TestConfig::default();
         let c: Vec<String> = config.get(|c| c.kees.iter().map(|(k, v)| v.clone()).collect());
         c.iter().for_each(|c| {
-            if let Some(cmd) = parse_func(c) {
+            if let Some(cmd) = FuncLexer::parse_func(c) {
                 let entry = cmd.entry;
                 let func = cmd.func;
                 let args = cmd.args;
@@ -1,50 +1,105 @@
 #![allow(unused)]
-use anyhow::{Context, Result};
+use anyhow::{Context, Result, bail};
 use std::path::{Path, PathBuf};
-use std::sync::OnceLock;
-static PARENT: OnceLock<PathBuf> = OnceLock::new();
+
+/// Overrides every base dir with `$TSCK_HOME/config`, `/data` and `/cache`,
+/// for tests and portable installs
+pub const TSCK_HOME: &str = "TSCK_HOME";
+
+/// What goes in a dir, they only differ on Linux where the XDG base dirs are
+/// followed. Elsewhere all three are `~/.config`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DirKind {
+    /// conf.json, kee.kee, tsck.json
+    Config,
+    /// The database and anything else that can't be rebuilt
+    Data,
+    /// Anything that can be thrown away
+    Cache,
+}
+
+impl DirKind {
+    fn name(self) -> &'static str {
+        match self {
+            DirKind::Config => "config",
+            DirKind::Data => "data",
+            DirKind::Cache => "cache",
+        }
+    }
+
+    /// XDG variable and its fallback under the home dir
+    fn xdg(self) -> (&'static str, &'static str) {
+        match self {
+            DirKind::Config => ("XDG_CONFIG_HOME", ".config"),
+            DirKind::Data => ("XDG_DATA_HOME", ".local/share"),
+            DirKind::Cache => ("XDG_CACHE_HOME", ".cache"),
+        }
+    }
+}
+
+/// Base dir of `kind` from the variables `env` gives back, nothing is created
+fn resolve(kind: DirKind, env: impl Fn(&str) -> Option<String>) -> Result<PathBuf> {
+    // empty counts as unset, relative XDG paths are invalid by the spec
+    let var = |name: &str| env(name).filter(|v| !v.is_empty());
+    if let Some(home) = var(TSCK_HOME) {
+        return Ok(Path::new(&home).join(kind.name()));
+    }
+    let home = var("USERPROFILE").or_else(|| var("HOME")).context(
+        "Neither HOME nor USERPROFILE is set, set TSCK_HOME to choose where tsck keeps its files",
+    )?;
+    if cfg!(target_os = "linux") {
+        let (xdg, fallback) = kind.xdg();
+        if let Some(dir) = var(xdg).map(PathBuf::from).filter(|d| d.is_absolute()) {
+            return Ok(dir);
+        }
+        return Ok(Path::new(&home).join(fallback));
+    }
+    Ok(Path::new(&home).join(".config"))
+}
 
 pub struct Dir;
 
 impl Dir {
+    /// Base dir of `kind`, resolved on every call so `TSCK_HOME` can change
+    pub fn base(kind: DirKind) -> Result<PathBuf> {
+        resolve(kind, |name| std::env::var(name).ok())
+    }
+
+    /// Dir of `app_name` under the base dir of `kind`, created if missing
+    pub fn app(kind: DirKind, app_name: &str) -> Result<PathBuf> {
+        let dir = Self::base(kind)?.join(app_name);
+        if !dir.exists() {
+            std::fs::create_dir_all(&dir).with_context(|| {
+                format!(
+                    "Failed to create {} directory {}",
+                    kind.name(),
+                    dir.display()
+                )
+            })?;
+        }
+        Ok(dir)
+    }
+
+    #[inline]
+    pub fn file(kind: DirKind, app_name: &str, filename: &str) -> Result<PathBuf> {
+        Ok(Self::app(kind, app_name)?.join(filename))
+    }
+
     #[inline]
-    pub fn store_parent() -> Result<&'static Path> {
-        let result = PARENT
-            .get_or_init(|| {
-                eprintln!("INIT DOTFILES PARENT DIR");
-                if let Some(base) = std::env::var("USERPROFILE")
-                    .or_else(|_| std::env::var("HOME"))
-                    .context("HOME or USERPROFILE environment variable not set")
-                    .ok()
-                {
-                    let dir = Path::new(&base).join(".config");
-                    std::fs::create_dir_all(&dir)
-                        .context("Failed to create .config directory")
-                        .ok();
-                    return dir;
-                }
-                panic!("Nooo");
-            })
-            .as_path();
-        Ok(result)
+    pub fn store_parent() -> Result<PathBuf> {
+        Self::base(DirKind::Config)
     }
 
     #[inline]
-    pub fn store_parent_str() -> Result<&'static str> {
+    pub fn store_parent_str() -> Result<String> {
         Self::store_parent()?
             .to_str()
             .context("Config path contains invalid UTF-8")
+            .map(str::to_string)
     }
 
     pub fn store_path(app_name: &str) -> Result<PathBuf> {
-        let parent = Self::store_parent()?;
-        let dir = parent.join(app_name);
-        if !dir.exists() {
-            std::fs::create_dir_all(&dir)
-                .with_context(|| format!("Failed to create config directory for '{}'", app_name))?;
-        }
-
-        Ok(dir)
+        Self::app(DirKind::Config, app_name)
     }
 
     #[inline]
@@ -57,7 +112,17 @@ impl Dir {
 
     #[inline]
     pub fn store_file(app_name: &str, filename: &str) -> Result<PathBuf> {
-        Ok(Self::store_path(app_name)?.join(filename))
+        Self::file(DirKind::Config, app_name, filename)
+    }
+
+    #[inline]
+    pub fn data_file(app_name: &str, filename: &str) -> Result<PathBuf> {
+        Self::file(DirKind::Data, app_name, filename)
+    }
+
+    #[inline]
+    pub fn cache_path(app_name: &str) -> Result<PathBuf> {
+        Self::app(DirKind::Cache, app_name)
     }
 }
 
@@ -67,12 +132,11 @@ mod dir_examples {
 
     #[test]
     fn example_basic_usage() -> Result<()> {
-        // Most efficient: Get as &Path
-        let parent: &Path = Dir::store_parent()?;
+        // Base dir, nothing is created
+        let parent: PathBuf = Dir::store_parent()?;
         println!("Config parent: {}", parent.display());
 
-        // Zero-copy string (after first call)
-        let parent_str: &str = Dir::store_parent_str()?;
+        let parent_str: String = Dir::store_parent_str()?;
         println!("Config parent str: {}", parent_str);
 
         // App-specific directory
@@ -121,4 +185,59 @@ mod dir_examples {
             Err(e) => eprintln!("Error: {:#}", e),
         }
     }
+
+    fn env<'a>(vars: &'a [(&str, &str)]) -> impl Fn(&str) -> Option<String> + 'a {
+        move |name| {
+            vars.iter()
+                .find(|(k, _)| *k == name)
+                .map(|(_, v)| v.to_string())
+        }
+    }
+
+    #[test]
+    fn test_resolve() -> Result<()> {
+        let portable = [("TSCK_HOME", "/opt/tsck"), ("HOME", "/home/me")];
+        assert_eq!(
+            resolve(DirKind::Data, env(&portable))?,
+            Path::new("/opt/tsck/data")
+        );
+        assert_eq!(
+            resolve(DirKind::Config, env(&portable))?,
+            Path::new("/opt/tsck/config")
+        );
+
+        let home = [("HOME", "/home/me"), ("TSCK_HOME", "")];
+        let xdg = [
+            ("HOME", "/home/me"),
+            ("XDG_CONFIG_HOME", "/xdg/config"),
+            ("XDG_CACHE_HOME", "relative/cache"),
+        ];
+        if cfg!(target_os = "linux") {
+            assert_eq!(
+                resolve(DirKind::Config, env(&home))?,
+                Path::new("/home/me/.config")
+            );
+            assert_eq!(
+                resolve(DirKind::Data, env(&home))?,
+                Path::new("/home/me/.local/share")
+            );
+            assert_eq!(
+                resolve(DirKind::Config, env(&xdg))?,
+                Path::new("/xdg/config")
+            );
+            assert_eq!(
+                resolve(DirKind::Cache, env(&xdg))?,
+                Path::new("/home/me/.cache")
+            );
+        } else {
+            assert_eq!(
+                resolve(DirKind::Data, env(&xdg))?,
+                Path::new("/home/me/.config")
+            );
+        }
+
+        let err = resolve(DirKind::Config, env(&[])).unwrap_err();
+        assert!(err.to_string().contains("TSCK_HOME"));
+        Ok(())
+    }
 }
@@ -0,0 +1,578 @@
+use crate::config::write_atomic;
+use anyhow::{Context, Result, bail};
+use serde::de::DeserializeOwned;
+use serde_json::Value;
+use std::{
+    collections::HashMap,
+    fs,
+    path::{Path, PathBuf},
+};
+use toml_edit::{DocumentMut, Item};
+
+/// File format of a config, picked by the extension of the file
+pub trait ConfigFormat: Send + Sync {
+    /// Extensions it is picked for, without the dot
+    fn extensions(&self) -> &'static [&'static str];
+
+    fn parse(&self, content: &str) -> Result<Value>;
+
+    /// `value` as a file. `previous` is the file it replaces, its comments
+    /// are kept where the format has any.
+    fn render(&self, value: &Value, previous: Option<&str>) -> Result<String>;
+}
+
+impl dyn ConfigFormat {
+    pub fn decode<T: DeserializeOwned>(&self, content: &str) -> Result<T> {
+        Ok(serde_json::from_value(self.parse(content)?)?)
+    }
+}
+
+pub struct Json;
+/// JSON with comments, trailing commas, unquoted keys and single quotes
+pub struct Json5;
+pub struct Toml;
+
+/// In the order `locate` prefers them, the commented ones first since they
+/// only exist when someone wrote or converted one
+pub const FORMATS: &[&dyn ConfigFormat] = &[&Json5, &Toml, &Json];
+
+/// Format of `path` by its extension, JSON when it has no known one
+pub fn format_for(path: &Path) -> &'static dyn ConfigFormat {
+    let extension = path
+        .extension()
+        .and_then(|e| e.to_str())
+        .unwrap_or_default();
+    FORMATS
+        .iter()
+        .copied()
+        .find(|f| {
+            f.extensions()
+                .iter()
+                .any(|e| e.eq_ignore_ascii_case(extension))
+        })
+        .unwrap_or(&Json)
+}
+
+/// Config file for `path` in whichever format exists, `conf.json` finds
+/// `conf.json5` or `conf.toml`. `path` itself when none does or it isn't a
+/// config file.
+pub fn locate(path: &Path) -> PathBuf {
+    let extension = path
+        .extension()
+        .and_then(|e| e.to_str())
+        .unwrap_or_default();
+    let is_config = FORMATS.iter().any(|f| f.extensions().contains(&extension));
+    if !is_config {
+        return path.to_path_buf();
+    }
+    FORMATS
+        .iter()
+        .flat_map(|f| f.extensions())
+        .map(|e| path.with_extension(e))
+        .find(|p| p.exists())
+        .unwrap_or_else(|| path.to_path_buf())
+}
+
+/// Read and deserialise a config file in the format of its extension
+pub fn read_config<T: DeserializeOwned>(path: &Path) -> Result<T> {
+    let content = fs::read_to_string(path)?;
+    format_for(path)
+        .decode(&content)
+        .with_context(|| format!("Failed to parse {}", path.display()))
+}
+
+/// Rewrite `from` in the format of `to`, an existing `to` is left alone
+pub fn convert(from: &Path, to: &Path) -> Result<()> {
+    if to.exists() {
+        bail!("{} already exists", to.display());
+    }
+    let content = fs::read_to_string(from)?;
+    let value = format_for(from)
+        .parse(&content)
+        .with_context(|| format!("Failed to parse {}", from.display()))?;
+    let rendered = format_for(to).render(&value, None)?;
+    write_atomic(to, &rendered, 0)
+}
+
+impl ConfigFormat for Json {
+    fn extensions(&self) -> &'static [&'static str] {
+        &["json"]
+    }
+
+    fn parse(&self, content: &str) -> Result<Value> {
+        Ok(serde_json::from_str(content)?)
+    }
+
+    fn render(&self, value: &Value, _: Option<&str>) -> Result<String> {
+        Ok(serde_json::to_string_pretty(value)?)
+    }
+}
+
+impl ConfigFormat for Json5 {
+    fn extensions(&self) -> &'static [&'static str] {
+        &["json5", "jsonc"]
+    }
+
+    fn parse(&self, content: &str) -> Result<Value> {
+        Ok(json5::from_str(content)?)
+    }
+
+    fn render(&self, value: &Value, previous: Option<&str>) -> Result<String> {
+        let comments = previous.map(Comments::scan).unwrap_or_default();
+        let mut out = String::new();
+        for comment in comments.before(&[]) {
+            out.push_str(comment);
+            out.push('\n');
+        }
+        render_json5(value, &mut Vec::new(), &comments, 0, &mut out);
+        out.push('\n');
+        for comment in &comments.footer {
+            out.push_str(comment);
+            out.push('\n');
+        }
+        Ok(out)
+    }
+}
+
+impl ConfigFormat for Toml {
+    fn extensions(&self) -> &'static [&'static str] {
+        &["toml"]
+    }
+
+    fn parse(&self, content: &str) -> Result<Value> {
+        Ok(toml_edit::de::from_str(content)?)
+    }
+
+    fn render(&self, value: &Value, previous: Option<&str>) -> Result<String> {
+        // TOML has no null, a `None` is left out
+        let value = without_nulls(value);
+        let mut new = toml_edit::ser::to_document(&value)?;
+        expand_tables(new.as_item_mut());
+        let Some(previous) = previous.and_then(|p| p.parse::<DocumentMut>().ok()) else {
+            return Ok(new.to_string());
+        };
+        let mut doc = previous;
+        merge_toml(doc.as_item_mut(), new.as_item().clone());
+        Ok(doc.to_string())
+    }
+}
+
+fn without_nulls(value: &Value) -> Value {
+    match value {
+        Value::Object(object) => Value::Object(
+            object
+                .iter()
+                .filter(|(_, v)| !v.is_null())
+                .map(|(k, v)| (k.clone(), without_nulls(v)))
+                .collect(),
+        ),
+        Value::Array(array) => Value::Array(array.iter().map(without_nulls).collect()),
+        value => value.clone(),
+    }
+}
+
+/// Turn the inline tables the serializer writes into `[table]` sections and
+/// arrays of them into `[[table]]` ones
+fn expand_tables(item: &mut Item) {
+    let Item::Table(table) = item else {
+        return;
+    };
+    for (_, item) in table.iter_mut() {
+        let expanded = match std::mem::take(item) {
+            Item::Value(toml_edit::Value::InlineTable(inline)) => Item::Table(inline.into_table()),
+            Item::Value(toml_edit::Value::Array(array))
+                if !array.is_empty() && array.iter().all(|v| v.is_inline_table()) =>
+            {
+                let tables = array.into_iter().filter_map(|v| match v {
+                    toml_edit::Value::InlineTable(inline) => Some(inline.into_table()),
+                    _ => None,
+                });
+                Item::ArrayOfTables(toml_edit::ArrayOfTables::from_iter(tables))
+            }
+            other => other,
+        };
+        *item = expanded;
+        expand_tables(item);
+    }
+}
+
+/// Write `new` over `old` keeping the layout, order and comments of `old`
+/// for every key still there
+fn merge_toml(old: &mut Item, new: Item) {
+    match (old, new) {
+        (Item::Table(old), Item::Table(new)) => {
+            let gone: Vec<String> = old
+                .iter()
+                .map(|(k, _)| k.to_string())
+                .filter(|k| !new.contains_key(k))
+                .collect();
+            for key in gone {
+                old.remove(&key);
+            }
+            for (key, item) in new {
+                match old.get_mut(&key) {
+                    Some(slot) => merge_toml(slot, item),
+                    None => {
+                        old.insert(&key, item);
+                    }
+                }
+            }
+        }
+        (Item::Value(old), Item::Value(new)) => {
+            let decor = old.decor().clone();
+            *old = new;
+            *old.decor_mut() = decor;
+        }
+        (old, new) => *old = new,
+    }
+}
+
+/// Comments of a JSON5 file by the path of the key or element they sit at
+#[derive(Debug, Default)]
+struct Comments {
+    /// Own line comments above a key or element
+    before: HashMap<Vec<String>, Vec<String>>,
+    /// Comment on the same line after a value
+    after: HashMap<Vec<String>, String>,
+    /// Comments before the closing bracket of an object or array
+    end: HashMap<Vec<String>, Vec<String>>,
+    /// Comments after the document
+    footer: Vec<String>,
+}
+
+enum Frame {
+    Object(Option<String>),
+    Array(usize),
+}
+
+impl Comments {
+    fn before(&self, path: &[String]) -> &[String] {
+        self.before.get(path).map_or(&[], Vec::as_slice)
+    }
+
+    fn scan(source: &str) -> Self {
+        let mut comments = Comments::default();
+        let chars: Vec<char> = source.chars().collect();
+        let mut stack: Vec<Frame> = Vec::new();
+        let mut pending: Vec<String> = Vec::new();
+        // value that ended last, while nothing but spaces followed it
+        let mut same_line: Option<Vec<String>> = None;
+        let path = |stack: &[Frame]| -> Vec<String> {
+            stack
+                .iter()
+                .filter_map(|frame| match frame {
+                    Frame::Object(key) => key.clone(),
+                    Frame::Array(index) => Some(index.to_string()),
+                })
+                .collect()
+        };
+        let mut i = 0;
+        while i < chars.len() {
+            let c = chars[i];
+            match c {
+                '\n' => {
+                    same_line = None;
+                    i += 1;
+                }
+                '/' if matches!(chars.get(i + 1), Some('/') | Some('*')) => {
+                    let start = i;
+                    if chars[i + 1] == '/' {
+                        while i < chars.len() && chars[i] != '\n' {
+                            i += 1;
+                        }
+                    } else {
+                        i += 2;
+                        while i < chars.len()
+                            && !(chars[i] == '*' && chars.get(i + 1) == Some(&'/'))
+                        {
+                            i += 1;
+                        }
+                        i = (i + 2).min(chars.len());
+                    }
+                    let comment: String =
+                        chars[start..i].iter().collect::<String>().trim_end().into();
+                    match same_line.take() {
+                        Some(path) => {
+                            comments.after.insert(path, comment);
+                        }
+                        None => pending.push(comment),
+                    }
+                }
+                '{' | '[' => {
+                    // a container starting as a value takes the comments above it
+                    if !pending.is_empty() {
+                        comments
+                            .before
+                            .entry(path(&stack))
+                            .or_default()
+                            .append(&mut pending);
+                    }
+                    stack.push(match c {
+                        '{' => Frame::Object(None),
+                        _ => Frame::Array(0),
+                    });
+                    same_line = None;
+                    i += 1;
+                }
+                '}' | ']' => {
+                    if !pending.is_empty() {
+                        comments
+                            .end
+                            .insert(path(&stack), std::mem::take(&mut pending));
+                    }
+                    stack.pop();
+                    same_line = Some(path(&stack));
+                    if stack.is_empty() {
+                        same_line = None;
+                    }
+                    i += 1;
+                }
+                ',' => {
+                    match stack.last_mut() {
+                        Some(Frame::Array(index)) => *index += 1,
+                        Some(Frame::Object(key)) => *key = None,
+                        None => {}
+                    }
+                    i += 1;
+                }
+                c if c.is_whitespace() || c == ':' => i += 1,
+                _ => {
+                    // string, identifier, number or literal
+                    let start = i;
+                    if c == '"' || c == '\'' {
+                        i += 1;
+                        while i < chars.len() && chars[i] != c {
+                            if chars[i] == '\\' {
+                                i += 1;
+                            }
+                            i += 1;
+                        }
+                        i = (i + 1).min(chars.len());
+                    } else {
+                        while i < chars.len()
+                            && !chars[i].is_whitespace()
+                            && !matches!(chars[i], ',' | ':' | '}' | ']' | '/')
+                        {
+                            i += 1;
+                        }
+                    }
+                    let token: String = chars[start..i].iter().collect();
+                    if let Some(Frame::Object(key @ None)) = stack.last_mut() {
+                        let name = json5::from_str::<String>(&token).unwrap_or(token);
+                        *key = Some(name);
+                        if !pending.is_empty() {
+                            comments
+                                .before
+                                .insert(path(&stack), std::mem::take(&mut pending));
+                        }
+                    } else {
+                        if !pending.is_empty() {
+                            comments
+                                .before
+                                .insert(path(&stack), std::mem::take(&mut pending));
+                        }
+                        same_line = Some(path(&stack));
+                    }
+                }
+            }
+        }
+        comments.footer = pending;
+        comments
+    }
+}
+
+fn render_json5(
+    value: &Value,
+    path: &mut Vec<String>,
+    comments: &Comments,
+    depth: usize,
+    out: &mut String,
+) {
+    let indent = "  ".repeat(depth + 1);
+    let entries: Vec<(String, &Value)> = match value {
+        Value::Object(object) => object.iter().map(|(k, v)| (k.clone(), v)).collect(),
+        Value::Array(array) => array
+            .iter()
+            .enumerate()
+            .map(|(i, v)| (i.to_string(), v))
+            .collect(),
+        value => {
+            out.push_str(&value.to_string());
+            return;
+        }
+    };
+    let (open, close) = match value.is_object() {
+        true => ('{', '}'),
+        false => ('[', ']'),
+    };
+    let end = comments.end.get(path.as_slice());
+    if entries.is_empty() && end.is_none() {
+        out.push(open);
+        out.push(close);
+        return;
+    }
+    out.push(open);
+    out.push('\n');
+    let last = entries.len().saturating_sub(1);
+    for (n, (key, value)) in entries.into_iter().enumerate() {
+        path.push(key);
+        for comment in comments.before(path) {
+            out.push_str(&format!("{}{}\n", indent, comment));
+        }
+        out.push_str(&indent);
+        if let Some(key) = path.last().filter(|_| open == '{') {
+            out.push_str(&format!("{}: ", Value::from(key.as_str())));
+        }
+        render_json5(value, path, comments, depth + 1, out);
+        if n < last {
+            out.push(',');
+        }
+        if let Some(comment) = comments.after.get(path.as_slice()) {
+            out.push_str(&format!(" {}", comment));
+        }
+        out.push('\n');
+        path.pop();
+    }
+    for comment in end.into_iter().flatten() {
+        out.push_str(&format!("{}{}\n", indent, comment));
+    }
+    out.push_str(&"  ".repeat(depth));
+    out.push(close);
+}
+
+#[cfg(test)]
+mod formats {
+    use super::*;
+    use serde_json::json;
+
+    const CONF_JSON5: &str = r#"// machine wide settings
+{
+  // where the photoshop assets live
+  store_root: 'D:/tsck',
+  http_server_port: 3031, // the file server
+  apps: [
+    // the first one is cycled to first
+    "photoshop",
+    "code",
+  ],
+  command_config: {
+    log_limit: 100,
+    // nothing yet
+  },
+}
+// end
+"#;
+
+    #[test]
+    fn test_format_for() {
+        assert!(
+            format_for(Path::new("conf.json5"))
+                .extensions()
+                .contains(&"json5")
+        );
+        assert!(
+            format_for(Path::new("conf.TOML"))
+                .extensions()
+                .contains(&"toml")
+        );
+        assert!(
+            format_for(Path::new("kee.kee"))
+                .extensions()
+                .contains(&"json")
+        );
+    }
+
+    #[test]
+    fn test_json5() -> Result<()> {
+        let value = Json5.parse(CONF_JSON5)?;
+        assert_eq!(value["http_server_port"], 3031);
+        assert_eq!(value["apps"], json!(["photoshop", "code"]));
+
+        // unchanged values come back with their comments
+        let rendered = Json5.render(&value, Some(CONF_JSON5))?;
+        assert_eq!(
+            rendered,
+            r#"// machine wide settings
+{
+  // where the photoshop assets live
+  "store_root": "D:/tsck",
+  "http_server_port": 3031, // the file server
+  "apps": [
+    // the first one is cycled to first
+    "photoshop",
+    "code"
+  ],
+  "command_config": {
+    "log_limit": 100
+    // nothing yet
+  }
+}
+// end
+"#
+        );
+        assert_eq!(Json5.parse(&rendered)?, value);
+
+        let mut changed = value.clone();
+        changed["http_server_port"] = json!(4000);
+        changed["apps"] = json!(["code"]);
+        changed.as_object_mut().unwrap().remove("store_root");
+        let rendered = Json5.render(&changed, Some(CONF_JSON5))?;
+        assert!(rendered.contains("\"http_server_port\": 4000, // the file server"));
+        assert!(!rendered.contains("photoshop assets"));
+        assert!(rendered.contains("    // the first one is cycled to first\n    \"code\""));
+        Ok(())
+    }
+
+    #[test]
+    fn test_toml() -> Result<()> {
+        let previous = r#"# machine wide settings
+store_root = "D:/tsck" # assets
+http_server_port = 3031
+
+# processes started from the command palette
+[command_config]
+log_limit = 100
+"#;
+        let mut value = Toml.parse(previous)?;
+        assert_eq!(value["command_config"]["log_limit"], 100);
+        value["http_server_port"] = json!(4000);
+        value["comfyui_url"] = Value::Null;
+        value["apps"] = json!(["code"]);
+        let rendered = Toml.render(&value, Some(previous))?;
+        assert_eq!(
+            rendered,
+            r#"# machine wide settings
+store_root = "D:/tsck" # assets
+http_server_port = 4000
+apps = ["code"]
+
+# processes started from the command palette
+[command_config]
+log_limit = 100
+"#
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_locate_and_convert() -> Result<()> {
+        let dir = std::env::temp_dir().join(format!("tsck-formats-{}", std::process::id()));
+        fs::create_dir_all(&dir)?;
+        let json = dir.join("conf.json");
+        fs::write(&json, r#"{ "store_root": "D:/tsck", "apps": ["code"] }"#)?;
+        assert_eq!(locate(&json), json);
+        assert_eq!(locate(&dir.join("kee.kee")), dir.join("kee.kee"));
+
+        let toml = dir.join("conf.toml");
+        convert(&json, &toml)?;
+        assert!(convert(&json, &toml).is_err());
+        assert_eq!(locate(&json), toml);
+        assert_eq!(
+            read_config::<Value>(&toml)?,
+            json!({ "store_root": "D:/tsck", "apps": ["code"] })
+        );
+
+        fs::remove_dir_all(&dir)?;
+        Ok(())
+    }
+}
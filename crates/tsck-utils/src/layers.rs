@@ -0,0 +1,369 @@
+use crate::{format::format_for, suggest::did_you_mean};
+use anyhow::{Result, anyhow, bail};
+use serde::{Serialize, de::DeserializeOwned};
+use serde_json::{Map, Value};
+use std::{
+    collections::BTreeMap,
+    fmt, fs,
+    path::{Path, PathBuf},
+};
+
+/// Layer an effective value came from
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub enum Origin {
+    /// Compiled in, `Default` of the config
+    Default,
+    File(PathBuf),
+    /// Environment variable, `TSCK_HTTP_SERVER_PORT`
+    Env(String),
+    /// `--set key=value`
+    Cli,
+}
+
+impl fmt::Display for Origin {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Origin::Default => write!(f, "default"),
+            Origin::File(path) => write!(f, "{}", path.display()),
+            Origin::Env(var) => write!(f, "env {}", var),
+            Origin::Cli => write!(f, "--set"),
+        }
+    }
+}
+
+/// Config merged from layers, each one overriding the keys it sets in the
+/// ones below: defaults, files, environment, command line. Objects merge key
+/// by key, anything else (arrays included) is replaced whole. A layer that
+/// doesn't fit the config is skipped and its error kept.
+pub struct Layered<T> {
+    data: T,
+    value: Value,
+    /// Origin of every leaf, by dotted key
+    origins: BTreeMap<String, Origin>,
+    errors: Vec<anyhow::Error>,
+}
+
+impl<T> Layered<T>
+where
+    T: Serialize + DeserializeOwned + Default,
+{
+    pub fn new() -> Result<Self> {
+        let value = serde_json::to_value(T::default())?;
+        let mut origins = BTreeMap::new();
+        leaves(&value, "", &mut |key| {
+            origins.insert(key, Origin::Default);
+        });
+        Ok(Self {
+            data: T::default(),
+            value,
+            origins,
+            errors: Vec::new(),
+        })
+    }
+
+    /// File on top, read in the format of its extension, skipped when it
+    /// doesn't exist
+    pub fn file(mut self, path: impl AsRef<Path>) -> Self {
+        let path = path.as_ref();
+        if !path.exists() {
+            return self;
+        }
+        let layer = fs::read_to_string(path)
+            .map_err(anyhow::Error::from)
+            .and_then(|content| format_for(path).parse(&content));
+        let result = layer.and_then(|layer| self.merge(layer, Origin::File(path.into())));
+        self.keep_error(result, &path.display().to_string())
+    }
+
+    /// `PREFIX_KEY` variables on top, `__` goes one level down:
+    /// `TSCK_COMMAND_CONFIG__LOG_LIMIT` sets `command_config.log_limit`.
+    /// Variables that don't name a key of the config are left alone.
+    pub fn env(mut self, prefix: &str, vars: impl IntoIterator<Item = (String, String)>) -> Self {
+        let mut vars: Vec<(String, String)> = vars.into_iter().collect();
+        vars.sort();
+        for (var, raw) in vars {
+            let Some(key) = var.strip_prefix(prefix) else {
+                continue;
+            };
+            let key = key.to_lowercase().replace("__", ".");
+            let top = key.split('.').next().unwrap_or_default();
+            if self.value.get(top).is_none() {
+                continue;
+            }
+            let result = self.assign(&key, &raw, Origin::Env(var.clone()));
+            self = self.keep_error(result, &var);
+        }
+        self
+    }
+
+    /// `key=value` assignments on top, an unknown key is an error
+    pub fn set<S: AsRef<str>>(mut self, assignments: impl IntoIterator<Item = S>) -> Self {
+        for assignment in assignments {
+            let assignment = assignment.as_ref();
+            let result = match assignment.split_once('=') {
+                Some((key, raw)) => self.assign(key.trim(), raw, Origin::Cli),
+                None => Err(anyhow!("expected key=value")),
+            };
+            self = self.keep_error(result, &format!("--set {}", assignment));
+        }
+        self
+    }
+
+    /// Set one dotted key from a raw string. It stays a string where the
+    /// config holds one, else it is read as JSON, `8080`, `true`, `["a"]`.
+    fn assign(&mut self, key: &str, raw: &str, origin: Origin) -> Result<()> {
+        let segments: Vec<&str> = key.split('.').collect();
+        if segments.iter().any(|s| s.is_empty()) {
+            bail!("empty key in `{}`", key);
+        }
+        let known = self.value.as_object().map(|o| o.keys().map(String::as_str));
+        if self.value.get(segments[0]).is_none() {
+            return Err(
+                match known.and_then(|known| did_you_mean(segments[0], known)) {
+                    Some(hint) => anyhow!("unknown key `{}` (did you mean {}?)", key, hint),
+                    None => anyhow!("unknown key `{}`", key),
+                },
+            );
+        }
+        let current = segments
+            .iter()
+            .try_fold(&self.value, |value, segment| value.get(segment));
+        let value = match current {
+            Some(Value::String(_)) => Value::String(raw.to_string()),
+            _ => serde_json::from_str(raw).unwrap_or_else(|_| Value::String(raw.to_string())),
+        };
+        let layer = segments.iter().rev().fold(value, |value, segment| {
+            Value::Object(Map::from_iter([(segment.to_string(), value)]))
+        });
+        self.merge(layer, origin)
+    }
+
+    /// Merge `layer` on top, it is dropped when the result doesn't deserialise
+    fn merge(&mut self, layer: Value, origin: Origin) -> Result<()> {
+        if !layer.is_object() {
+            bail!("expected an object at the top level");
+        }
+        let mut value = self.value.clone();
+        merge_value(&mut value, layer.clone());
+        self.data = serde_json::from_value(value.clone())?;
+        self.value = value;
+        leaves(&layer, "", &mut |key| {
+            // a replaced array or object drops the origins of what it held
+            let nested = format!("{}.", key);
+            self.origins.retain(|k, _| !k.starts_with(&nested));
+            self.origins.insert(key, origin.clone());
+        });
+        Ok(())
+    }
+
+    fn keep_error(mut self, result: Result<()>, layer: &str) -> Self {
+        if let Err(err) = result {
+            self.errors.push(err.context(format!("{} skipped", layer)));
+        }
+        self
+    }
+
+    pub fn get(&self) -> &T {
+        &self.data
+    }
+
+    pub fn into_inner(self) -> T {
+        self.data
+    }
+
+    pub fn value(&self) -> &Value {
+        &self.value
+    }
+
+    /// Where each leaf of the effective config comes from, by dotted key
+    pub fn origins(&self) -> &BTreeMap<String, Origin> {
+        &self.origins
+    }
+
+    pub fn origin(&self, key: &str) -> Option<&Origin> {
+        self.origins.get(key)
+    }
+
+    /// Layers, variables and assignments that were skipped
+    pub fn errors(&self) -> &[anyhow::Error] {
+        &self.errors
+    }
+
+    /// `key = value  (origin)` for every leaf
+    pub fn show_origins(&self) -> String {
+        let width = self.origins.keys().map(String::len).max().unwrap_or(0);
+        self.origins
+            .iter()
+            .map(|(key, origin)| {
+                let value = key
+                    .split('.')
+                    .try_fold(&self.value, |value, segment| value.get(segment))
+                    .unwrap_or(&Value::Null);
+                format!("{:width$} = {}  ({})\n", key, value, origin, width = width)
+            })
+            .collect()
+    }
+}
+
+fn merge_value(base: &mut Value, layer: Value) {
+    match (base, layer) {
+        (Value::Object(base), Value::Object(layer)) => {
+            for (key, value) in layer {
+                match base.get_mut(&key) {
+                    Some(slot) => merge_value(slot, value),
+                    None => {
+                        base.insert(key, value);
+                    }
+                }
+            }
+        }
+        (base, layer) => *base = layer,
+    }
+}
+
+/// Dotted keys of every non-object value and empty object
+fn leaves(value: &Value, prefix: &str, f: &mut impl FnMut(String)) {
+    match value {
+        Value::Object(object) if !object.is_empty() => {
+            for (key, value) in object {
+                let key = match prefix.is_empty() {
+                    true => key.clone(),
+                    false => format!("{}.{}", prefix, key),
+                };
+                leaves(value, &key, f);
+            }
+        }
+        _ => f(prefix.to_string()),
+    }
+}
+
+#[cfg(test)]
+mod layered {
+    use super::*;
+    use serde::Deserialize;
+
+    #[derive(Debug, Serialize, Deserialize, PartialEq)]
+    #[serde(default)]
+    struct Conf {
+        store_root: String,
+        http_server_port: u16,
+        apps: Vec<String>,
+        command_config: Commands,
+    }
+
+    #[derive(Debug, Serialize, Deserialize, PartialEq)]
+    #[serde(default)]
+    struct Commands {
+        log_limit: u32,
+        shell: String,
+    }
+
+    impl Default for Conf {
+        fn default() -> Self {
+            Self {
+                store_root: "C:/tsck".into(),
+                http_server_port: 3031,
+                apps: vec!["code".into()],
+                command_config: Commands::default(),
+            }
+        }
+    }
+
+    impl Default for Commands {
+        fn default() -> Self {
+            Self {
+                log_limit: 100,
+                shell: "pwsh".into(),
+            }
+        }
+    }
+
+    fn env(vars: &[(&str, &str)]) -> Vec<(String, String)> {
+        vars.iter()
+            .map(|(k, v)| (k.to_string(), v.to_string()))
+            .collect()
+    }
+
+    #[test]
+    fn test_layers() -> Result<()> {
+        let dir = std::env::temp_dir().join(format!("tsck-layers-{}", std::process::id()));
+        fs::create_dir_all(&dir)?;
+        let conf = dir.join("conf.json");
+        let local = dir.join("conf.local.json");
+        fs::write(
+            &conf,
+            r#"{ "store_root": "D:/tsck", "http_server_port": 4000 }"#,
+        )?;
+        fs::write(&local, r#"{ "command_config": { "shell": "cmd" } }"#)?;
+
+        let layered = Layered::<Conf>::new()?
+            .file(&conf)
+            .file(&local)
+            .file(dir.join("missing.json"))
+            .env(
+                "TSCK_",
+                env(&[
+                    ("TSCK_HTTP_SERVER_PORT", "5000"),
+                    ("TSCK_COMMAND_CONFIG__LOG_LIMIT", "20"),
+                    ("TSCK_HOME", "/elsewhere"),
+                    ("PATH", "/bin"),
+                ]),
+            )
+            .set(["store_root=E:/tsck", "apps=[\"photoshop\"]"]);
+        assert!(layered.errors().is_empty());
+        let conf_data = layered.get();
+        assert_eq!(conf_data.store_root, "E:/tsck");
+        assert_eq!(conf_data.http_server_port, 5000);
+        assert_eq!(conf_data.apps, ["photoshop"]);
+        assert_eq!(conf_data.command_config.log_limit, 20);
+        assert_eq!(conf_data.command_config.shell, "cmd");
+
+        assert_eq!(layered.origin("store_root"), Some(&Origin::Cli));
+        assert_eq!(
+            layered.origin("http_server_port"),
+            Some(&Origin::Env("TSCK_HTTP_SERVER_PORT".into()))
+        );
+        assert_eq!(
+            layered.origin("command_config.shell"),
+            Some(&Origin::File(local.clone()))
+        );
+        assert_eq!(
+            layered
+                .origin("command_config.log_limit")
+                .map(|o| o.to_string())
+                .as_deref(),
+            Some("env TSCK_COMMAND_CONFIG__LOG_LIMIT")
+        );
+        assert!(layered.origin("home").is_none());
+        let shown = layered.show_origins();
+        let line = shown.lines().find(|l| l.starts_with("http_server_port"));
+        assert_eq!(
+            line.map(|l| l.split_whitespace().collect::<Vec<_>>().join(" ")),
+            Some("http_server_port = 5000 (env TSCK_HTTP_SERVER_PORT)".into())
+        );
+
+        fs::remove_dir_all(&dir)?;
+        Ok(())
+    }
+
+    #[test]
+    fn test_skipped() -> Result<()> {
+        let layered = Layered::<Conf>::new()?
+            .env("TSCK_", env(&[("TSCK_HTTP_SERVER_PORT", "http")]))
+            .set(["http_server_prot=80", "apps", "http_server_port=90"]);
+        assert_eq!(layered.get().http_server_port, 90);
+        let errors: Vec<String> = layered
+            .errors()
+            .iter()
+            .map(|e| format!("{:#}", e))
+            .collect();
+        assert_eq!(errors.len(), 3);
+        assert!(errors[0].starts_with("TSCK_HTTP_SERVER_PORT skipped: "));
+        assert_eq!(
+            errors[1],
+            "--set http_server_prot=80 skipped: unknown key `http_server_prot` (did you mean http_server_port?)"
+        );
+        assert_eq!(errors[2], "--set apps skipped: expected key=value");
+        Ok(())
+    }
+}
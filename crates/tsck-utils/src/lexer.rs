@@ -1,30 +1,355 @@
-#[derive(Debug, PartialEq)]
-enum Token<'a> {
+use std::borrow::Cow;
+
+#[derive(Debug, Clone, PartialEq)]
+enum FuncToken<'a> {
     Ident(&'a str),
-    String(&'a str),
+    String(Cow<'a, str>),
     Number(i64),
-    DoubleColon,
+    Float(f64),
     LParen,
     RParen,
+    LBracket,
+    RBracket,
     Comma,
+    DoubleColon,
+    Semicolon,
+    Eq,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum FuncExpr<'a> {
+    Number(i64),
+    Float(f64),
+    Bool(bool),
+    String(Cow<'a, str>),
+    Ident(&'a str),
+    /// `(a, b, ...)`
+    Tuple(Vec<FuncExpr<'a>>),
+    /// `[a, b, ...]`
+    List(Vec<FuncExpr<'a>>),
+    Call {
+        name: &'a str,
+        args: Vec<FuncExpr<'a>>,
+    },
+    /// `name=value` argument
+    Named(&'a str, Box<FuncExpr<'a>>),
+}
+
+/// `entry::FUNC(args...)`
+#[derive(Debug, Clone, PartialEq)]
+pub struct Func<'a> {
+    pub entry: &'a str,
+    pub func: &'a str,
+    pub args: Vec<FuncExpr<'a>>,
+}
+
+/// Conversion of a single argument into a variant field.
+pub trait FromFuncExpr: Sized {
+    fn from_expr(expr: &FuncExpr) -> Result<Self, String>;
+}
+
+impl FromFuncExpr for String {
+    fn from_expr(expr: &FuncExpr) -> Result<Self, String> {
+        match expr {
+            FuncExpr::String(s) => Ok(s.to_string()),
+            FuncExpr::Ident(s) => Ok(s.to_string()),
+            other => Err(format!("expected a string, found {}", other.kind())),
+        }
+    }
+}
+
+impl FromFuncExpr for bool {
+    fn from_expr(expr: &FuncExpr) -> Result<Self, String> {
+        match expr {
+            FuncExpr::Bool(b) => Ok(*b),
+            other => Err(format!("expected a bool, found {}", other.kind())),
+        }
+    }
+}
+
+macro_rules! from_func_expr_int {
+    ($($ty:ty),+) => {$(
+        impl FromFuncExpr for $ty {
+            fn from_expr(expr: &FuncExpr) -> Result<Self, String> {
+                match expr {
+                    FuncExpr::Number(n) => <$ty>::try_from(*n)
+                        .map_err(|_| format!("{} is out of range for {}", n, stringify!($ty))),
+                    other => Err(format!("expected a number, found {}", other.kind())),
+                }
+            }
+        }
+    )+};
+}
+from_func_expr_int!(i32, i64, u32, u64, usize);
+
+macro_rules! from_func_expr_float {
+    ($($ty:ty),+) => {$(
+        impl FromFuncExpr for $ty {
+            fn from_expr(expr: &FuncExpr) -> Result<Self, String> {
+                match expr {
+                    FuncExpr::Float(n) => Ok(*n as $ty),
+                    FuncExpr::Number(n) => Ok(*n as $ty),
+                    other => Err(format!("expected a float, found {}", other.kind())),
+                }
+            }
+        }
+    )+};
+}
+from_func_expr_float!(f32, f64);
+
+/// `none` leaves an optional field empty, the derive also does when it's omitted
+impl<T: FromFuncExpr> FromFuncExpr for Option<T> {
+    fn from_expr(expr: &FuncExpr) -> Result<Self, String> {
+        match expr {
+            FuncExpr::Ident(name) if name.eq_ignore_ascii_case("none") => Ok(None),
+            expr => T::from_expr(expr).map(Some),
+        }
+    }
+}
+
+/// `[a, b]`, or `(a, b)`
+impl<T: FromFuncExpr> FromFuncExpr for Vec<T> {
+    fn from_expr(expr: &FuncExpr) -> Result<Self, String> {
+        match expr {
+            FuncExpr::List(items) | FuncExpr::Tuple(items) => items
+                .iter()
+                .enumerate()
+                .map(|(i, item)| T::from_expr(item).map_err(|e| format!("item {}: {}", i + 1, e)))
+                .collect(),
+            other => Err(format!("expected a list, found {}", other.kind())),
+        }
+    }
+}
+
+macro_rules! from_func_expr_tuple {
+    ($len:literal => $($ty:ident $index:tt),+) => {
+        impl<$($ty: FromFuncExpr),+> FromFuncExpr for ($($ty,)+) {
+            fn from_expr(expr: &FuncExpr) -> Result<Self, String> {
+                match expr {
+                    FuncExpr::Tuple(items) | FuncExpr::List(items) if items.len() == $len => Ok(($(
+                        $ty::from_expr(&items[$index])
+                            .map_err(|e| format!("item {}: {}", $index + 1, e))?,
+                    )+)),
+                    FuncExpr::Tuple(items) | FuncExpr::List(items) => Err(format!(
+                        "expected a tuple of {}, found {} item(s)",
+                        $len,
+                        items.len()
+                    )),
+                    other => Err(format!("expected a tuple, found {}", other.kind())),
+                }
+            }
+        }
+    };
+}
+from_func_expr_tuple!(2 => A 0, B 1);
+from_func_expr_tuple!(3 => A 0, B 1, C 2);
+
+/// Conversion of a variant field back into an argument, the inverse of
+/// `FromFuncExpr`.
+pub trait ToFuncExpr {
+    fn to_expr(&self) -> FuncExpr<'static>;
+}
+
+impl ToFuncExpr for str {
+    fn to_expr(&self) -> FuncExpr<'static> {
+        FuncExpr::String(Cow::Owned(self.to_string()))
+    }
+}
+
+impl ToFuncExpr for String {
+    fn to_expr(&self) -> FuncExpr<'static> {
+        self.as_str().to_expr()
+    }
+}
+
+impl ToFuncExpr for bool {
+    fn to_expr(&self) -> FuncExpr<'static> {
+        FuncExpr::Bool(*self)
+    }
+}
+
+macro_rules! to_func_expr_num {
+    ($variant:ident, $target:ty, $($ty:ty),+) => {$(
+        impl ToFuncExpr for $ty {
+            fn to_expr(&self) -> FuncExpr<'static> {
+                FuncExpr::$variant(*self as $target)
+            }
+        }
+    )+};
+}
+to_func_expr_num!(Number, i64, i32, i64, u32, u64, usize);
+to_func_expr_num!(Float, f64, f32, f64);
+
+impl<T: ToFuncExpr> ToFuncExpr for Option<T> {
+    fn to_expr(&self) -> FuncExpr<'static> {
+        match self {
+            Some(value) => value.to_expr(),
+            None => FuncExpr::Ident("none"),
+        }
+    }
+}
+
+impl<T: ToFuncExpr> ToFuncExpr for Vec<T> {
+    fn to_expr(&self) -> FuncExpr<'static> {
+        FuncExpr::List(self.iter().map(ToFuncExpr::to_expr).collect())
+    }
+}
+
+macro_rules! to_func_expr_tuple {
+    ($($ty:ident $index:tt),+) => {
+        impl<$($ty: ToFuncExpr),+> ToFuncExpr for ($($ty,)+) {
+            fn to_expr(&self) -> FuncExpr<'static> {
+                FuncExpr::Tuple(vec![$(self.$index.to_expr()),+])
+            }
+        }
+    };
+}
+to_func_expr_tuple!(A 0, B 1);
+to_func_expr_tuple!(A 0, B 1, C 2);
+
+impl<'a> FuncExpr<'a> {
+    /// Convert the argument into a variant field, used by the `FuncParser` derive.
+    pub fn parse_as<T: FromFuncExpr>(&self) -> Result<T, String> {
+        T::from_expr(self)
+    }
+
+    /// Argument for a variant field, used by the `FuncParser` derive to print
+    /// a value back as `FUNC(args)`.
+    pub fn of<T: ToFuncExpr + ?Sized>(value: &T) -> FuncExpr<'static> {
+        value.to_expr()
+    }
+
+    pub fn kind(&self) -> &'static str {
+        match self {
+            FuncExpr::Number(_) => "a number",
+            FuncExpr::Float(_) => "a float",
+            FuncExpr::Bool(_) => "a bool",
+            FuncExpr::String(_) => "a string",
+            FuncExpr::Ident(_) => "an identifier",
+            FuncExpr::Tuple(_) => "a tuple",
+            FuncExpr::List(_) => "a list",
+            FuncExpr::Call { .. } => "a call",
+            FuncExpr::Named(..) => "a named argument",
+        }
+    }
+
+    /// Match positional and `name=value` arguments to the fields of a variant.
+    /// Fields with an empty name can only be passed by position.
+    pub fn bind<'b>(
+        args: &'b [FuncExpr<'a>],
+        names: &[&str],
+    ) -> Result<Vec<Option<&'b FuncExpr<'a>>>, String> {
+        let label = |index: usize| match names[index] {
+            "" => (index + 1).to_string(),
+            name => name.to_string(),
+        };
+        let mut slots = vec![None; names.len()];
+        let mut position = 0;
+        for arg in args {
+            let (index, value) = match arg {
+                FuncExpr::Named(name, value) => {
+                    let index = names
+                        .iter()
+                        .position(|n| !n.is_empty() && n.eq_ignore_ascii_case(name))
+                        .ok_or_else(|| format!("unknown argument `{}`", name))?;
+                    (index, value.as_ref())
+                }
+                value => {
+                    if position >= names.len() {
+                        return Err(format!(
+                            "expected at most {} argument(s), found {}",
+                            names.len(),
+                            args.len()
+                        ));
+                    }
+                    position += 1;
+                    (position - 1, value)
+                }
+            };
+            if slots[index].is_some() {
+                return Err(format!("argument `{}` given twice", label(index)));
+            }
+            slots[index] = Some(value);
+        }
+        Ok(slots)
+    }
+}
+
+fn write_list(f: &mut std::fmt::Formatter<'_>, items: &[FuncExpr]) -> std::fmt::Result {
+    for (i, item) in items.iter().enumerate() {
+        if i > 0 {
+            f.write_str(", ")?;
+        }
+        write!(f, "{}", item)?;
+    }
+    Ok(())
+}
+
+/// Prints the kee.kee spelling, strings are single quoted.
+impl std::fmt::Display for FuncExpr<'_> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            FuncExpr::Number(n) => write!(f, "{}", n),
+            // `{:?}` keeps the `.0` of whole floats
+            FuncExpr::Float(n) => write!(f, "{:?}", n),
+            FuncExpr::Bool(b) => write!(f, "{}", b),
+            FuncExpr::String(s) => {
+                f.write_str("'")?;
+                for c in s.chars() {
+                    match c {
+                        '\\' => f.write_str("\\\\")?,
+                        '\'' => f.write_str("\\'")?,
+                        '\n' => f.write_str("\\n")?,
+                        '\t' => f.write_str("\\t")?,
+                        '\r' => f.write_str("\\r")?,
+                        '\0' => f.write_str("\\0")?,
+                        c => write!(f, "{}", c)?,
+                    }
+                }
+                f.write_str("'")
+            }
+            FuncExpr::Ident(name) => f.write_str(name),
+            FuncExpr::Tuple(items) => {
+                f.write_str("(")?;
+                write_list(f, items)?;
+                f.write_str(")")
+            }
+            FuncExpr::List(items) => {
+                f.write_str("[")?;
+                write_list(f, items)?;
+                f.write_str("]")
+            }
+            FuncExpr::Call { name, args } => {
+                write!(f, "{}(", name)?;
+                write_list(f, args)?;
+                f.write_str(")")
+            }
+            FuncExpr::Named(name, value) => write!(f, "{}={}", name, value),
+        }
+    }
 }
+
+/// `entry::FUNC(args...)`, without parentheses when there are no arguments
+impl std::fmt::Display for Func<'_> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}::{}", self.entry, self.func)?;
+        if !self.args.is_empty() {
+            f.write_str("(")?;
+            write_list(f, &self.args)?;
+            f.write_str(")")?;
+        }
+        Ok(())
+    }
+}
+
 #[derive(Debug, Clone)]
-struct Lexer<'a> {
+pub struct FuncLexer<'a> {
     input: &'a str,
     pos: usize,
 }
 
-#[derive(Debug)]
-pub enum Expr<'a> {
-    Ident(&'a str),
-    String(&'a str),
-    Number(i64),
-    Tuple(Vec<Expr<'a>>),
-    Call { name: &'a str, args: Vec<Expr<'a>> },
-}
-
-impl<'a> Lexer<'a> {
-    fn new(input: &'a str) -> Self {
+impl<'a> FuncLexer<'a> {
+    pub fn new(input: &'a str) -> Self {
         Self { input, pos: 0 }
     }
 
@@ -32,180 +357,277 @@ impl<'a> Lexer<'a> {
         self.input[self.pos..].chars().next()
     }
 
+    fn peek_second(&self) -> Option<char> {
+        self.input[self.pos..].chars().nth(1)
+    }
+
     fn next_char(&mut self) -> Option<char> {
         let ch = self.peek()?;
         self.pos += ch.len_utf8();
         Some(ch)
     }
-    fn peek_token(&self) -> Option<Token<'a>> {
+
+    fn skip_whitespace(&mut self) {
+        while matches!(self.peek(), Some(c) if c.is_whitespace()) {
+            self.next_char();
+        }
+    }
+
+    fn at_end(&mut self) -> bool {
+        self.skip_whitespace();
+        self.pos >= self.input.len()
+    }
+
+    fn peek_token(&self) -> Option<FuncToken<'a>> {
         let mut clone = self.clone();
         clone.next_token()
     }
 
-    fn next_token(&mut self) -> Option<Token<'a>> {
-        while let Some(c) = self.peek() {
-            if c.is_whitespace() {
-                self.next_char();
-            } else {
-                break;
-            }
-        }
+    fn next_token(&mut self) -> Option<FuncToken<'a>> {
+        self.skip_whitespace();
         let start = self.pos;
 
         match self.next_char()? {
             ':' => {
                 if self.peek() == Some(':') {
                     self.next_char();
-                    Some(Token::DoubleColon)
+                    Some(FuncToken::DoubleColon)
                 } else {
                     None
                 }
             }
-            '(' => Some(Token::LParen),
-            ')' => Some(Token::RParen),
-            ',' => Some(Token::Comma),
+            '(' => Some(FuncToken::LParen),
+            ')' => Some(FuncToken::RParen),
+            '[' => Some(FuncToken::LBracket),
+            ']' => Some(FuncToken::RBracket),
+            ',' => Some(FuncToken::Comma),
+            ';' => Some(FuncToken::Semicolon),
+            '=' => Some(FuncToken::Eq),
 
-            c if c.is_ascii_digit() => {
-                while let Some(c) = self.peek() {
-                    if c.is_ascii_digit() {
-                        self.next_char();
-                    } else {
-                        break;
-                    }
-                }
-                let num = self.input[start..self.pos].parse().ok()?;
-                Some(Token::Number(num))
+            c if c.is_ascii_digit()
+                || (c == '-' && matches!(self.peek(), Some(d) if d.is_ascii_digit())) =>
+            {
+                self.number(start)
             }
 
             c if c.is_alphabetic() || c == '_' => {
                 while matches!(
-                self.peek(),
-                Some(c) if c.is_alphanumeric() || c == '_' || c=='-'
+                    self.peek(),
+                    Some(c) if c.is_alphanumeric() || c == '_' || c == '-'
                 ) {
                     self.next_char();
                 }
-                Some(Token::Ident(&self.input[start..self.pos]))
+                Some(FuncToken::Ident(&self.input[start..self.pos]))
             }
 
-            c if c == '\'' => {
-                while let Some(c) = self.peek() {
-                    if c != '\'' {
-                        self.next_char();
-                    } else {
-                        break;
-                    }
-                }
-                self.next_char()?;
-                Some(Token::String(&self.input[start + 1..self.pos - 1]))
-            }
+            quote @ ('\'' | '"') => self.string(quote),
 
             _ => None,
         }
     }
-}
 
-#[derive(Debug)]
-pub struct Func<'a> {
-    pub entry: &'a str,
-    pub func: &'a str,
-    pub args: Vec<Expr<'a>>,
-}
-
-fn parse_value<'a>(lexer: &mut Lexer<'a>) -> Option<Expr<'a>> {
-    match lexer.next_token()? {
-        Token::Number(n) => Some(Expr::Number(n)),
-        Token::String(n) => Some(Expr::String(n)),
-
-        Token::Ident(name) => {
-            if let Some(Token::LParen) = lexer.peek_token() {
-                lexer.next_token(); // consume '('
-                let args = parse_arg_list(lexer)?;
-                Some(Expr::Call { name, args })
+    /// `-12`, `40`, `1.5`, `-0.25`
+    fn number(&mut self, start: usize) -> Option<FuncToken<'a>> {
+        let mut is_float = false;
+        while let Some(c) = self.peek() {
+            if c.is_ascii_digit() {
+                self.next_char();
+            } else if c == '.'
+                && !is_float
+                && matches!(self.peek_second(), Some(d) if d.is_ascii_digit())
+            {
+                is_float = true;
+                self.next_char();
             } else {
-                Some(Expr::Ident(name))
+                break;
             }
         }
+        let text = &self.input[start..self.pos];
+        if is_float {
+            text.parse().ok().map(FuncToken::Float)
+        } else {
+            text.parse().ok().map(FuncToken::Number)
+        }
+    }
 
-        Token::LParen => {
-            // tuple
-            let mut items = Vec::new();
-
-            loop {
-                items.push(parse_value(lexer)?);
-
-                match lexer.next_token()? {
-                    Token::Comma => continue,
-                    Token::RParen => break,
-                    _ => return None,
+    /// Quoted with `'` or `"`, supports `\\ \' \" \n \t \r \0` escapes.
+    /// Borrows from the input unless an escape had to be resolved.
+    fn string(&mut self, quote: char) -> Option<FuncToken<'a>> {
+        let start = self.pos;
+        let mut owned: Option<String> = None;
+        loop {
+            let pos = self.pos;
+            match self.next_char()? {
+                c if c == quote => {
+                    let value = match owned {
+                        Some(s) => Cow::Owned(s),
+                        None => Cow::Borrowed(&self.input[start..pos]),
+                    };
+                    return Some(FuncToken::String(value));
+                }
+                '\\' => {
+                    let escaped = match self.next_char()? {
+                        'n' => '\n',
+                        't' => '\t',
+                        'r' => '\r',
+                        '0' => '\0',
+                        c @ ('\\' | '\'' | '"') => c,
+                        _ => return None,
+                    };
+                    owned
+                        .get_or_insert_with(|| self.input[start..pos].to_string())
+                        .push(escaped);
+                }
+                c => {
+                    if let Some(s) = owned.as_mut() {
+                        s.push(c);
+                    }
                 }
             }
-
-            Some(Expr::Tuple(items))
         }
-
-        _ => None,
     }
-}
-
-fn parse_args<'a>(lexer: &mut Lexer<'a>) -> Option<Vec<Expr<'a>>> {
-    let mut args = Vec::new();
 
-    if let Some(Token::LParen) = lexer.next_token() {
+    /// Comma separated values up to the `close` token, which is consumed.
+    fn parse_list(&mut self, close: FuncToken<'a>) -> Option<Vec<FuncExpr<'a>>> {
+        let mut items = Vec::new();
+        if self.peek_token().as_ref() == Some(&close) {
+            self.next_token();
+            return Some(items);
+        }
         loop {
-            args.push(parse_value(lexer)?);
-
-            match lexer.next_token()? {
-                Token::Comma => continue,
-                Token::RParen => break,
+            items.push(self.parse_expr()?);
+            match self.next_token()? {
+                FuncToken::Comma => continue,
+                token if token == close => break,
                 _ => return None,
             }
         }
+        Some(items)
     }
 
-    Some(args)
-}
-fn parse_arg_list<'a>(lexer: &mut Lexer<'a>) -> Option<Vec<Expr<'a>>> {
-    let mut args = Vec::new();
+    fn parse_expr(&mut self) -> Option<FuncExpr<'a>> {
+        match self.next_token()? {
+            FuncToken::Number(n) => Some(FuncExpr::Number(n)),
+            FuncToken::Float(n) => Some(FuncExpr::Float(n)),
+            FuncToken::String(s) => Some(FuncExpr::String(s)),
+            FuncToken::Ident(name) => {
+                if matches!(self.peek_token(), Some(FuncToken::LParen)) {
+                    self.next_token();
+                    let args = self.parse_list(FuncToken::RParen)?;
+                    Some(FuncExpr::Call { name, args })
+                } else if matches!(self.peek_token(), Some(FuncToken::Eq)) {
+                    self.next_token();
+                    let value = self.parse_expr()?;
+                    Some(FuncExpr::Named(name, Box::new(value)))
+                } else if name.eq_ignore_ascii_case("true") {
+                    Some(FuncExpr::Bool(true))
+                } else if name.eq_ignore_ascii_case("false") {
+                    Some(FuncExpr::Bool(false))
+                } else {
+                    Some(FuncExpr::Ident(name))
+                }
+            }
+            FuncToken::LParen => self.parse_list(FuncToken::RParen).map(FuncExpr::Tuple),
+            FuncToken::LBracket => self.parse_list(FuncToken::RBracket).map(FuncExpr::List),
+            _ => None,
+        }
+    }
 
-    if let Some(Token::RParen) = lexer.peek_token() {
-        lexer.next_token();
-        return Some(args);
+    pub fn parse_func<'b>(input: &'b str) -> Option<Func<'b>> {
+        let mut lexer = FuncLexer::new(input);
+
+        let FuncToken::Ident(entry) = lexer.next_token()? else {
+            return None;
+        };
+        let FuncToken::DoubleColon = lexer.next_token()? else {
+            return None;
+        };
+        let FuncToken::Ident(func) = lexer.next_token()? else {
+            return None;
+        };
+
+        let args = if matches!(lexer.peek_token(), Some(FuncToken::LParen)) {
+            lexer.next_token();
+            lexer.parse_list(FuncToken::RParen)?
+        } else {
+            Vec::new()
+        };
+
+        if !lexer.at_end() {
+            return None;
+        }
+        Some(Func { entry, func, args })
     }
 
-    loop {
-        args.push(parse_value(lexer)?);
+    /// Split `a::F(1) ; delay(200) ; b::G` into its steps, `;` inside quotes
+    /// doesn't count. Steps are trimmed sub-slices of `input`.
+    pub fn split_chain(input: &str) -> Vec<&str> {
+        let mut lexer = FuncLexer::new(input);
+        let mut steps = Vec::new();
+        let mut start = 0;
+        while lexer.pos < input.len() {
+            let before = lexer.pos;
+            if let Some(FuncToken::Semicolon) = lexer.next_token() {
+                steps.push(input[start..before].trim());
+                start = lexer.pos;
+            }
+        }
+        steps.push(input[start..].trim());
+        steps
+    }
 
-        match lexer.next_token()? {
-            Token::Comma => continue,
-            Token::RParen => break,
-            _ => return None,
+    /// `delay(ms)` step of a chain
+    pub fn parse_delay(step: &str) -> Option<u64> {
+        let mut lexer = FuncLexer::new(step);
+        let FuncToken::Ident(name) = lexer.next_token()? else {
+            return None;
+        };
+        if !name.eq_ignore_ascii_case("delay") {
+            return None;
         }
+        let (Some(FuncToken::LParen), Some(FuncToken::Number(ms)), Some(FuncToken::RParen), None) = (
+            lexer.next_token(),
+            lexer.next_token(),
+            lexer.next_token(),
+            lexer.next_token(),
+        ) else {
+            return None;
+        };
+        u64::try_from(ms).ok()
     }
 
-    Some(args)
-}
-pub fn parse_func<'a>(input: &'a str) -> Option<Func<'a>> {
-    let mut lexer = Lexer::new(input);
-    let Token::Ident(namespace) = lexer.next_token()? else {
-        return None;
-    };
-    let Token::DoubleColon = lexer.next_token()? else {
-        return None;
-    };
-    let Token::Ident(action) = lexer.next_token()? else {
-        return None;
-    };
-    let args = parse_args(&mut lexer)?;
-    Some(Func {
-        entry: namespace,
-        func: action,
-        args,
-    })
+    /// `mode(name)` step of a chain, enters the `mode name { ... }` block
+    pub fn parse_mode(step: &str) -> Option<&str> {
+        let mut lexer = FuncLexer::new(step);
+        let FuncToken::Ident(name) = lexer.next_token()? else {
+            return None;
+        };
+        if !name.eq_ignore_ascii_case("mode") {
+            return None;
+        }
+        let (Some(FuncToken::LParen), Some(FuncToken::Ident(mode)), Some(FuncToken::RParen), None) = (
+            lexer.next_token(),
+            lexer.next_token(),
+            lexer.next_token(),
+            lexer.next_token(),
+        ) else {
+            return None;
+        };
+        Some(mode)
+    }
 }
 
 #[cfg(test)]
 mod test_lexer {
-    use crate::lexer::parse_func;
+    use std::borrow::Cow;
+
+    use crate::lexer::{Func, FuncExpr, FuncLexer};
+
+    fn args(input: &str) -> Vec<FuncExpr<'_>> {
+        FuncLexer::parse_func(input)
+            .unwrap_or_else(|| panic!("failed to parse {}", input))
+            .args
+    }
 
     #[test]
     fn lexer() {
@@ -216,27 +638,181 @@ mod test_lexer {
             "workspace::CALC(12,14)",
             "workspace::RESIZE(WINDOW(12,14))",
             "window::TRANSFORM((300,300),(0,0))",
-            "app::TSOCKEE(TODO)",
-            "app::PHOTOSHOP",
-            "app::TSOCKEE(TSOOGLE SOMETHING)",
             "app::CYCLE",
         ];
 
         for s in inputs {
-            if let Some(cmd) = parse_func(s) {
-                println!("{:#?}", cmd);
-            }
+            assert!(FuncLexer::parse_func(s).is_some(), "{}", s);
         }
+        assert_eq!(
+            FuncLexer::parse_func("app::PHOTOSHOP"),
+            Some(Func {
+                entry: "app",
+                func: "PHOTOSHOP",
+                args: vec![]
+            })
+        );
+        assert!(FuncLexer::parse_func("app::TSOCKEE(TSOOGLE SOMETHING)").is_none());
+        assert!(FuncLexer::parse_func("app::PAGE(1) junk").is_none());
     }
+
     #[test]
-    fn lexer_adv() {
-        let input = [
-            "app::SCRIPT('main')",
-            "app::SCRIPT('some/main.js')",
-            "app::SCRIPT('path/to/another jsfile.js',(10,20,(10,10)))",
-        ];
-        for s in input {
-            println!("{:#?}", parse_func(s))
+    fn lexer_args() {
+        assert_eq!(
+            args("workspace::RESIZEACTIVEWINDOW('INC', 'WIDTH', -40)"),
+            vec![
+                FuncExpr::String(Cow::Borrowed("INC")),
+                FuncExpr::String(Cow::Borrowed("WIDTH")),
+                FuncExpr::Number(-40),
+            ]
+        );
+        assert_eq!(
+            args("app::SET(1.5, -0.25, true, FALSE, PHOTOSHOP)"),
+            vec![
+                FuncExpr::Float(1.5),
+                FuncExpr::Float(-0.25),
+                FuncExpr::Bool(true),
+                FuncExpr::Bool(false),
+                FuncExpr::Ident("PHOTOSHOP"),
+            ]
+        );
+        assert_eq!(
+            args("app::SCRIPT('path/to/another jsfile.js',(10,20,(10,10)), [1, 'a'], [])"),
+            vec![
+                FuncExpr::String(Cow::Borrowed("path/to/another jsfile.js")),
+                FuncExpr::Tuple(vec![
+                    FuncExpr::Number(10),
+                    FuncExpr::Number(20),
+                    FuncExpr::Tuple(vec![FuncExpr::Number(10), FuncExpr::Number(10)]),
+                ]),
+                FuncExpr::List(vec![
+                    FuncExpr::Number(1),
+                    FuncExpr::String(Cow::Borrowed("a"))
+                ]),
+                FuncExpr::List(vec![]),
+            ]
+        );
+        assert_eq!(
+            args("workspace::RESIZE(WINDOW(12,14))"),
+            vec![FuncExpr::Call {
+                name: "WINDOW",
+                args: vec![FuncExpr::Number(12), FuncExpr::Number(14)]
+            }]
+        );
+    }
+
+    #[test]
+    fn lexer_named() {
+        let parsed = args("workspace::MOVEACTIVEWINDOW('LEFT', by=80, duration = 200)");
+        assert_eq!(
+            parsed,
+            vec![
+                FuncExpr::String(Cow::Borrowed("LEFT")),
+                FuncExpr::Named("by", Box::new(FuncExpr::Number(80))),
+                FuncExpr::Named("duration", Box::new(FuncExpr::Number(200))),
+            ]
+        );
+        let slots = FuncExpr::bind(&parsed, &["dir", "by", "duration", "easing"]).unwrap();
+        assert_eq!(
+            slots,
+            vec![
+                Some(&FuncExpr::String(Cow::Borrowed("LEFT"))),
+                Some(&FuncExpr::Number(80)),
+                Some(&FuncExpr::Number(200)),
+                None,
+            ]
+        );
+        let parsed = args("app::F(by=1, 'LEFT')");
+        assert_eq!(
+            FuncExpr::bind(&parsed, &["by", "dir"]),
+            Err("argument `by` given twice".to_string())
+        );
+        let parsed = args("app::F(speed=1)");
+        assert_eq!(
+            FuncExpr::bind(&parsed, &["by"]),
+            Err("unknown argument `speed`".to_string())
+        );
+        let parsed = args("app::F(1, 2)");
+        assert_eq!(
+            FuncExpr::bind(&parsed, &[""]),
+            Err("expected at most 1 argument(s), found 2".to_string())
+        );
+    }
+
+    #[test]
+    fn lexer_strings() {
+        assert_eq!(
+            args(r#"app::SCRIPT("it's.js")"#),
+            vec![FuncExpr::String(Cow::Borrowed("it's.js"))]
+        );
+        assert_eq!(
+            args(r#"app::SCRIPT('it\'s \"a\"\\b\n')"#),
+            vec![FuncExpr::String(Cow::Owned("it's \"a\"\\b\n".into()))]
+        );
+        assert!(FuncLexer::parse_func(r#"app::SCRIPT('bad\q')"#).is_none());
+        assert!(FuncLexer::parse_func("app::SCRIPT('open)").is_none());
+    }
+
+    #[test]
+    fn parse_as() {
+        let parsed = args("app::SET('a', 2, 2.5, true)");
+        assert_eq!(parsed[0].parse_as::<String>(), Ok("a".to_string()));
+        assert_eq!(parsed[1].parse_as::<i32>(), Ok(2));
+        assert_eq!(parsed[1].parse_as::<f64>(), Ok(2.0));
+        assert_eq!(parsed[2].parse_as::<f32>(), Ok(2.5));
+        assert_eq!(parsed[3].parse_as::<bool>(), Ok(true));
+        assert_eq!(
+            parsed[0].parse_as::<i32>(),
+            Err("expected a number, found a string".to_string())
+        );
+        assert!(FuncExpr::Number(-1).parse_as::<u32>().is_err());
+
+        let parsed = args("app::SET(none, 'a', [1, 2], ('w', 40), [1, 'x'], (1, 2, 3))");
+        assert_eq!(parsed[0].parse_as::<Option<i32>>(), Ok(None));
+        assert_eq!(parsed[1].parse_as::<Option<String>>(), Ok(Some("a".into())));
+        assert_eq!(parsed[2].parse_as::<Vec<u32>>(), Ok(vec![1, 2]));
+        assert_eq!(
+            parsed[3].parse_as::<(String, i32)>(),
+            Ok(("w".to_string(), 40))
+        );
+        assert_eq!(
+            parsed[4].parse_as::<Vec<i32>>(),
+            Err("item 2: expected a number, found a string".to_string())
+        );
+        assert_eq!(
+            parsed[5].parse_as::<(i32, i32)>(),
+            Err("expected a tuple of 2, found 3 item(s)".to_string())
+        );
+        assert_eq!(
+            FuncExpr::of(&(Some(vec![1, 2]), None::<bool>)).to_string(),
+            "([1, 2], none)"
+        );
+    }
+
+    #[test]
+    fn builtin_steps() {
+        assert_eq!(FuncLexer::parse_delay("delay(200)"), Some(200));
+        assert_eq!(FuncLexer::parse_delay("delay(-1)"), None);
+        assert_eq!(FuncLexer::parse_mode("mode(resize)"), Some("resize"));
+        assert_eq!(FuncLexer::parse_mode("MODE( resize )"), Some("resize"));
+        assert_eq!(FuncLexer::parse_mode("mode('resize')"), None);
+        assert_eq!(FuncLexer::parse_mode("app::MODE(resize)"), None);
+    }
+
+    #[test]
+    fn display() {
+        for input in [
+            "app::PAGE(1)",
+            "app::RELOADCONFIG",
+            "app::SCRIPT('it\\'s \\\\ \\n', -2, 0.5, 3.0, true)",
+            "ws::MOVE(dir=LEFT, by=[1, 2], at=(3, 4), ease=out(2))",
+        ] {
+            let func = FuncLexer::parse_func(input).unwrap();
+            assert_eq!(func.to_string(), input);
+            assert_eq!(FuncLexer::parse_func(&func.to_string()), Some(func));
         }
+        assert_eq!(FuncExpr::of("a'b").to_string(), "'a\\'b'");
+        assert_eq!(FuncExpr::of(&80u64), FuncExpr::Number(80));
+        assert_eq!(FuncExpr::of(&1.5f32), FuncExpr::Float(1.5));
     }
 }
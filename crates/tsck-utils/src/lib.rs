@@ -1,8 +1,26 @@
+mod chain;
 mod config;
 pub mod directory;
+mod format;
+mod layers;
 mod lexer;
 mod macros;
-pub use config::ConfigStore;
-pub use directory::Dir;
-pub use lexer::{Expr, Func, parse_func};
+mod migrate;
+mod schema;
+mod suggest;
+mod validate;
+mod watch;
+pub use chain::{ChainStep, run_chain};
+pub use config::{ConfigStore, DEFAULT_BACKUPS, write_atomic};
+pub use directory::{Dir, DirKind, TSCK_HOME};
+pub use format::{
+    ConfigFormat, FORMATS, Json, Json5, Toml, convert, format_for, locate, read_config,
+};
+pub use layers::{Layered, Origin};
+pub use lexer::{FromFuncExpr, Func, FuncExpr, FuncLexer, ToFuncExpr};
+pub use migrate::{Migration, SCHEMA_VERSION, migrate, rename_key, schema_version, stamp};
 pub use paste;
+pub use schema::{ArgSchema, FuncSchema, ScopeSchema};
+pub use suggest::{did_you_mean, edit_distance};
+pub use validate::{Issue, Report, Severity, find_executable};
+pub use watch::{ConfigWatcher, DEFAULT_WATCH_DEBOUNCE, DEFAULT_WATCH_INTERVAL, WatchHandle};
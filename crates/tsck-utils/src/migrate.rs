@@ -0,0 +1,145 @@
+use anyhow::{Context, Result, bail};
+use serde_json::{Map, Value};
+
+/// Key holding the schema version of a document, a missing one is version 0
+pub const SCHEMA_VERSION: &str = "schema_version";
+
+/// One upgrade step of a config document, from version `to - 1` to `to`. `C`
+/// carries what a step moves out of the document, like bindings into kee.kee.
+#[derive(Debug)]
+pub struct Migration<C = ()> {
+    pub to: u32,
+    /// Logged once the step ran
+    pub description: &'static str,
+    pub apply: fn(&mut Map<String, Value>, &mut C) -> Result<()>,
+}
+
+impl<C> Migration<C> {
+    /// Version of a document once every migration ran
+    pub fn latest(migrations: &[Migration<C>]) -> u32 {
+        migrations.iter().map(|m| m.to).max().unwrap_or(0)
+    }
+}
+
+pub fn schema_version(doc: &Value) -> u32 {
+    doc.get(SCHEMA_VERSION)
+        .and_then(Value::as_u64)
+        .map_or(0, |v| v as u32)
+}
+
+/// Set the version of `doc` to the latest, for a document that is written
+/// from scratch and has nothing to migrate
+pub fn stamp<C>(doc: &mut Value, migrations: &[Migration<C>]) {
+    if let Some(object) = doc.as_object_mut() {
+        object.insert(SCHEMA_VERSION.into(), Migration::latest(migrations).into());
+    }
+}
+
+/// Upgrade `doc` step by step up to the latest version and return the steps
+/// that ran, in order. A document of a newer version than this build knows is
+/// refused rather than loaded with its unknown fields dropped.
+pub fn migrate<'a, C>(
+    doc: &mut Value,
+    migrations: &'a [Migration<C>],
+    ctx: &mut C,
+) -> Result<Vec<&'a Migration<C>>> {
+    let latest = Migration::latest(migrations);
+    let version = schema_version(doc);
+    if version > latest {
+        bail!(
+            "schema version {} is newer than the {} this build knows",
+            version,
+            latest
+        );
+    }
+    let Some(object) = doc.as_object_mut() else {
+        bail!("expected an object at the top level");
+    };
+    let mut steps: Vec<&Migration<C>> = migrations.iter().filter(|m| m.to > version).collect();
+    steps.sort_by_key(|m| m.to);
+    for step in &steps {
+        (step.apply)(object, ctx)
+            .with_context(|| format!("migration to v{} failed: {}", step.to, step.description))?;
+        object.insert(SCHEMA_VERSION.into(), step.to.into());
+    }
+    Ok(steps)
+}
+
+/// Move `from` to `to` unless `to` is already set
+pub fn rename_key(object: &mut Map<String, Value>, from: &str, to: &str) {
+    if let Some(value) = object.remove(from)
+        && !object.contains_key(to)
+    {
+        object.insert(to.into(), value);
+    }
+}
+
+#[cfg(test)]
+mod migrations {
+    use super::*;
+    use serde_json::json;
+
+    const STEPS: &[Migration<Vec<String>>] = &[
+        Migration {
+            to: 2,
+            description: "move `names` out",
+            apply: |object, moved| {
+                if let Some(Value::Array(names)) = object.remove("names") {
+                    moved.extend(names.iter().filter_map(|n| n.as_str().map(String::from)));
+                }
+                Ok(())
+            },
+        },
+        Migration {
+            to: 1,
+            description: "rename `nmae` to `name`",
+            apply: |object, _| {
+                rename_key(object, "nmae", "name");
+                Ok(())
+            },
+        },
+        Migration {
+            to: 3,
+            description: "port as a number",
+            apply: |object, _| {
+                if let Some(Value::String(port)) = object.get("port") {
+                    let port: u16 = port.parse()?;
+                    object.insert("port".into(), port.into());
+                }
+                Ok(())
+            },
+        },
+    ];
+
+    #[test]
+    fn test_migrate() -> Result<()> {
+        let mut doc = json!({ "nmae": "tsck", "names": ["a", "b"], "port": "8080" });
+        let mut moved = Vec::new();
+        let ran = migrate(&mut doc, STEPS, &mut moved)?;
+        assert_eq!(ran.iter().map(|m| m.to).collect::<Vec<_>>(), [1, 2, 3]);
+        assert_eq!(
+            doc,
+            json!({ "name": "tsck", "port": 8080, "schema_version": 3 })
+        );
+        assert_eq!(moved, ["a", "b"]);
+
+        // already up to date
+        assert!(migrate(&mut doc, STEPS, &mut moved)?.is_empty());
+        // only the missing steps run
+        let mut doc = json!({ "name": "tsck", "port": "90", "schema_version": 2 });
+        assert_eq!(migrate(&mut doc, STEPS, &mut moved)?.len(), 1);
+        assert_eq!(doc["port"], 90);
+        Ok(())
+    }
+
+    #[test]
+    fn test_refused() {
+        let mut moved = Vec::new();
+        let mut newer = json!({ "schema_version": 4 });
+        assert!(migrate(&mut newer, STEPS, &mut moved).is_err());
+        let mut bad_port = json!({ "port": "http" });
+        let err = migrate(&mut bad_port, STEPS, &mut moved).unwrap_err();
+        assert_eq!(err.to_string(), "migration to v3 failed: port as a number");
+        assert!(migrate(&mut json!([]), STEPS, &mut moved).is_err());
+    }
+}
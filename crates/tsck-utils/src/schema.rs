@@ -0,0 +1,99 @@
+use serde::Serialize;
+
+use crate::suggest::did_you_mean;
+
+/// Argument of a function, generated by the `FuncParser` derive
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+pub struct ArgSchema {
+    /// Name accepted as `name=value`, `None` for positional only arguments
+    pub name: Option<&'static str>,
+    /// Rust type of the field, `i32`, `Option<String>`, `(i32,i32)`
+    pub ty: &'static str,
+    /// Value used when left out, as written in kee.kee
+    pub default: Option<&'static str>,
+    /// Can be left out, either defaulted or an `Option`
+    pub optional: bool,
+    pub doc: Option<&'static str>,
+}
+
+/// `FUNC(args)`, generated by the `FuncParser` derive
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+pub struct FuncSchema {
+    /// Printed name, `APPTOFRONT`
+    pub name: &'static str,
+    /// Other names it parses from, `front`
+    pub aliases: &'static [&'static str],
+    pub doc: Option<&'static str>,
+    pub args: &'static [ArgSchema],
+}
+
+/// `scope::`, generated by the `ScopeParser` derive
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+pub struct ScopeSchema {
+    pub name: &'static str,
+    pub aliases: &'static [&'static str],
+    pub doc: Option<&'static str>,
+    pub funcs: &'static [FuncSchema],
+}
+
+impl FuncSchema {
+    /// `MOVE(dir: String, by: i32 = 80)`
+    pub fn signature(&self) -> String {
+        let args: Vec<String> = self
+            .args
+            .iter()
+            .map(|arg| {
+                let mut out = match arg.name {
+                    Some(name) => format!("{}: {}", name, arg.ty),
+                    None => arg.ty.to_string(),
+                };
+                if let Some(default) = arg.default {
+                    out.push_str(&format!(" = {}", default));
+                }
+                out
+            })
+            .collect();
+        match args.is_empty() {
+            true => self.name.to_string(),
+            false => format!("{}({})", self.name, args.join(", ")),
+        }
+    }
+
+    pub fn matches(&self, func: &str) -> bool {
+        std::iter::once(&self.name)
+            .chain(self.aliases)
+            .any(|name| name.eq_ignore_ascii_case(func))
+    }
+
+    /// Name or alias of `funcs` closest to the unknown `func`
+    pub fn suggest(funcs: &[FuncSchema], func: &str) -> Option<&'static str> {
+        let names = funcs
+            .iter()
+            .flat_map(|f| std::iter::once(f.name).chain(f.aliases.iter().copied()));
+        did_you_mean(func, names)
+    }
+}
+
+impl ScopeSchema {
+    pub fn matches(&self, scope: &str) -> bool {
+        std::iter::once(&self.name)
+            .chain(self.aliases)
+            .any(|name| name.eq_ignore_ascii_case(scope))
+    }
+
+    /// Name or alias of `scopes` closest to the unknown `scope`
+    pub fn suggest(scopes: &[ScopeSchema], scope: &str) -> Option<&'static str> {
+        let names = scopes
+            .iter()
+            .flat_map(|s| std::iter::once(s.name).chain(s.aliases.iter().copied()));
+        did_you_mean(scope, names)
+    }
+
+    pub fn func(&self, func: &str) -> Option<&'static FuncSchema> {
+        self.funcs.iter().find(|f| f.matches(func))
+    }
+
+    pub fn to_json(scopes: &[ScopeSchema]) -> String {
+        serde_json::to_string_pretty(scopes).unwrap_or_default()
+    }
+}
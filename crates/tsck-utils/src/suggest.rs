@@ -0,0 +1,64 @@
+/// Edits to turn `a` into `b`, ignoring case. Swapping two neighbouring chars
+/// counts as one edit, `CYLCE` is one away from `CYCLE`.
+pub fn edit_distance(a: &str, b: &str) -> usize {
+    let a: Vec<char> = a.chars().flat_map(char::to_lowercase).collect();
+    let b: Vec<char> = b.chars().flat_map(char::to_lowercase).collect();
+    // rows i - 2, i - 1 and i of the distance matrix
+    let mut before: Vec<usize> = vec![0; b.len() + 1];
+    let mut previous: Vec<usize> = (0..=b.len()).collect();
+    for i in 1..=a.len() {
+        let mut current = vec![i; b.len() + 1];
+        for j in 1..=b.len() {
+            let cost = usize::from(a[i - 1] != b[j - 1]);
+            current[j] = (previous[j] + 1)
+                .min(current[j - 1] + 1)
+                .min(previous[j - 1] + cost);
+            if i > 1 && j > 1 && a[i - 1] == b[j - 2] && a[i - 2] == b[j - 1] {
+                current[j] = current[j].min(before[j - 2] + 1);
+            }
+        }
+        before = std::mem::replace(&mut previous, current);
+    }
+    previous[b.len()]
+}
+
+/// Closest of `candidates` to `name`, `None` when nothing is close enough to
+/// be a typo: a third of the name may differ, at least one char.
+pub fn did_you_mean<'a>(
+    name: &str,
+    candidates: impl IntoIterator<Item = &'a str>,
+) -> Option<&'a str> {
+    let limit = (name.chars().count() / 3).max(1);
+    candidates
+        .into_iter()
+        .map(|candidate| (edit_distance(name, candidate), candidate))
+        .filter(|(distance, _)| *distance <= limit)
+        .min_by_key(|(distance, _)| *distance)
+        .map(|(_, candidate)| candidate)
+}
+
+#[cfg(test)]
+mod typos {
+    use super::*;
+
+    #[test]
+    fn test_edit_distance() {
+        assert_eq!(edit_distance("CYCLEAPPS", "cycleapps"), 0);
+        assert_eq!(edit_distance("CYCLEAP", "CYCLEAPPS"), 2);
+        assert_eq!(edit_distance("CYLCE", "CYCLE"), 1);
+        assert_eq!(edit_distance("", "app"), 3);
+        assert_eq!(edit_distance("page", "script"), 6);
+    }
+
+    #[test]
+    fn test_did_you_mean() {
+        let names = ["SCRIPT", "CYCLEAPPS", "CYCLEPAGES", "PAGE"];
+        assert_eq!(did_you_mean("CYCLEAP", names), Some("CYCLEAPPS"));
+        assert_eq!(did_you_mean("cyclepage", names), Some("CYCLEPAGES"));
+        assert_eq!(did_you_mean("SCRIPTS", names), Some("SCRIPT"));
+        assert_eq!(did_you_mean("pgae", names), Some("PAGE"));
+        assert_eq!(did_you_mean("reload", names), None);
+        assert_eq!(did_you_mean("ap", ["app", "workspace"]), Some("app"));
+        assert_eq!(did_you_mean("x", ["app", "workspace"]), None);
+    }
+}
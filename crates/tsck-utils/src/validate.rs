@@ -0,0 +1,225 @@
+use serde::Serialize;
+use std::{
+    env,
+    ffi::OsString,
+    fmt,
+    path::{Path, PathBuf},
+};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Severity {
+    /// The config can't be used as is
+    Error,
+    /// Works, but likely not as meant
+    Warning,
+}
+
+impl fmt::Display for Severity {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Severity::Error => write!(f, "error"),
+            Severity::Warning => write!(f, "warning"),
+        }
+    }
+}
+
+/// One finding of a validation pass
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct Issue {
+    pub severity: Severity,
+    /// Where in the document, `command_config.commands[0].executable`
+    pub path: String,
+    pub message: String,
+}
+
+impl fmt::Display for Issue {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.path.is_empty() {
+            // about the whole document
+            true => write!(f, "{}: {}", self.severity, self.message),
+            false => write!(f, "{}: {}: {}", self.severity, self.path, self.message),
+        }
+    }
+}
+
+/// Errors and warnings of a semantic check, what parses but can't work:
+/// clashing ports, missing paths, programs that aren't installed
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct Report {
+    issues: Vec<Issue>,
+}
+
+impl Report {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn error(&mut self, path: impl Into<String>, message: impl Into<String>) {
+        self.push(Severity::Error, path.into(), message.into());
+    }
+
+    pub fn warn(&mut self, path: impl Into<String>, message: impl Into<String>) {
+        self.push(Severity::Warning, path.into(), message.into());
+    }
+
+    fn push(&mut self, severity: Severity, path: String, message: String) {
+        self.issues.push(Issue {
+            severity,
+            path,
+            message,
+        });
+    }
+
+    /// Append the issues of `other`, with `prefix` put in front of their paths
+    pub fn nest(&mut self, prefix: &str, other: Report) {
+        self.issues
+            .extend(other.issues.into_iter().map(|issue| Issue {
+                path: match prefix.is_empty() {
+                    true => issue.path,
+                    false => format!("{}.{}", prefix, issue.path),
+                },
+                ..issue
+            }));
+    }
+
+    pub fn issues(&self) -> &[Issue] {
+        &self.issues
+    }
+
+    pub fn errors(&self) -> impl Iterator<Item = &Issue> {
+        self.issues.iter().filter(|i| i.severity == Severity::Error)
+    }
+
+    pub fn warnings(&self) -> impl Iterator<Item = &Issue> {
+        self.issues
+            .iter()
+            .filter(|i| i.severity == Severity::Warning)
+    }
+
+    pub fn has_errors(&self) -> bool {
+        self.errors().next().is_some()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.issues.is_empty()
+    }
+}
+
+/// One issue per line, errors first
+impl fmt::Display for Report {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for issue in self.errors().chain(self.warnings()) {
+            writeln!(f, "{}", issue)?;
+        }
+        Ok(())
+    }
+}
+
+/// Where `program` would be run from: as given when it has a directory part,
+/// else the first match on `PATH`. `PATHEXT` extensions are tried too, so
+/// `pwsh` finds `pwsh.exe` on Windows.
+pub fn find_executable(program: &str) -> Option<PathBuf> {
+    resolve_executable(program, env::var_os("PATH"), env::var("PATHEXT").ok())
+}
+
+fn resolve_executable(
+    program: &str,
+    path: Option<OsString>,
+    pathext: Option<String>,
+) -> Option<PathBuf> {
+    let extensions: Vec<String> = pathext
+        .iter()
+        .flat_map(|e| e.split(';'))
+        .filter(|e| !e.is_empty())
+        .map(str::to_lowercase)
+        .collect();
+    let candidates = |base: PathBuf| {
+        let with_extensions = extensions.iter().map({
+            let base = base.clone();
+            move |extension| {
+                let mut name = base.clone().into_os_string();
+                name.push(extension);
+                PathBuf::from(name)
+            }
+        });
+        std::iter::once(base).chain(with_extensions)
+    };
+    let program = Path::new(program);
+    if program.as_os_str().is_empty() {
+        return None;
+    }
+    if program.components().count() > 1 {
+        return candidates(program.to_path_buf()).find(|p| p.is_file());
+    }
+    path.iter()
+        .flat_map(env::split_paths)
+        .flat_map(|dir| candidates(dir.join(program)))
+        .find(|p| p.is_file())
+}
+
+#[cfg(test)]
+mod validation {
+    use super::*;
+    use std::fs;
+
+    #[test]
+    fn test_report() {
+        let mut commands = Report::new();
+        commands.warn("commands[1].executable", "not set");
+        commands.error("commands[0].executable", "`comfy` not found");
+        let mut report = Report::new();
+        report.error("http_server_port", "same as websocket_server_port");
+        report.nest("command_config", commands);
+        report.error("", "doesn't load");
+
+        assert!(report.has_errors());
+        assert_eq!(report.errors().count(), 3);
+        assert_eq!(
+            report.to_string(),
+            "error: http_server_port: same as websocket_server_port\n\
+             error: command_config.commands[0].executable: `comfy` not found\n\
+             error: doesn't load\n\
+             warning: command_config.commands[1].executable: not set\n"
+        );
+        assert_eq!(
+            serde_json::to_value(&report.issues()[0]).unwrap(),
+            serde_json::json!({
+                "severity": "error",
+                "path": "http_server_port",
+                "message": "same as websocket_server_port",
+            })
+        );
+        assert!(Report::new().is_empty());
+    }
+
+    #[test]
+    fn test_resolve_executable() -> anyhow::Result<()> {
+        let dir = env::temp_dir().join(format!("tsck-validate-{}", std::process::id()));
+        let bin = dir.join("bin");
+        fs::create_dir_all(&bin)?;
+        fs::write(bin.join("comfy"), "")?;
+        fs::write(bin.join("pwsh.exe"), "")?;
+        let path = env::join_paths([dir.join("missing"), bin.clone()])?;
+
+        let resolve = |program: &str| {
+            resolve_executable(program, Some(path.clone()), Some(".COM;.EXE;".into()))
+        };
+        assert_eq!(resolve("comfy"), Some(bin.join("comfy")));
+        assert_eq!(resolve("pwsh"), Some(bin.join("pwsh.exe")));
+        assert_eq!(resolve("python"), None);
+        assert_eq!(resolve(""), None);
+        let direct = bin.join("comfy");
+        assert_eq!(
+            resolve_executable(direct.to_str().unwrap(), None, None),
+            Some(direct)
+        );
+        assert_eq!(
+            resolve_executable(bin.join("pwsh").to_str().unwrap(), None, None),
+            None
+        );
+
+        fs::remove_dir_all(&dir)?;
+        Ok(())
+    }
+}
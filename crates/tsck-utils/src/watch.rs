@@ -0,0 +1,236 @@
+use anyhow::Result;
+use std::{
+    fs,
+    path::{Path, PathBuf},
+    sync::{
+        Arc,
+        atomic::{AtomicBool, Ordering},
+    },
+    thread::JoinHandle,
+    time::{Duration, Instant, SystemTime},
+};
+
+/// How often the file is looked at
+pub const DEFAULT_WATCH_INTERVAL: Duration = Duration::from_millis(250);
+/// Quiet time after the last write before the file is read, editors save in
+/// several writes
+pub const DEFAULT_WATCH_DEBOUNCE: Duration = Duration::from_millis(300);
+
+type Parse<T> = Box<dyn Fn(&str) -> Result<T> + Send>;
+type OnChange<T> = Box<dyn Fn(&T, &T) + Send>;
+type OnReject = Box<dyn Fn(&Path, &anyhow::Error) + Send>;
+
+/// Modification time and size, `None` while the file doesn't exist
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct Stamp(Option<(SystemTime, u64)>);
+
+impl Stamp {
+    fn of(path: &Path) -> Self {
+        Self(
+            fs::metadata(path)
+                .ok()
+                .and_then(|meta| Some((meta.modified().ok()?, meta.len()))),
+        )
+    }
+}
+
+/// Tells when a file settled after a change, time is passed in by the caller
+#[derive(Debug)]
+struct Debouncer {
+    seen: Stamp,
+    /// Last time the stamp moved, `None` once it has been reported
+    changed_at: Option<Instant>,
+    debounce: Duration,
+}
+
+impl Debouncer {
+    fn new(seen: Stamp, debounce: Duration) -> Self {
+        Self {
+            seen,
+            changed_at: None,
+            debounce,
+        }
+    }
+
+    /// True once, when the file has been quiet for the debounce after a change
+    fn feed(&mut self, stamp: Stamp, now: Instant) -> bool {
+        if stamp != self.seen {
+            self.seen = stamp;
+            self.changed_at = Some(now);
+            return false;
+        }
+        match self.changed_at {
+            Some(changed_at) if now.duration_since(changed_at) >= self.debounce => {
+                self.changed_at = None;
+                true
+            }
+            _ => false,
+        }
+    }
+}
+
+/// Opt-in polling watcher of a config file. Every settled change is read and
+/// parsed; a valid one replaces the last good value and is handed to the
+/// subscribers with the value it replaces, an invalid one is rejected and the
+/// last good value kept.
+pub struct ConfigWatcher<T> {
+    path: PathBuf,
+    parse: Parse<T>,
+    subscribers: Vec<OnChange<T>>,
+    on_reject: Option<OnReject>,
+    interval: Duration,
+    debounce: Duration,
+}
+
+impl<T: Send + 'static> ConfigWatcher<T> {
+    /// `parse` doubles as the validation, an `Err` rejects the edit
+    pub fn new(
+        path: impl Into<PathBuf>,
+        parse: impl Fn(&str) -> Result<T> + Send + 'static,
+    ) -> Self {
+        Self {
+            path: path.into(),
+            parse: Box::new(parse),
+            subscribers: Vec::new(),
+            on_reject: None,
+            interval: DEFAULT_WATCH_INTERVAL,
+            debounce: DEFAULT_WATCH_DEBOUNCE,
+        }
+    }
+
+    pub fn interval(mut self, interval: Duration) -> Self {
+        self.interval = interval;
+        self
+    }
+
+    pub fn debounce(mut self, debounce: Duration) -> Self {
+        self.debounce = debounce;
+        self
+    }
+
+    /// Called with the old and the new value after every accepted edit
+    pub fn on_change(mut self, f: impl Fn(&T, &T) + Send + 'static) -> Self {
+        self.subscribers.push(Box::new(f));
+        self
+    }
+
+    /// Called with the reason when an edit doesn't parse
+    pub fn on_reject(mut self, f: impl Fn(&Path, &anyhow::Error) + Send + 'static) -> Self {
+        self.on_reject = Some(Box::new(f));
+        self
+    }
+
+    /// Read the file and hand it out if it parses, `current` is kept otherwise
+    fn reload(&self, current: &mut T) {
+        let parsed = fs::read_to_string(&self.path)
+            .map_err(anyhow::Error::from)
+            .and_then(|content| (self.parse)(&content));
+        match parsed {
+            Ok(new) => {
+                let old = std::mem::replace(current, new);
+                for subscriber in &self.subscribers {
+                    subscriber(&old, current);
+                }
+            }
+            Err(err) => {
+                if let Some(on_reject) = &self.on_reject {
+                    on_reject(&self.path, &err);
+                }
+            }
+        }
+    }
+
+    /// Watch from a background thread, starting from the last good `current`.
+    /// The thread stops when the handle is dropped.
+    pub fn spawn(self, current: T) -> Result<WatchHandle> {
+        let stop = Arc::new(AtomicBool::new(false));
+        let stopped = stop.clone();
+        let mut debouncer = Debouncer::new(Stamp::of(&self.path), self.debounce);
+        let thread = std::thread::Builder::new()
+            .name(format!("watch {}", self.path.display()))
+            .spawn(move || {
+                let mut current = current;
+                while !stopped.load(Ordering::Relaxed) {
+                    std::thread::sleep(self.interval);
+                    if debouncer.feed(Stamp::of(&self.path), Instant::now()) {
+                        self.reload(&mut current);
+                    }
+                }
+            })?;
+        Ok(WatchHandle {
+            stop,
+            thread: Some(thread),
+        })
+    }
+}
+
+/// Keeps a `ConfigWatcher` running, dropping it stops the watch
+pub struct WatchHandle {
+    stop: Arc<AtomicBool>,
+    thread: Option<JoinHandle<()>>,
+}
+
+impl Drop for WatchHandle {
+    fn drop(&mut self) {
+        self.stop.store(true, Ordering::Relaxed);
+        if let Some(thread) = self.thread.take() {
+            _ = thread.join();
+        }
+    }
+}
+
+#[cfg(test)]
+mod config_watcher {
+    use super::*;
+    use std::sync::mpsc;
+
+    #[test]
+    fn test_debouncer() {
+        let now = Instant::now();
+        let ms = Duration::from_millis;
+        let a = Stamp(Some((SystemTime::UNIX_EPOCH, 1)));
+        let b = Stamp(Some((SystemTime::UNIX_EPOCH, 2)));
+        let mut debouncer = Debouncer::new(a, ms(300));
+        assert!(!debouncer.feed(a, now));
+        assert!(!debouncer.feed(b, now));
+        // every write pushes the read back
+        assert!(!debouncer.feed(a, now + ms(200)));
+        assert!(!debouncer.feed(a, now + ms(400)));
+        assert!(debouncer.feed(a, now + ms(500)));
+        assert!(!debouncer.feed(a, now + ms(900)));
+        // deleted counts as a change too
+        assert!(!debouncer.feed(Stamp(None), now + ms(1000)));
+        assert!(debouncer.feed(Stamp(None), now + ms(1300)));
+    }
+
+    #[test]
+    fn test_watch() -> Result<()> {
+        let dir = std::env::temp_dir().join(format!("tsck-watch-{}", std::process::id()));
+        fs::create_dir_all(&dir)?;
+        let path = dir.join("port.json");
+        fs::write(&path, "1")?;
+
+        let (changes, changed) = mpsc::channel();
+        let (rejects, rejected) = mpsc::channel();
+        let watcher = ConfigWatcher::new(&path, |s| Ok(serde_json::from_str::<u16>(s)?))
+            .interval(Duration::from_millis(10))
+            .debounce(Duration::from_millis(30))
+            .on_change(move |old, new| _ = changes.send((*old, *new)))
+            .on_reject(move |_, err| _ = rejects.send(err.to_string()));
+        let handle = watcher.spawn(1)?;
+        let wait = Duration::from_secs(5);
+
+        // mtime granularity can hide a quick second write, keep the sizes apart
+        fs::write(&path, "8080")?;
+        assert_eq!(changed.recv_timeout(wait)?, (1, 8080));
+        fs::write(&path, "{ nope")?;
+        assert!(rejected.recv_timeout(wait).is_ok());
+        fs::write(&path, "90")?;
+        // the rejected edit never replaced the last good value
+        assert_eq!(changed.recv_timeout(wait)?, (8080, 90));
+
+        drop(handle);
+        fs::remove_dir_all(&dir)?;
+        Ok(())
+    }
+}
@@ -1,6 +1,8 @@
+use crate::app_config::config_issues;
 use crate::event::{
     EventPayload, UserEvent, WinLevel, WindowInfoExt, WsMessagePayload, WsPayloadContent,
 };
+use crate::hotkee::{kee_cheat_sheet, kee_conflicts, kee_schema, readable_hotkees};
 use crate::ipc::{IpcHelper, IpcRequest, IpcResponse};
 use crate::photoshop::customscripts::CustomScripts;
 use crate::protocol::setup_custom_protocol;
@@ -65,7 +67,7 @@ macro_rules! to_frontend {
 type UE = UserEvent;
 impl TsckApp {
     pub fn new(channel_bus: Arc<ChannelBus>) -> Self {
-        let config = ConfigParser::parse(include_str!("../tsck.json"));
+        let config = ConfigParser::load();
         Self::init(&channel_bus, &config);
         Self {
             windows: HashMap::new(),
@@ -75,7 +77,7 @@ impl TsckApp {
         }
     }
     pub fn reload_config(&mut self) {
-        let config = ConfigParser::parse(include_str!("../tsck.json"));
+        let config = ConfigParser::load();
         self.plugin_config = Arc::new(config.plugins);
     }
     fn init(sender: &Arc<ChannelBus>, config: &ConfigParser) {
@@ -94,7 +96,7 @@ impl TsckApp {
                 UE::ReloadConfig => {
                     self.reload_config();
                 }
-                UE::ActivateWorkSpace(_) => {
+                UE::ActivateWorkSpace(_) | UE::KeeModeChange(_) => {
                     get_window_by_label!(self, "workspace", |ws| {
                         if let Ok(payload) =
                             IpcHelper::compile(EventPayload::FrontEnd.to_string(), cmd)
@@ -228,15 +230,38 @@ impl TsckApp {
                 }
                 UE::GetReadableHotkee => {
                     get_window!(self, window_id, |ws| {
-                        request.map(|_req| -> anyhow::Result<()> {
-                            // let kees: Vec<ReadableHotkee> = self
-                            //     .channel_bus
-                            //     .get_app_config()
-                            //     .kees
-                            //     .iter()
-                            //     .map(|(k, f)| kee_to_readable_hotkee(k, f))
-                            //     .collect();
-                            // response_success!(ws.webview, req, kees);
+                        request.map(|req| -> anyhow::Result<()> {
+                            let source = self.channel_bus.get_config().lock().kee_source();
+                            let kees = readable_hotkees(&source);
+                            response_success!(ws.webview, req, kees);
+                            Ok(())
+                        });
+                    });
+                }
+                UE::GetKeeConflicts => {
+                    get_window!(self, window_id, |ws| {
+                        request.map(|req| -> anyhow::Result<()> {
+                            let source = self.channel_bus.get_config().lock().kee_source();
+                            let conflicts = kee_conflicts(&source);
+                            response_success!(ws.webview, req, conflicts);
+                            Ok(())
+                        });
+                    });
+                }
+                UE::GetKeeCheatSheet => {
+                    get_window!(self, window_id, |ws| {
+                        request.map(|req| -> anyhow::Result<()> {
+                            let source = self.channel_bus.get_config().lock().kee_source();
+                            let sections = kee_cheat_sheet(&source);
+                            response_success!(ws.webview, req, sections);
+                            Ok(())
+                        });
+                    });
+                }
+                UE::GetKeeSchema => {
+                    get_window!(self, window_id, |ws| {
+                        request.map(|req| -> anyhow::Result<()> {
+                            response_success!(ws.webview, req, kee_schema());
                             Ok(())
                         });
                     });
@@ -372,8 +397,8 @@ impl TsckApp {
                                 }
                                 crate::event::WsPayloadType::PushToWhatsapp => {
                                     if let WsPayloadContent::Text(filepath) = m.content {
-                                        let cfg = self.channel_bus.get_app_config();
-                                        let url = cfg.whatsapp_url;
+                                        let url =
+                                            self.channel_bus.get_config().lock().whatsapp_url();
                                         let result = format!(
                                             "http://{}/send-thumbnail?filepath={}&channel={}",
                                             url,
@@ -467,7 +492,13 @@ impl TsckApp {
                     });
                 }
                 UE::SetAppConfig(config) => {
-                    self.channel_bus.update_app_config(config);
+                    let report = self.channel_bus.update_app_config(config);
+                    get_window!(self, window_id, |ws| {
+                        request.map(|req| -> anyhow::Result<()> {
+                            response_success!(ws.webview, req, config_issues(&report));
+                            Ok(())
+                        });
+                    });
                 }
                 UE::UpdateTextureFavorite(id, favorite) => {
                     self.channel_bus.texture_update_favorite(id, favorite);
@@ -516,16 +547,14 @@ impl TsckApp {
                     });
                 }
                 UE::UpdateRawfilterTemplates(templates) => {
-                    _ = self
-                        .channel_bus
+                    self.channel_bus
                         .get_config()
                         .lock()
-                        .config_store
                         .set(|c| c.rawfilter_template = templates);
                 }
                 UE::GoogleDownloadImage(url) => {
-                    let comfyui_root = &self.channel_bus.get_app_config().comfyui_root;
-                    if let Some(payload) = dl_image(&url, comfyui_root) {
+                    let comfyui_root = self.channel_bus.get_config().lock().comfyui_root();
+                    if let Some(payload) = dl_image(&url, &comfyui_root) {
                         self.channel_bus.broadcast_to_websocket(payload);
                     }
                 }
@@ -549,7 +578,7 @@ impl TsckApp {
                     }
                 }
                 UserEvent::ExecuteScript(script) => {
-                    let cs_script = self.channel_bus.get_app_config().store_root;
+                    let cs_script = self.channel_bus.get_config().lock().store_root();
                     let customscripts = Path::new(&cs_script).join("customscripts");
                     if let Ok(scr) = CustomScripts::new().script_to_str(&customscripts, &script) {
                         log_debug!(&scr);
@@ -1,11 +1,18 @@
 use crate::cmd::CommandConfig;
 use crate::event::TS_PATH;
 use crate::store::config::WindowSize;
-use crate::{DOTFILE_DIR, ts_struct};
+use crate::{DOTFILE_DIR, log_info, log_warn, ts_struct};
+use anyhow::bail;
 use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
+use std::path::Path;
+use std::sync::OnceLock;
 use ts_rs::TS;
-use tsck_kee::{KeeParser, TKeePair};
-use tsck_utils::ConfigStore;
+use tsck_kee::{KeeDocument, KeeNode};
+use tsck_utils::{
+    ConfigStore, DEFAULT_BACKUPS, FuncExpr, FuncLexer, Layered, Migration, Report, find_executable,
+    locate, rename_key, write_atomic,
+};
 
 ts_struct! { path = TS_PATH,
     pub struct RawFilterDataType {
@@ -58,40 +65,410 @@ macro_rules! app_config {
         	$(pub $field:$a),+
         }
         pub struct $struct_name_handler {
-        	pub(crate) config_store:ConfigStore<AppConfig>
+        	/// conf.json alone, what the settings edit and save
+        	pub(crate) config_store:ConfigStore<AppConfig>,
+        	/// conf.json between the defaults and the local, env and `--set` layers
+        	layered:Layered<AppConfig>,
+        	/// Why conf.json was left for the defaults, until it is saved or reloaded
+        	load_error:Option<String>
         }
         impl $struct_name_handler{
-        	pub fn new()->Self{Self{config_store:Self::get()}}
+        	pub fn new()->Self{
+        		let (config_store, load_error) = Self::load();
+        		let layered = layer(&config_store);
+        		Self{config_store,layered,load_error}
+        	}
         	pub fn update_config(&mut self,config:AppConfig){
-         			_ = self.config_store.set(|c|*c=config);
+         			self.set(|c|*c=config);
          }
-         	pub fn get()->ConfigStore<AppConfig>{ConfigStore::<AppConfig>::new(DOTFILE_DIR, "conf.json").expect("Failed to load conf.json")}
+         	pub fn set(&mut self,f:impl FnOnce(&mut AppConfig)){
+         		match self.config_store.set(f) {
+         			Ok(()) => self.load_error = None,
+         			Err(err) => log_warn!("conf.json", err),
+         		}
+         		self.layered = layer(&self.config_store);
+         	}
+         	/// conf.json changed on disk and was parsed already
+         	pub fn reload_file(&mut self,config:AppConfig){
+         		self.config_store.replace(config);
+         		self.load_error = None;
+         		self.layered = layer(&self.config_store);
+         	}
+         	/// Effective config, with the origin of every value
+         	pub fn layered(&self)->&Layered<AppConfig>{
+         		&self.layered
+         	}
+         	/// Set when conf.json didn't load and the defaults are used instead
+         	pub fn load_error(&self)->Option<&str>{
+         		self.load_error.as_deref()
+         	}
+         	pub fn get()->ConfigStore<AppConfig>{
+         		Self::load().0
+         	}
+         	/// The store, and why conf.json didn't load when it holds the defaults
+         	fn load()->(ConfigStore<AppConfig>,Option<String>){
+         		// kee.kee takes the carried lines before conf.json is saved upgraded
+         		let (store, ran) = match ConfigStore::<AppConfig>::new_migrated(DOTFILE_DIR, "conf.json", MIGRATIONS, KeeCarry::save) {
+         			Ok(loaded) => loaded,
+         			Err(err) => {
+         				// left on disk as it is, to fix or to migrate on the next start
+         				let err = format!("{:#}", err);
+         				log_warn!("conf.json doesn't load, using the defaults", &err);
+         				return (ConfigStore::fallback(DOTFILE_DIR, "conf.json"), Some(err));
+         			}
+         		};
+         		if let Some(backup) = store.recovered_from() {
+         			log_warn!("conf.json doesn't parse, loaded", backup.display());
+         		}
+         		for step in ran {
+         			log_info!("conf.json migrated to", step.to, step.description);
+         		}
+         		(store, None)
+         	}
           $($custom_func)*
           $(
           pub fn $field(&self)->$a{
-          	self.config_store.get(|c|c.$field.clone())
+          	self.layered.get().$field.clone()
           }
           )+
         }
     };
 }
-fn read_kee() -> Vec<TKeePair> {
-    if let Ok(content) = ConfigStore::<String>::read_file(DOTFILE_DIR, "kee.kee") {
-        let k = KeeParser::new(&content).parse();
-        return k
-            .iter()
-            .map(|k| TKeePair::new(k.kee, k.func))
-            .collect::<Vec<_>>();
-    } else {
-        return Vec::new();
+static CLI_OVERRIDES: OnceLock<Vec<String>> = OnceLock::new();
+
+/// `--set key=value` of the command line, layered over every load of the config
+pub fn set_cli_overrides(assignments: Vec<String>) {
+    _ = CLI_OVERRIDES.set(assignments);
+}
+
+/// Defaults, conf.json, conf.local.json next to it, `TSCK_*` variables and
+/// `--set` flags, each overriding the ones before. A layer that doesn't fit is
+/// logged and skipped.
+fn layer(store: &ConfigStore<AppConfig>) -> Layered<AppConfig> {
+    let layered = Layered::<AppConfig>::new()
+        .expect("AppConfig serialises")
+        .file(store.source())
+        .file(locate(&store.path().with_file_name("conf.local.json")))
+        .env("TSCK_", std::env::vars())
+        .set(CLI_OVERRIDES.get().into_iter().flatten());
+    for err in layered.errors() {
+        log_warn!("config", format!("{:#}", err));
+    }
+    layered
+}
+
+/// What the migrations move out of conf.json into kee.kee
+#[derive(Debug, Default, PartialEq)]
+pub struct KeeCarry {
+    /// `let` lines, put above the bindings
+    variables: Vec<String>,
+    bindings: Vec<String>,
+    /// Carried `INCREMENTS`, the bindings that left their `by` out get it
+    increments: Vec<(&'static str, &'static str, usize)>,
+}
+
+impl KeeCarry {
+    /// kee.kee `source` with the carried lines added, bindings that relied
+    /// on a carried increment pass it as `by=$name`
+    fn merge(&self, source: &str) -> String {
+        let mut out = String::new();
+        for variable in &self.variables {
+            out.push_str(variable);
+            out.push('\n');
+        }
+        out.push_str(source);
+        if !self.bindings.is_empty() {
+            if !out.is_empty() && !out.ends_with('\n') {
+                out.push('\n');
+            }
+            out.push_str("\n[conf.json]\n");
+            for binding in &self.bindings {
+                out.push_str(binding);
+                out.push('\n');
+            }
+        }
+        match self.increments.is_empty() {
+            true => out,
+            false => self.pass_increments(&out),
+        }
+    }
+
+    fn pass_increments(&self, source: &str) -> String {
+        let mut out = String::with_capacity(source.len());
+        for line in KeeDocument::parse(source).lines {
+            let KeeNode::Binding { func, .. } = line.node else {
+                out.push_str(line.raw);
+                continue;
+            };
+            let start = func.as_ptr() as usize - line.raw.as_ptr() as usize;
+            out.push_str(&line.raw[..start]);
+            out.push_str(&self.pass_increment(func));
+            out.push_str(&line.raw[start + func.len()..]);
+        }
+        out
+    }
+
+    /// `chain` with `by=$name` added to its steps that leave `by` out
+    fn pass_increment(&self, chain: &str) -> String {
+        let inserts: Vec<(usize, String)> = FuncLexer::split_chain(chain)
+            .into_iter()
+            .filter_map(|step| {
+                let func = FuncLexer::parse_func(step)?;
+                if !["workspace", "ws"].contains(&func.entry.to_lowercase().as_str()) {
+                    return None;
+                }
+                let (name, _, by) = self
+                    .increments
+                    .iter()
+                    .find(|(_, f, _)| f.eq_ignore_ascii_case(func.func))?;
+                let positional = func
+                    .args
+                    .iter()
+                    .filter(|a| !matches!(a, FuncExpr::Named(..)))
+                    .count();
+                let named = func
+                    .args
+                    .iter()
+                    .any(|a| matches!(a, FuncExpr::Named(n, _) if n.eq_ignore_ascii_case("by")));
+                if positional > *by || named || !step.ends_with(')') {
+                    return None;
+                }
+                let at = step.as_ptr() as usize - chain.as_ptr() as usize + step.len() - 1;
+                let comma = if func.args.is_empty() { "" } else { ", " };
+                Some((at, format!("{}by=${}", comma, name)))
+            })
+            .collect();
+        let mut chain = chain.to_string();
+        for (at, by) in inserts.into_iter().rev() {
+            chain.insert_str(at, &by);
+        }
+        chain
+    }
+
+    fn save(&self) -> anyhow::Result<()> {
+        if *self == Self::default() {
+            return Ok(());
+        }
+        let path = ConfigStore::<String>::get_file_path(DOTFILE_DIR, "kee.kee")?;
+        write_atomic(
+            Path::new(&path),
+            &self.merge(&read_kee_source()),
+            DEFAULT_BACKUPS,
+        )
+    }
+}
+
+/// Upgrades of older conf.json files, by the `schema_version` they lead to
+pub const MIGRATIONS: &[Migration<KeeCarry>] = &[
+    Migration {
+        to: 1,
+        description: "rename `worskpace` to `workspaces`, drop the unused `version`",
+        apply: |config, _| {
+            rename_key(config, "worskpace", "workspaces");
+            // never read, `schema_version` is what tells the layouts apart
+            config.remove("version");
+            Ok(())
+        },
+    },
+    Migration {
+        to: 2,
+        description: "move the `kees` map into kee.kee",
+        apply: migrate_kees,
+    },
+    Migration {
+        to: 3,
+        description: "move `move_increment` and `resize_increment` into kee.kee variables \
+                      and pass them to the bindings that left `by` out",
+        apply: migrate_increments,
+    },
+];
+
+fn migrate_kees(config: &mut Map<String, Value>, carry: &mut KeeCarry) -> anyhow::Result<()> {
+    let Some(kees) = config.remove("kees") else {
+        return Ok(());
+    };
+    let Value::Object(kees) = kees else {
+        bail!("`kees` is not a map of keys to functions");
+    };
+    for (kee, func) in kees {
+        let Value::String(func) = func else {
+            bail!("`kees.{}` is not a function", kee);
+        };
+        carry.bindings.push(format!("{} = {}", kee, func));
+    }
+    Ok(())
+}
+
+/// conf.json increments, the workspace function each one was the `by` of
+/// and the position of `by` among its arguments
+const INCREMENTS: [(&str, &str, usize); 2] = [
+    ("move_increment", "MOVEACTIVEWINDOW", 1),
+    ("resize_increment", "RESIZEACTIVEWINDOW", 2),
+];
+
+fn migrate_increments(config: &mut Map<String, Value>, carry: &mut KeeCarry) -> anyhow::Result<()> {
+    for increment in INCREMENTS {
+        let name = increment.0;
+        let Some(value) = config.remove(name) else {
+            continue;
+        };
+        let Some(by) = value.as_i64() else {
+            bail!("`{}` is not a number", name);
+        };
+        carry.variables.push(format!("let {} = {}", name, by));
+        carry.increments.push(increment);
+    }
+    Ok(())
+}
+
+fn read_kee_source() -> String {
+    ConfigStore::<String>::read_file(DOTFILE_DIR, "kee.kee").unwrap_or_default()
+}
+
+ts_struct! { path = TS_PATH,
+    pub struct ConfigIssueReport {
+        /// `error` or `warning`
+        pub severity: String,
+        /// `command_config.commands[0].executable`
+        pub path: String,
+        pub message: String,
+    }
+}
+
+/// Issues of a config check, as the frontend gets them
+pub fn config_issues(report: &Report) -> Vec<ConfigIssueReport> {
+    report
+        .issues()
+        .iter()
+        .map(|issue| ConfigIssueReport {
+            severity: issue.severity.to_string(),
+            path: issue.path.clone(),
+            message: issue.message.clone(),
+        })
+        .collect()
+}
+
+impl AppConfig {
+    /// What parses but can't work: clashing ports, missing folders, commands
+    /// whose program isn't installed, sizes that aren't sizes
+    pub fn validate(&self) -> Report {
+        let mut report = Report::new();
+        self.validate_ports(&mut report);
+        check_dir(&mut report, "store_root", &self.store_root, true);
+        check_dir(&mut report, "comfyui_root", &self.comfyui_root, false);
+        report.nest("command_config", self.command_config.validate());
+        for (i, size) in self.window_sizes.iter().enumerate() {
+            let path = format!("window_sizes[{}]", i);
+            if size.width <= 0 || size.height <= 0 {
+                report.error(
+                    path,
+                    format!("{}x{} is not a size", size.width, size.height),
+                );
+            } else if let Some(first) = self.window_sizes[..i]
+                .iter()
+                .position(|s| (s.width, s.height) == (size.width, size.height))
+            {
+                report.warn(path, format!("same as window_sizes[{}]", first));
+            }
+        }
+        for (i, (width, height)) in self.monitors.iter().enumerate() {
+            if *width <= 0 || *height <= 0 {
+                report.error(
+                    format!("monitors[{}]", i),
+                    format!("{}x{} is not a resolution", width, height),
+                );
+            }
+        }
+        report
+    }
+
+    fn validate_ports(&self, report: &mut Report) {
+        let ports = [
+            ("http_server_port", self.http_server_port),
+            ("websocket_server_port", self.websocket_server_port),
+            ("whatsapp_bot_port", self.whatsapp_bot_port),
+        ];
+        for (i, (name, port)) in ports.iter().enumerate() {
+            if *port == 0 {
+                // the bot is optional, the servers are what the frontend talks to
+                if *name != "whatsapp_bot_port" {
+                    report.error(*name, "not set");
+                }
+                continue;
+            }
+            if let Some((other, _)) = ports[..i].iter().find(|(_, p)| p == port) {
+                report.error(*name, format!("{} is taken by {} already", port, other));
+            }
+        }
+    }
+}
+
+impl CommandConfig {
+    /// Paths are relative to `command_config`
+    fn validate(&self) -> Report {
+        let mut report = Report::new();
+        if self.log_limit <= 0 {
+            report.warn("log_limit", "no output would be kept");
+        }
+        for (i, command) in self.commands.iter().enumerate() {
+            let path = format!("commands[{}]", i);
+            if command.name.trim().is_empty() {
+                report.error(format!("{}.name", path), "empty");
+            } else if let Some(first) = self.commands[..i]
+                .iter()
+                .position(|c| c.name == command.name)
+            {
+                report.error(
+                    format!("{}.name", path),
+                    format!(
+                        "`{}` is commands[{}] already, only that one runs",
+                        command.name, first
+                    ),
+                );
+            }
+            match command.executable.as_deref().map(str::trim) {
+                None | Some("") => {
+                    report.warn(format!("{}.executable", path), "not set, it can't be run")
+                }
+                Some(program) if find_executable(program).is_none() => report.error(
+                    format!("{}.executable", path),
+                    format!("`{}` is not a file nor on PATH", program),
+                ),
+                Some(_) => {}
+            }
+            if let Some(work_dir) = &command.work_dir {
+                check_dir(&mut report, &format!("{}.work_dir", path), work_dir, true);
+            }
+        }
+        report
+    }
+}
+
+/// An existing directory at `dir`, an empty one is only an error when
+/// `required`
+fn check_dir(report: &mut Report, path: &str, dir: &str, required: bool) {
+    if dir.trim().is_empty() {
+        if required {
+            report.error(path, "not set");
+        }
+        return;
+    }
+    let message = match Path::new(dir) {
+        p if p.is_dir() => return,
+        p if p.exists() => format!("`{}` is not a directory", dir),
+        _ => format!("`{}` doesn't exist", dir),
+    };
+    match required {
+        true => report.error(path, message),
+        false => report.warn(path, message),
     }
 }
 
 app_config!(
     AppConfig =>AppConfigHandler,
     custom: (
-        pub fn get_tkee_pair(&self)->Vec<TKeePair>{
-            read_kee()
+        pub fn kee_source(&self)->String{
+            read_kee_source()
             // self.config_store.get(|c| {
             //     c.kees
             //         .clone()
@@ -101,28 +478,127 @@ app_config!(
             // })
         }
     ),
+    // version of the conf.json layout, older files go through `MIGRATIONS`
+    schema_version        : u32,
     monitors							: Vec<(i32, i32)>,
     apps									: Vec<String>,
     pages									: Vec<String>,
     workspaces						: Vec<String>,
     // kees									: HashMap<String, String>,
-    version								: String,
     store_root						: String,
     http_server_port			: u16,
     whatsapp_bot_port			: u16,
     comfyui_url						: String,
     comfyui_root					: String,
     websocket_server_port	: u16,
-    move_increment				: i32,
-    resize_increment			: i32,
     rawfilter_template		: Vec<RawFilterTemplate>,
     command_config				: CommandConfig,
     color_list						: Vec<String>,
     whatsapp_url					: String,
-    window_sizes          : Vec<WindowSize>
+    window_sizes          : Vec<WindowSize>,
+    // max ms between the strokes of `M-k c` like bindings, 0 = default
+    kee_sequence_timeout  : u32,
+    // idle ms after which a `mode name { ... }` block is left, 0 = default
+    kee_mode_timeout      : u32,
+    // reload conf.json, tsck.json and kee.kee when they change on disk, read at startup
+    watch_config          : bool
 
 );
 
+#[cfg(test)]
+mod migrations {
+    use super::*;
+    use tsck_utils::migrate;
+
+    fn fixture(source: &str) -> String {
+        source.replace("\r\n", "\n")
+    }
+
+    #[test]
+    fn test_v0() -> anyhow::Result<()> {
+        let mut config: Value = serde_json::from_str(include_str!("../fixtures/conf_v0.json"))?;
+        let mut carry = KeeCarry::default();
+        let ran = migrate(&mut config, MIGRATIONS, &mut carry)?;
+        assert_eq!(ran.iter().map(|m| m.to).collect::<Vec<_>>(), [1, 2, 3]);
+        let migrated: Value =
+            serde_json::from_str(include_str!("../fixtures/conf_v0.migrated.json"))?;
+        assert_eq!(config, migrated);
+        let kee = carry.merge(&fixture(include_str!("../fixtures/kee_v0.kee")));
+        assert_eq!(
+            kee,
+            fixture(include_str!("../fixtures/kee_v0.migrated.kee"))
+        );
+        let config: AppConfig = serde_json::from_value(config)?;
+        assert_eq!(config.workspaces, ["photoshop", "code"]);
+        Ok(())
+    }
+
+    #[test]
+    fn test_v1() -> anyhow::Result<()> {
+        // renamed already, `workspaces` is left alone
+        let mut config = serde_json::json!({
+            "schema_version": 1,
+            "workspaces": ["code"],
+            "worskpace": ["stale"],
+            "kees": { "M-a": "app::CYCLEAPPS" }
+        });
+        let mut carry = KeeCarry::default();
+        assert_eq!(migrate(&mut config, MIGRATIONS, &mut carry)?.len(), 2);
+        assert_eq!(config["workspaces"], serde_json::json!(["code"]));
+        assert_eq!(carry.merge(""), "\n[conf.json]\nM-a = app::CYCLEAPPS\n");
+
+        let mut broken = serde_json::json!({ "schema_version": 1, "kees": ["M-a"] });
+        assert!(migrate(&mut broken, MIGRATIONS, &mut KeeCarry::default()).is_err());
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod validation {
+    use super::*;
+
+    #[test]
+    fn test_validate() -> anyhow::Result<()> {
+        let root = std::env::temp_dir();
+        let root = root.to_string_lossy();
+        let config: AppConfig = serde_json::from_value(serde_json::json!({
+            "store_root": root,
+            "comfyui_root": format!("{}/no-such-comfyui", root),
+            "http_server_port": 3030,
+            "websocket_server_port": 3030,
+            "command_config": {
+                "log_limit": 20,
+                "commands": [
+                    { "name": "comfy", "executable": "no-such-program", "work_dir": null, "args": [] },
+                    { "name": "comfy", "executable": null, "work_dir": root, "args": [] }
+                ]
+            },
+            "window_sizes": [{ "width": 800, "height": 600 }, { "width": 800, "height": 600 }],
+            "monitors": [[1920, 1080], [0, 1080]]
+        }))?;
+        let report = config.validate();
+        let issues: Vec<String> = report
+            .issues()
+            .iter()
+            .map(|i| format!("{} {}", i.severity, i.path))
+            .collect();
+        assert_eq!(
+            issues,
+            [
+                "error websocket_server_port",
+                "warning comfyui_root",
+                "error command_config.commands[0].executable",
+                "error command_config.commands[1].name",
+                "warning command_config.commands[1].executable",
+                "warning window_sizes[1]",
+                "error monitors[1]",
+            ]
+        );
+        assert_eq!(config_issues(&report)[0].severity, "error");
+        Ok(())
+    }
+}
+
 // #[derive(Serialize, Debug, Clone, Deserialize, Default)]
 // #[serde(default)]
 // pub struct AppConfig {
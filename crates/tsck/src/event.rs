@@ -88,6 +88,9 @@ ts_struct! {
 #[ts(export,export_to=TS_PATH)]
 pub enum UserEvent {
     GetReadableHotkee,
+    GetKeeConflicts,
+    GetKeeCheatSheet,
+    GetKeeSchema,
     WindowFocusChange(bool),
     ReloadConfig,
     Minimize,
@@ -112,6 +115,8 @@ pub enum UserEvent {
     SetIgnoreCursorEvent(bool),
     GoogleDownloadImage(String),
     ActivateWorkSpace(i32),
+    /// Mode entered from kee.kee, `None` once it's left
+    KeeModeChange(Option<String>),
     CyclePages(i32),
     GetActiveWindows,
     IncomingWebsocketMessage(u32, String),
@@ -1,39 +1,71 @@
 use std::time::Duration;
 use std::{str::FromStr, sync::Arc, time::Instant};
 
-use crate::event::ChannelEvent;
+use crate::event::{ChannelEvent, TS_PATH};
 use crate::utils::animation::{AnimationEasing, map_value};
+use crate::{DOTFILE_DIR, dp, log_debug, log_warn, ts_struct};
 use crate::{app_config::AppConfigHandler, event::UserEvent};
-use crate::{dp, log_debug};
 use flume::Sender;
 use parking_lot::Mutex;
+use serde::{Deserialize, Serialize};
+use ts_rs::TS;
 use tsck_derive::{FuncParser, ScopeParser};
-use tsck_kee::{Event, Kee, SafeHWND, TKeePair, WinPos, WindowInfo, list_windows};
-use tsck_kee::{Func, FuncExpr, FuncLexer};
+use tsck_kee::{ArgSchema, Func, FuncExpr, FuncLexer, FuncSchema, ScopeSchema};
+use tsck_kee::{
+    CheatSheet, DEFAULT_MODE_TIMEOUT, DEFAULT_SEQUENCE_TIMEOUT, Event, Kee, KeeDiagnostic,
+    KeeParser, SafeHWND, TKeePair, TsckKeeSequence, WinPos, WindowInfo, list_windows,
+};
+use tsck_utils::{ChainStep, ConfigStore, ConfigWatcher, WatchHandle, run_chain};
 use winit::event_loop::EventLoopProxy;
 
 #[derive(Debug, FuncParser)]
 enum WorkspaceFunc {
+    /// Switch to workspace N
     Activate(i32),
-    MoveActiveWindow(String),
-    ResizeActiveWindow(String, String),
+    /// `MOVEACTIVEWINDOW(dir='LEFT', by=80, duration=200)`
+    MoveActiveWindow {
+        dir: String,
+        #[func(default = 80)]
+        by: i32,
+        #[func(default = 150)]
+        duration: u64,
+    },
+    /// `RESIZEACTIVEWINDOW('INC', 'WIDTH', by=40)`
+    ResizeActiveWindow {
+        inc: String,
+        prop: String,
+        #[func(default = 40)]
+        by: i32,
+        #[func(default = 150)]
+        duration: u64,
+    },
+    /// Cycle the width of the focused window
     CycleActiveWindowWidth,
+    /// Cycle the position of the focused window
     CycleActiveWindowPos,
     MoveActiveWindowToWorkspace,
+    /// Cycle through the workspaces
     CycleWorkspace,
     WorkspaceTest,
 }
 
 #[derive(Debug, FuncParser)]
 enum AppFunc {
+    /// Run a Photoshop script
     Script(String),
+    /// Call a function of the Photoshop plugin
     FuncCall(String),
     CyclePages(String),
     LaunchPlugin(String),
+    /// Toggle the window level of tsck
     ToggleWindowLevel,
+    /// Show page N
     Page(i32),
     CycleApps,
+    /// Bring the named app to the front
+    #[func(rename = "APPTOFRONT", alias = "front")]
     AppToFront(String),
+    /// Reload conf.json and kee.kee
     ReloadConfig,
     ToggleCompactMode,
     ToggleShadow,
@@ -42,21 +74,29 @@ enum AppFunc {
 
 #[derive(Debug, ScopeParser)]
 enum FuncEntries {
+    /// tsck and Photoshop
     App(AppFunc),
+    /// Tiling of the desktop windows
+    #[scope(alias = "ws")]
     Workspace(WorkspaceFunc),
 }
 
+/// Queue a `UserEvent` for the event loop, an error once the loop is gone
 macro_rules! sender {
     ($channel_bus:ident,$page:ident  ) => {
-        _ = $channel_bus.send((UserEvent::$page , None, None));
+        sender!(@send $channel_bus, UserEvent::$page)
     };
     ($channel_bus:ident,$page:ident,  { $($fields:tt)* }  ) => {
-        _ = $channel_bus.send((UserEvent::$page { $($fields)* }, None, None));
+        sender!(@send $channel_bus, UserEvent::$page { $($fields)* })
     };
     ($channel_bus:ident,$page:ident,$arg:expr) => {
-        _ = $channel_bus.send((UserEvent::$page($arg), None, None));
+        sender!(@send $channel_bus, UserEvent::$page($arg))
+    };
+    (@send $channel_bus:ident, $event:expr) => {
+        $channel_bus
+            .send(($event, None, None))
+            .map_err(|_| String::from("the event loop is gone"))
     };
-
 }
 macro_rules! match_ignore_case {
     (
@@ -136,29 +176,32 @@ impl WindowOpsHandler {
         &mut self,
         sender: Sender<ChannelEvent>,
         proxy: Arc<EventLoopProxy>,
-    ) {
-        if let Some(active_window) = self.active_window.as_ref() {
-            if self
-                .workspaces_apps
-                .contains(&active_window.name().to_uppercase())
-            {
-                if let Some(window) = self
-                    .ws_windows
-                    .iter_mut()
-                    .find(|ws| ws.id == active_window.name())
-                {
-                    if window.workspace == self.active_workspace {
-                        window.real_position = active_window.position().clone();
-                    }
-                    log_debug!(dp!(window.real_position));
-                    window.workspace = (window.workspace + 1) % WORKSPACE_LENGTH;
-                    _ = active_window.move_to(
-                        window.real_position.x,
-                        window.real_position.y - (window.workspace as i32 * 1440),
-                    );
-                }
-            }
+    ) -> Result<(), String> {
+        let Some(active_window) = self.active_window.as_ref() else {
+            return Err("no active window".into());
+        };
+        if !self
+            .workspaces_apps
+            .contains(&active_window.name().to_uppercase())
+        {
+            return Err(format!("`{}` is not in workspaces", active_window.name()));
         }
+        let Some(window) = self
+            .ws_windows
+            .iter_mut()
+            .find(|ws| ws.id == active_window.name())
+        else {
+            return Err(format!("`{}` has no workspace yet", active_window.name()));
+        };
+        if window.workspace == self.active_workspace {
+            window.real_position = active_window.position().clone();
+        }
+        log_debug!(dp!(window.real_position));
+        window.workspace = (window.workspace + 1) % WORKSPACE_LENGTH;
+        active_window.move_to(
+            window.real_position.x,
+            window.real_position.y - (window.workspace as i32 * 1440),
+        )
         // self.ws_windows.iter().for_each(|w| {
         //     println!(
         //         "{:<10} =>x:{} y:{}, w:{}",
@@ -167,7 +210,11 @@ impl WindowOpsHandler {
         // });
         // cycle_value!(self.active_workspace, WORKSPACE_LENGTH);
     }
-    pub fn cycle_workspace(&mut self, sender: Sender<ChannelEvent>, proxy: Arc<EventLoopProxy>) {
+    pub fn cycle_workspace(
+        &mut self,
+        sender: Sender<ChannelEvent>,
+        proxy: Arc<EventLoopProxy>,
+    ) -> Result<(), String> {
         cycle_value!(self.active_workspace, WORKSPACE_LENGTH);
         self.ws_windows.iter().for_each(|w| {
             if self.active_workspace == w.workspace {
@@ -178,8 +225,9 @@ impl WindowOpsHandler {
                     .move_to(w.real_position.x, w.real_position.y - 1440);
             }
         });
-        sender!(sender, ActivateWorkSpace, self.active_workspace as i32);
+        sender!(sender, ActivateWorkSpace, self.active_workspace as i32)?;
         proxy.wake_up();
+        Ok(())
     }
     pub fn update_workspaces_app(&mut self, window_info: &WindowInfo) {
         let windows = list_windows();
@@ -211,20 +259,21 @@ impl WindowOpsHandler {
         self.config_handler = AppConfigHandler::new();
         self.apps = self.config_handler.apps();
     }
-    pub fn next_app(&mut self, sender: Sender<ChannelEvent>) {
+    pub fn next_app(&mut self, sender: Sender<ChannelEvent>) -> Result<(), String> {
+        if self.apps.is_empty() {
+            return Err("no apps in conf.json".into());
+        }
         self.active_index = (self.active_index + 1) % self.apps.len();
-        if let Some(app) = self.get_app(self.active_index) {
-            if let Some((lhs, rhs)) = app.split_once(":") {
-                if lhs == "T" {
-                    if rhs.eq_ignore_ascii_case("TSCK-BROWSER") {
-                        sender!(sender, LaunchPlugin, rhs.to_lowercase());
-                    } else {
-                        WindowOps::to_front(SearchMode::Title, rhs);
-                    }
-                }
-            } else {
-                WindowOps::to_front(SearchMode::Name, app);
+        let Some(app) = self.get_app(self.active_index) else {
+            return Ok(());
+        };
+        match app.split_once(":") {
+            Some(("T", rhs)) if rhs.eq_ignore_ascii_case("TSCK-BROWSER") => {
+                sender!(sender, LaunchPlugin, rhs.to_lowercase())
             }
+            Some(("T", rhs)) => WindowOps::to_front(SearchMode::Title, rhs),
+            Some(_) => Ok(()),
+            None => WindowOps::to_front(SearchMode::Name, app),
         }
     }
     // pub fn next_workspace(&mut self) -> usize {
@@ -240,18 +289,22 @@ impl WindowOpsHandler {
     pub fn get_app(&self, index: usize) -> Option<&String> {
         self.apps.get(index)
     }
-    fn cycle_move_active_app(&mut self, window: &WindowInfo) {
-        if self
+    fn cycle_move_active_app(&mut self, window: &WindowInfo) -> Result<(), String> {
+        self.workspace_app(window)?;
+        cycle_value!(self.active_workspace, WORKSPACE_LENGTH);
+        let active_workspace = self.active_workspace as i32;
+        //zed -8 -8 (2576, 1408)
+        let monitor = 2576 / 3;
+        window.move_to(-8 + (monitor * active_workspace), -8)
+    }
+    fn workspace_app(&self, window: &WindowInfo) -> Result<(), String> {
+        match self
             .workspaces_apps
             .iter()
-            .find(|w| window.name().to_uppercase() == w.to_uppercase())
-            .is_some()
+            .any(|w| window.name().to_uppercase() == w.to_uppercase())
         {
-            cycle_value!(self.active_workspace, WORKSPACE_LENGTH);
-            let active_workspace = self.active_workspace as i32;
-            //zed -8 -8 (2576, 1408)
-            let monitor = 2576 / 3;
-            _ = window.move_to(-8 + (monitor * active_workspace), -8);
+            true => Ok(()),
+            false => Err(format!("`{}` is not in workspaces", window.name())),
         }
     }
     fn arrange_window_on_workspace(&self) {
@@ -270,113 +323,107 @@ impl WindowOpsHandler {
             }
         }
     }
-    fn cycle_resize_active_app(&mut self, window: &WindowInfo) {
-        if self
-            .workspaces_apps
-            .iter()
-            .find(|w| window.name().to_uppercase() == w.to_uppercase())
-            .is_some()
-        {
-            cycle_value!(self.active_workspace, WORKSPACE_LENGTH);
-            let active_workspace = self.active_workspace as i32;
-            let monitor = (2576, 1408);
+    fn cycle_resize_active_app(&mut self, window: &WindowInfo) -> Result<(), String> {
+        self.workspace_app(window)?;
+        cycle_value!(self.active_workspace, WORKSPACE_LENGTH);
+        let active_workspace = self.active_workspace as i32;
+        let monitor = (2576, 1408);
 
-            //zed -8 -8 (2576, 1408)
+        //zed -8 -8 (2576, 1408)
 
-            // log_debug!(window.name(), dp!(window.size()), dp!(window.position()));
-            match active_workspace {
-                0 => {
-                    _ = window.resize(monitor.0 / 3, monitor.1);
-                    _ = window.move_to(-8, -8);
-                }
-                1 => {
-                    _ = window.resize(monitor.0 / 2, monitor.1);
-                    _ = window.move_to(-8, -8);
-                }
-                2 => {
-                    _ = window.resize(monitor.0, monitor.1);
-                    _ = window.move_to(-8, -8);
-                }
-                _ => {}
-            }
-        }
+        // log_debug!(window.name(), dp!(window.size()), dp!(window.position()));
+        let width = match active_workspace {
+            0 => monitor.0 / 3,
+            1 => monitor.0 / 2,
+            _ => monitor.0,
+        };
+        window.resize(width, monitor.1)?;
+        window.move_to(-8, -8)
     }
 }
 
 struct WindowOps;
 
 impl WindowOps {
-    fn to_front(search_mode: SearchMode, payload: &str) {
-        if let Some(window) = tsck_kee::list_windows().iter().find(|k| match search_mode {
+    fn to_front(search_mode: SearchMode, payload: &str) -> Result<(), String> {
+        let windows = tsck_kee::list_windows();
+        let window = windows.iter().find(|k| match search_mode {
             SearchMode::Title => k.title().to_uppercase() == payload.to_uppercase(),
             SearchMode::Name => k.name().to_uppercase() == payload.to_uppercase(),
-        }) {
-            _ = window.bring_to_front();
+        });
+        match window {
+            Some(window) => window.bring_to_front(),
+            None => Err(format!("no window `{}`", payload)),
         }
     }
 
-    fn move_active_window(to: &str, hwnd: &SafeHWND) {
+    fn move_active_window(
+        to: &str,
+        inc: i32,
+        duration: u64,
+        hwnd: &SafeHWND,
+    ) -> Result<(), String> {
+        let w = WindowOps::find(hwnd)?;
+        let (wx, wy) = (w.position().x, w.position().y);
+        let (a, b) = match_ignore_case!(to,
+              else : return Err(format!("`{}` is not LEFT, RIGHT, UP or DOWN", to)),
+              "LEFT"  => (wx - inc, wy),
+              "RIGHT" => (wx + inc, wy),
+              "UP"    => (wx, wy - inc),
+              "DOWN"  => (wx, wy + inc),
+        );
         if window_animation::is_running() {
-            return;
+            return Err("a window animation is running".into());
         }
-        let to = to.to_string();
-        let hwnd = hwnd.clone();
         std::thread::spawn(move || {
-            let inc = AppConfigHandler::new().move_increment();
-            if let Some(w) = list_windows().iter().find(|w| &w.hwnd == &hwnd) {
-                let (wx, wy) = (w.position().x, w.position().y);
-
-                let (a, b) = match_ignore_case!(&to,
-                      else : (wx,wy),
-                      "LEFT"  => (wx - inc, wy),
-                      "RIGHT" => (wx + inc, wy),
-                      "UP"    => (wx, wy - inc),
-                      "DOWN"  => (wx, wy + inc),
-                );
-                // _ = w.move_to(a, b);
-                WindowOps::animate_window(
-                    w,
-                    Some((a, b)),
-                    None,
-                    150,
-                    AnimationEasing::EaseInOutCubic,
-                );
-            }
+            // _ = w.move_to(a, b);
+            WindowOps::animate_window(
+                &w,
+                Some((a, b)),
+                None,
+                duration,
+                AnimationEasing::EaseInOutCubic,
+            );
         });
+        Ok(())
     }
 
-    fn resize_active_window(prop: &str, increment: bool, hwnd: &SafeHWND) {
+    fn find(hwnd: &SafeHWND) -> Result<WindowInfo, String> {
+        list_windows()
+            .into_iter()
+            .find(|w| &w.hwnd == hwnd)
+            .ok_or_else(|| "the active window is gone".to_string())
+    }
+
+    fn resize_active_window(
+        prop: &str,
+        increment: bool,
+        by: i32,
+        duration: u64,
+        hwnd: &SafeHWND,
+    ) -> Result<(), String> {
+        let inc = if increment { by } else { -by };
+        let w = WindowOps::find(hwnd)?;
+        let (width, height) = (w.size().width, w.size().height);
+        let (a, b) = match_ignore_case!(prop,
+            else: return Err(format!("`{}` is not WIDTH or HEIGHT", prop)),
+            "WIDTH" => (width + inc, height),
+            "HEIGHT" => (width, height + inc),
+        );
         if window_animation::is_running() {
-            return;
+            return Err("a window animation is running".into());
         }
-        let prop = prop.to_string();
-        let hwnd = hwnd.clone();
         std::thread::spawn(move || {
-            let inc = {
-                let increment_value = AppConfigHandler::new().move_increment();
-                if increment {
-                    increment_value
-                } else {
-                    -increment_value
-                }
-            };
-            if let Some(w) = list_windows().iter().find(|w| &w.hwnd == &hwnd) {
-                let (width, height) = (w.size().width, w.size().height);
-                let (a, b) = match_ignore_case!(&prop,
-                    else: (width, height),
-                    "WIDTH" => (width + inc, height),
-                    "HEIGHT" => (width, height + inc),
-                );
-                // _ = w.resize(a, b);
-                WindowOps::animate_window(
-                    w,
-                    None,
-                    Some((a, b)),
-                    150,
-                    AnimationEasing::EaseInOutCubic,
-                );
-            }
+            // _ = w.resize(a, b);
+            WindowOps::animate_window(
+                &w,
+                None,
+                Some((a, b)),
+                duration,
+                AnimationEasing::EaseInOutCubic,
+            );
         });
+        Ok(())
     }
     fn animate_window(
         ws: &WindowInfo,
@@ -442,168 +489,449 @@ impl WindowOps {
     }
 }
 
+ts_struct! {path = TS_PATH,
+    pub struct ReadableHotkee {
+        pub section: Option<String>,
+        /// App the binding is limited to, `M-d [PHOTOSHOP]`
+        pub app: Option<String>,
+        /// `mode name { ... }` block the key belongs to
+        pub mode: Option<String>,
+        pub key: String,
+        pub meta: bool,
+        pub ctrl: bool,
+        pub shift: bool,
+        pub alt: bool,
+        pub func: String,
+    }
+}
+
+/// Bindings of kee.kee in file order, tagged with their `[section]`.
+pub fn readable_hotkees(source: &str) -> Vec<ReadableHotkee> {
+    KeeParser::new(source)
+        .parse()
+        .iter()
+        .filter_map(|k| {
+            let sequence = TsckKeeSequence::parse(k.kee).ok()?;
+            let binding = sequence.strokes.first()?;
+            // modifiers of the first stroke, then every key: `M-k c` -> meta + `k c`
+            let (keys, _) = TsckKeeSequence::split_app(k.kee);
+            let key = keys
+                .split_whitespace()
+                .map(|stroke| stroke.rsplit('-').next().unwrap_or(stroke))
+                .collect::<Vec<_>>()
+                .join(" ");
+            Some(ReadableHotkee {
+                section: k.section.map(str::to_string),
+                app: sequence.app.clone(),
+                mode: k.mode.map(str::to_string),
+                key,
+                meta: binding.modifiers.meta,
+                ctrl: binding.modifiers.ctrl,
+                shift: binding.modifiers.shift,
+                alt: binding.modifiers.alt,
+                func: k.func.to_string(),
+            })
+        })
+        .collect()
+}
+
+/// Validate kee.kee: key specs, scopes, functions and argument types as
+/// errors, conflicting bindings as warnings.
+pub fn kee_diagnostics(source: &str) -> Vec<KeeDiagnostic> {
+    let parser = KeeParser::new(source);
+    let mut diagnostics = parser.check(|func| FuncEntries::parse(func).map(|_| ()));
+    diagnostics.extend(parser.conflicts().iter().map(|c| c.to_diagnostic(source)));
+    diagnostics.sort_by_key(|d| d.span.start);
+    diagnostics
+}
+
+ts_struct! {path = TS_PATH,
+    pub struct KeeConflictReport {
+        /// `duplicate`, `shadowed` or `reserved`
+        pub kind: String,
+        /// Canonical form, `S-M-a` -> `M-S-a`
+        pub key: String,
+        pub line: usize,
+        /// The binding it collides with
+        pub other_key: Option<String>,
+        pub other_line: Option<usize>,
+        pub message: String,
+    }
+}
+
+/// Duplicate, shadowed and OS-reserved bindings of kee.kee.
+pub fn kee_conflicts(source: &str) -> Vec<KeeConflictReport> {
+    KeeParser::new(source)
+        .conflicts()
+        .into_iter()
+        .map(|c| {
+            let (other_key, other_line) = c.other.unzip();
+            KeeConflictReport {
+                kind: c.kind.to_string(),
+                key: c.kee,
+                line: c.line,
+                other_key,
+                other_line,
+                message: c.message,
+            }
+        })
+        .collect()
+}
+
+ts_struct! {path = TS_PATH,
+    pub struct KeeCheatEntry {
+        /// Human-readable keys, `Win+Shift+←`
+        pub keys: String,
+        /// Canonical form, `M-S-left`
+        pub kee: String,
+        pub func: String,
+        pub app: Option<String>,
+        pub description: Option<String>,
+        pub line: usize,
+    }
+}
+
+ts_struct! {path = TS_PATH,
+    pub struct KeeCheatSection {
+        /// Section name, `mode <name>` or `general`
+        pub title: String,
+        pub section: Option<String>,
+        pub mode: Option<String>,
+        pub description: Option<String>,
+        pub entries: Vec<KeeCheatEntry>,
+    }
+}
+
+/// Cheat sheet of kee.kee, bindings without a comment are described by the
+/// doc comment of their function
+pub fn cheat_sheet(source: &str) -> CheatSheet {
+    let mut sheet = CheatSheet::parse(source);
+    for entry in sheet.sections.iter_mut().flat_map(|s| s.entries.iter_mut()) {
+        if entry.description.is_none() {
+            entry.description = FuncEntries::help(&entry.func).map(str::to_string);
+        }
+    }
+    sheet
+}
+
+/// kee.kee grouped by section and mode, for the cheat sheet overlay.
+pub fn kee_cheat_sheet(source: &str) -> Vec<KeeCheatSection> {
+    cheat_sheet(source)
+        .sections
+        .into_iter()
+        .map(|section| KeeCheatSection {
+            title: section.title(),
+            section: section.name,
+            mode: section.mode,
+            description: section.description,
+            entries: section
+                .entries
+                .into_iter()
+                .map(|e| KeeCheatEntry {
+                    keys: e.keys,
+                    kee: e.kee,
+                    func: e.func,
+                    app: e.app,
+                    description: e.description,
+                    line: e.line,
+                })
+                .collect(),
+        })
+        .collect()
+}
+
+ts_struct! {path = TS_PATH,
+    pub struct KeeArgSchema {
+        /// Name accepted as `name=value`, null for positional only arguments
+        pub name: Option<String>,
+        /// Rust type, `i32`, `Option<String>`, `(i32,i32)`
+        pub ty: String,
+        /// Value used when left out, as written in kee.kee
+        pub default: Option<String>,
+        pub optional: bool,
+        pub doc: Option<String>,
+    }
+}
+
+ts_struct! {path = TS_PATH,
+    pub struct KeeFuncSchema {
+        pub name: String,
+        pub aliases: Vec<String>,
+        pub doc: Option<String>,
+        /// `MOVEACTIVEWINDOW(dir: String, by: i32 = 80, duration: u64 = 150)`
+        pub signature: String,
+        pub args: Vec<KeeArgSchema>,
+    }
+}
+
+ts_struct! {path = TS_PATH,
+    pub struct KeeScopeSchema {
+        pub name: String,
+        pub aliases: Vec<String>,
+        pub doc: Option<String>,
+        pub funcs: Vec<KeeFuncSchema>,
+    }
+}
+
+/// Every `scope::FUNC(args)` kee.kee accepts, for autocomplete in the settings
+pub fn kee_schema() -> Vec<KeeScopeSchema> {
+    let strings = |names: &[&str]| -> Vec<String> { names.iter().map(|n| n.to_string()).collect() };
+    let arg = |a: &ArgSchema| KeeArgSchema {
+        name: a.name.map(str::to_string),
+        ty: a.ty.to_string(),
+        default: a.default.map(str::to_string),
+        optional: a.optional,
+        doc: a.doc.map(str::to_string),
+    };
+    let func = |f: &FuncSchema| KeeFuncSchema {
+        name: f.name.to_string(),
+        aliases: strings(f.aliases),
+        doc: f.doc.map(str::to_string),
+        signature: f.signature(),
+        args: f.args.iter().map(arg).collect(),
+    };
+    FuncEntries::schema()
+        .iter()
+        .map(|s| KeeScopeSchema {
+            name: s.name.to_string(),
+            aliases: strings(s.aliases),
+            doc: s.doc.map(str::to_string),
+            funcs: s.funcs.iter().map(func).collect(),
+        })
+        .collect()
+}
+
+pub fn kee_schema_json() -> String {
+    ScopeSchema::to_json(FuncEntries::schema())
+}
+
+/// Load the bindings from kee.kee, logging and skipping the invalid ones so a
+/// single typo doesn't take every hotkey down with it.
+fn load_kee_pairs(config: &AppConfigHandler) -> Vec<TKeePair> {
+    kee_pairs(&config.kee_source())
+}
+
+fn kee_pairs(source: &str) -> Vec<TKeePair> {
+    let diagnostics = kee_diagnostics(source);
+    for diagnostic in &diagnostics {
+        log_warn!("kee.kee", diagnostic);
+    }
+    KeeParser::new(source)
+        .parse()
+        .iter()
+        .filter(|k| {
+            !k.kee.is_empty() && !diagnostics.iter().any(|d| d.is_error() && d.line == k.line)
+        })
+        .map(|k| TKeePair::new(k.kee, k.func.as_ref()).in_mode(k.mode))
+        .collect()
+}
+
+fn sequence_timeout(config: &AppConfigHandler) -> Duration {
+    match config.kee_sequence_timeout() {
+        0 => DEFAULT_SEQUENCE_TIMEOUT,
+        ms => Duration::from_millis(ms as u64),
+    }
+}
+
+fn mode_timeout(config: &AppConfigHandler) -> Duration {
+    match config.kee_mode_timeout() {
+        0 => DEFAULT_MODE_TIMEOUT,
+        ms => Duration::from_millis(ms as u64),
+    }
+}
+
+/// Rebind kee.kee whenever it changes on disk. An edit with errors is rejected
+/// as a whole, the bindings already in place stay.
+fn watch_kee(kee: Arc<Mutex<Kee>>, source: String) -> anyhow::Result<WatchHandle> {
+    let path = ConfigStore::<String>::get_file_path(DOTFILE_DIR, "kee.kee")?;
+    let handle = ConfigWatcher::new(path, |source| {
+        match kee_diagnostics(source).into_iter().find(|d| d.is_error()) {
+            Some(diagnostic) => anyhow::bail!("{}", diagnostic),
+            None => Ok(source.to_string()),
+        }
+    })
+    .on_change(move |_, new: &String| {
+        _ = kee.lock().update_hotkeys(kee_pairs(new));
+    })
+    .on_reject(|path, err| log_warn!(path.display(), err))
+    .spawn(source)?;
+    Ok(handle)
+}
+
+/// Runs the function chains of the bindings. Shared with the threads of the
+/// chains that wait on a `delay(ms)` step.
+struct KeeDispatcher {
+    kee: Arc<Mutex<Kee>>,
+    winops: Arc<Mutex<WindowOpsHandler>>,
+    channel_bus: Sender<ChannelEvent>,
+    proxy: Arc<EventLoopProxy>,
+}
+
+impl KeeDispatcher {
+    /// Run `chain` in order, on a thread of its own when it sleeps so the
+    /// bindings and events that come in meanwhile don't wait for it
+    fn dispatch(self: &Arc<Self>, chain: &str) {
+        if ChainStep::waits(chain) {
+            let (dispatcher, chain) = (self.clone(), chain.to_string());
+            std::thread::spawn(move || dispatcher.run_steps(&chain));
+        } else {
+            self.run_steps(chain);
+        }
+    }
+
+    /// `a ; delay(200) ; b` stops at the first failing step
+    fn run_steps(&self, chain: &str) {
+        let result = run_chain(chain, |step| match step {
+            ChainStep::Call(call) => {
+                let result = FuncEntries::from_str(call).and_then(|entries| self.run(entries));
+                self.proxy.wake_up();
+                result
+            }
+            ChainStep::Mode(mode) => match self.kee.lock().enter_mode(mode) {
+                true => Ok(()),
+                false => Err(format!("no mode `{}`", mode)),
+            },
+            ChainStep::Delay(_) => Ok(()),
+        });
+        if let Err((step, err)) = result {
+            log_warn!("kee chain stopped", step, err);
+        }
+    }
+
+    fn active_window(&self) -> Result<WindowInfo, String> {
+        let active = self.winops.lock().get_active_window().clone();
+        active.ok_or_else(|| "no active window".to_string())
+    }
+
+    fn run(&self, entries: FuncEntries) -> Result<(), String> {
+        let channel_bus = &self.channel_bus;
+        match entries {
+            FuncEntries::App(func) => match func {
+                AppFunc::ReloadConfig => {
+                    self.winops.lock().update_apps();
+                    let config = AppConfigHandler::new();
+                    let kee = self.kee.lock();
+                    kee.set_sequence_timeout(sequence_timeout(&config));
+                    kee.set_mode_timeout(mode_timeout(&config));
+                    kee.update_hotkeys(load_kee_pairs(&config))
+                        .map_err(|err| err.to_string())
+                }
+                AppFunc::LaunchPlugin(win_title) => {
+                    sender!(channel_bus, LaunchPlugin, win_title.to_lowercase())
+                }
+                AppFunc::AppToFront(what) => WindowOps::to_front(SearchMode::Name, &what),
+                AppFunc::CycleApps => self.winops.lock().next_app(channel_bus.clone()),
+                AppFunc::CyclePages(direction) => {
+                    let step = match_ignore_case!(direction.as_str(),
+                        else: return Err(format!("`{}` is not PREV or NEXT", direction)),
+                        "PREV" => -1,
+                        "NEXT" => 1,
+                    );
+                    sender!(channel_bus, CyclePages, step)
+                }
+                AppFunc::Page(page) => sender!(channel_bus, FocusPage, page),
+                AppFunc::Script(script) => sender!(channel_bus, ExecuteScript, script),
+                AppFunc::FuncCall(func) => {
+                    sender!(channel_bus, FunctionCall, { func, args: vec![] })
+                }
+                AppFunc::ToggleShadow => sender!(channel_bus, ToggleShadow),
+                AppFunc::ToggleWindowLevel => sender!(channel_bus, ToggleWindowLevel),
+                AppFunc::ToggleCompactMode => sender!(channel_bus, ToggleCompactMode),
+                AppFunc::AppTest => Err("APPTEST does nothing yet".into()),
+            },
+            FuncEntries::Workspace(func) => match func {
+                WorkspaceFunc::Activate(page) => sender!(channel_bus, ActivateWorkSpace, page),
+                WorkspaceFunc::MoveActiveWindow { dir, by, duration } => {
+                    let w = self.active_window()?;
+                    WindowOps::move_active_window(&dir, by, duration, &w.hwnd)
+                }
+                WorkspaceFunc::ResizeActiveWindow {
+                    inc,
+                    prop,
+                    by,
+                    duration,
+                } => {
+                    let increment = match_ignore_case!(inc.as_str(),
+                        else: return Err(format!("`{}` is not INC or DEC", inc)),
+                        "INC" => true,
+                        "DEC" => false,
+                    );
+                    let w = self.active_window()?;
+                    WindowOps::resize_active_window(&prop, increment, by, duration, &w.hwnd)
+                }
+                WorkspaceFunc::CycleActiveWindowWidth => {
+                    let w = self.find_active_window()?;
+                    self.winops.lock().cycle_move_active_app(&w)
+                }
+                WorkspaceFunc::CycleActiveWindowPos => {
+                    let w = self.find_active_window()?;
+                    self.winops.lock().cycle_resize_active_app(&w)
+                }
+                WorkspaceFunc::CycleWorkspace => self
+                    .winops
+                    .lock()
+                    .cycle_workspace(channel_bus.clone(), self.proxy.clone()),
+                WorkspaceFunc::MoveActiveWindowToWorkspace => self
+                    .winops
+                    .lock()
+                    .move_active_window_to_workspace(channel_bus.clone(), self.proxy.clone()),
+                WorkspaceFunc::WorkspaceTest => Err("WORKSPACETEST does nothing yet".into()),
+            },
+        }
+    }
+
+    /// The active window as listed now, by its name
+    fn find_active_window(&self) -> Result<WindowInfo, String> {
+        let active = self.active_window()?;
+        list_windows()
+            .into_iter()
+            .find(|w| w.name() == active.name())
+            .ok_or_else(|| format!("`{}` is gone", active.name()))
+    }
+}
+
+/// Register the hotkeys, returns the kee.kee watcher when `watch_config` is on
 pub fn __spawn_hotkee(
     proxy: Arc<EventLoopProxy>,
     sender: Sender<ChannelEvent>,
-) -> anyhow::Result<()> {
+) -> anyhow::Result<Option<WatchHandle>> {
     let config = AppConfigHandler::new();
-    let kees: Vec<TKeePair> = config.get_tkee_pair();
+    let kees: Vec<TKeePair> = load_kee_pairs(&config);
     let winops_handler = Arc::new(Mutex::new(WindowOpsHandler::new()));
     let kee = Arc::new(Mutex::new(Kee::new()));
-    let arc_channel_bus = sender.clone();
-    let arc_kee = kee.clone();
-    let arc_winops_handler = winops_handler.clone();
+    kee.lock().set_sequence_timeout(sequence_timeout(&config));
+    kee.lock().set_mode_timeout(mode_timeout(&config));
+    let watcher = match config.watch_config() {
+        true => watch_kee(kee.clone(), config.kee_source())
+            .inspect_err(|err| log_warn!("watch", err))
+            .ok(),
+        false => None,
+    };
+    let dispatcher = Arc::new(KeeDispatcher {
+        kee: kee.clone(),
+        winops: winops_handler,
+        channel_bus: sender,
+        proxy,
+    });
 
     kee.lock()
         .on_message(move |event| match event {
-            Event::Keys(_, f) => {
-                proxy.wake_up();
-                if let Ok(entries) = FuncEntries::from_str(f) {
-                    match entries {
-                        FuncEntries::App(func) => match func {
-                            AppFunc::ReloadConfig => {
-                                {
-                                    arc_winops_handler.lock().update_apps();
-                                }
-                                _ = arc_kee
-                                    .clone()
-                                    .lock()
-                                    .update_hotkeys(AppConfigHandler::new().get_tkee_pair());
-                            }
-                            AppFunc::LaunchPlugin(win_title) => {
-                                sender!(arc_channel_bus, LaunchPlugin, win_title.to_lowercase());
-                            }
-                            AppFunc::AppToFront(what) => {
-                                WindowOps::to_front(SearchMode::Name, &what);
-                            }
-                            AppFunc::CycleApps => {
-                                arc_winops_handler.lock().next_app(arc_channel_bus.clone());
-                            }
-                            AppFunc::CyclePages(direction) => {
-                                sender!(
-                                    arc_channel_bus,
-                                    CyclePages,
-                                    match direction.as_str() {
-                                        "PREV" => -1,
-                                        "NEXT" => 1,
-                                        _ => 0,
-                                    }
-                                );
-                            }
-                            AppFunc::Page(page) => {
-                                sender!(arc_channel_bus, FocusPage, page);
-                            }
-                            AppFunc::Script(script) => {
-                                sender!(arc_channel_bus, ExecuteScript, script);
-                            }
-                            AppFunc::FuncCall(func) => {
-                                sender!(arc_channel_bus, FunctionCall, { func, args: vec![] });
-                            }
-                            AppFunc::ToggleShadow => {
-                                sender!(arc_channel_bus, ToggleShadow);
-                            }
-                            AppFunc::ToggleWindowLevel => {
-                                sender!(arc_channel_bus, ToggleWindowLevel);
-                            }
-                            AppFunc::ToggleCompactMode => {
-                                sender!(arc_channel_bus, ToggleCompactMode);
-                            }
-                            AppFunc::AppTest => todo!(),
-                        },
-                        FuncEntries::Workspace(func) => match func {
-                            WorkspaceFunc::Activate(page) => {
-                                sender!(arc_channel_bus, ActivateWorkSpace, page);
-                            }
-                            WorkspaceFunc::MoveActiveWindow(to) => {
-                                let clone_apps = arc_winops_handler.clone();
-                                {
-                                    if let Some(w) = clone_apps.lock().get_active_window() {
-                                        WindowOps::move_active_window(&to, &w.hwnd);
-                                    }
-                                }
-                            }
-                            WorkspaceFunc::ResizeActiveWindow(inc, prop) => {
-                                let increment = match inc.to_uppercase().as_str() {
-                                    "INC" => true,
-                                    _ => false,
-                                };
-                                let clone_apps = arc_winops_handler.clone();
-                                {
-                                    if let Some(w) = clone_apps.lock().get_active_window() {
-                                        WindowOps::resize_active_window(&prop, increment, &w.hwnd);
-                                    }
-                                }
-                            }
-                            WorkspaceFunc::CycleActiveWindowWidth => {
-                                {
-                                    let active_window = {
-                                        let g = arc_winops_handler.lock();
-                                        g.get_active_window().clone()
-                                    };
-                                    // let mut guard = { arc_winops_handler.lock() };
-                                    if let Some(active_window) = active_window {
-                                        if let Some(w) = list_windows()
-                                            .iter()
-                                            .find(|w| w.name() == active_window.name())
-                                        {
-                                            arc_winops_handler.lock().cycle_move_active_app(w);
-                                        }
-                                    }
-                                }
-                                {
-                                    // let index = { arc_winops_handler.lock().next_workspace() };
-                                    // sender!(arc_channel_bus, ActivateWorkSpace, index as i32);
-                                    // proxy.wake_up();
-                                }
-                            }
-                            WorkspaceFunc::CycleActiveWindowPos => {
-                                {
-                                    let active_window = {
-                                        let g = arc_winops_handler.lock();
-                                        g.get_active_window().clone()
-                                    };
-                                    // let mut guard = { arc_winops_handler.lock() };
-                                    if let Some(active_window) = active_window {
-                                        if let Some(w) = list_windows()
-                                            .iter()
-                                            .find(|w| w.name() == active_window.name())
-                                        {
-                                            arc_winops_handler.lock().cycle_resize_active_app(w);
-                                        }
-                                    }
-                                }
-                                {
-                                    // let index = { arc_winops_handler.lock().next_workspace() };
-                                    // sender!(arc_channel_bus, ActivateWorkSpace, index as i32);
-                                    // proxy.wake_up();
-                                }
-                            }
-                            WorkspaceFunc::CycleWorkspace => {
-                                arc_winops_handler
-                                    .lock()
-                                    .cycle_workspace(arc_channel_bus.clone(), proxy.clone());
-                            }
-                            WorkspaceFunc::MoveActiveWindowToWorkspace => {
-                                arc_winops_handler.lock().move_active_window_to_workspace(
-                                    arc_channel_bus.clone(),
-                                    proxy.clone(),
-                                );
-                            }
-                            WorkspaceFunc::WorkspaceTest => todo!(),
-                        },
-                    }
-                }
+            Event::Keys(_, chain) => dispatcher.dispatch(chain),
+            Event::ModeChange(mode) => {
+                let channel_bus = &dispatcher.channel_bus;
+                _ = sender!(channel_bus, KeeModeChange, mode.clone());
+                dispatcher.proxy.wake_up();
             }
             Event::WindowChange(safe_window_info) => {
-                let clone_apps = arc_winops_handler.clone();
-                {
-                    clone_apps.lock().update_workspaces_app(safe_window_info);
-                }
+                dispatcher
+                    .winops
+                    .lock()
+                    .update_workspaces_app(safe_window_info);
             }
             _ => {}
         })
-        .run(kees);
-    Ok(())
+        .run(kees)?;
+    Ok(watcher)
 }
 
 #[cfg(test)]
@@ -11,13 +11,13 @@ mod protocol;
 mod store;
 mod utils;
 use crate::app::TsckApp;
-use crate::app_config::{AppConfig, AppConfigHandler};
+use crate::app_config::{AppConfig, AppConfigHandler, set_cli_overrides};
 use crate::cmd::{CmdrHelper, CommandConfig};
 use crate::event::{ChannelEvent, UserEvent, WinLevel};
-use crate::hotkee::__spawn_hotkee;
+use crate::hotkee::{__spawn_hotkee, cheat_sheet, kee_diagnostics, kee_schema_json};
 use crate::io::{HttpServer, Response};
 use crate::photoshop::{PaginationItems, SmartObjectItem, SmartObjects, TextureRepo};
-use crate::store::config::WindowConf;
+use crate::store::config::{ConfigParser, WindowConf};
 use crate::store::{DbStore, PageChunk, Texture};
 use crate::utils::winview_util::webview_bounds;
 use flume::{Receiver, Sender, unbounded};
@@ -29,7 +29,11 @@ use std::os::windows::process::CommandExt;
 use std::path::{Path, PathBuf};
 use std::process::Command;
 use std::sync::Arc;
-use tsck_utils::ConfigStore;
+use tsck_kee::{CheatSheetFormat, KeeDocument};
+use tsck_utils::{
+    ConfigStore, ConfigWatcher, DEFAULT_BACKUPS, Dir, Report, WatchHandle, format_for, locate,
+    write_atomic,
+};
 use winit::event_loop::{EventLoop, EventLoopProxy};
 use winit::window::Window;
 use wry::WebView;
@@ -107,6 +111,8 @@ pub struct ChannelBus {
         Sender<WebsocketMessagePayload>,
         Receiver<WebsocketMessagePayload>,
     ),
+    /// conf.json, tsck.json and kee.kee watchers when `watch_config` is on
+    watchers: Vec<WatchHandle>,
 }
 impl ChannelBus {
     fn new(proxy: EventLoopProxy) -> anyhow::Result<Self> {
@@ -124,19 +130,78 @@ impl ChannelBus {
             proxy: proxy,
             clients: Arc::new(Mutex::new(HashMap::new())),
             websocket_bus: unbounded::<WebsocketMessagePayload>(),
+            watchers: Vec::new(),
         })
     }
-    pub fn init(self) -> Self {
+    pub fn init(mut self) -> Self {
+        self.log_config_issues();
         _ = self.init_hotkee();
         _ = self.init_file_server();
+        if let Err(err) = self.init_watchers() {
+            log_warn!("watch", err);
+        }
         self
     }
-    fn init_hotkee(&self) {
-        let proxy = self.proxy.clone();
-        let sender = self.sender.clone();
-        std::thread::spawn(|| {
-            _ = __spawn_hotkee(proxy, sender);
-        });
+    /// Log what `tsck config check` would report, startup goes on regardless
+    fn log_config_issues(&self) {
+        match config_reports(&self.config_handler.lock()) {
+            Ok(reports) => {
+                for (path, report) in reports {
+                    for issue in report.issues() {
+                        log_warn!(&path, issue);
+                    }
+                }
+            }
+            Err(err) => log_warn!("config", err),
+        }
+    }
+    fn init_hotkee(&mut self) {
+        // the hooks and the event loop of tsck-kee run on threads of their own
+        match __spawn_hotkee(self.proxy.clone(), self.sender.clone()) {
+            Ok(Some(watcher)) => self.watchers.push(watcher),
+            Ok(None) => {}
+            Err(err) => log_warn!("hotkee", err),
+        }
+    }
+
+    /// Hot-reload conf.json into the handler and tsck.json into the app, an
+    /// edit that doesn't parse is logged and the last good config kept.
+    /// kee.kee is watched from `__spawn_hotkee`.
+    fn init_watchers(&mut self) -> anyhow::Result<()> {
+        let (watch, conf_watcher, current) = {
+            let handler = self.config_handler.lock();
+            (
+                handler.watch_config(),
+                handler.config_store.watch(),
+                handler.config_store.get(|c| c.clone()),
+            )
+        };
+        if !watch {
+            return Ok(());
+        }
+        let config_handler = self.config_handler.clone();
+        let conf = conf_watcher
+            .on_change(move |_, new: &AppConfig| {
+                config_handler.lock().reload_file(new.clone());
+            })
+            .on_reject(|path, err| log_warn!(path.display(), err))
+            .spawn(current)?;
+
+        let (sender, proxy) = (self.sender.clone(), self.proxy.clone());
+        let tsck_path = ConfigStore::<String>::get_file_path(DOTFILE_DIR, "tsck.json")?;
+        let format = format_for(Path::new(&tsck_path));
+        let tsck = ConfigWatcher::new(tsck_path, move |content| {
+            format.decode::<ConfigParser>(content)
+        })
+        .on_change(move |_, _| {
+            _ = sender.send((UserEvent::ReloadConfig, None, None));
+            proxy.wake_up();
+        })
+        .on_reject(|path, err| log_warn!(path.display(), err))
+        .spawn(ConfigParser::load())?;
+
+        self.watchers.extend([conf, tsck]);
+        Ok(())
     }
 
     fn init_file_server(&self) -> anyhow::Result<()> {
@@ -181,9 +246,18 @@ impl ChannelBus {
     pub fn wake_up(&self) {
         self.proxy.wake_up();
     }
-    pub fn update_app_config(&self, config: AppConfig) {
-        self.config_handler.lock().update_config(config);
+    /// Save `config` from the settings unless its check finds errors
+    pub fn update_app_config(&self, config: AppConfig) -> Report {
+        let report = config.validate();
+        for issue in report.issues() {
+            log_warn!("SetAppConfig", issue);
+        }
+        if !report.has_errors() {
+            self.config_handler.lock().update_config(config);
+        }
+        report
     }
+    /// conf.json as saved, without the local, environment and `--set` layers
     pub fn get_app_config(&self) -> AppConfig {
         let guard = {
             let g = self.config_handler.lock();
@@ -351,7 +425,22 @@ fn print_help() {
     tsck.exe                : run gui
     tsck.exe delete         : delete Webview2 folder
     tsck.exe config         : edit conf.json
+    tsck.exe config show [--origin]
+                            : print the effective config, and where each value comes from
+    tsck.exe config check   : validate the effective config and tsck.json
+    tsck.exe config convert <from> <to>
+                            : rewrite a config file in the format of <to>, json, json5 or toml,
+                              names are relative to the config dir
+    tsck.exe --set key=value
+                            : override a config value for this run, repeatable,
+                              conf.local.json and TSCK_KEY variables override conf.json too
     tsck.exe kee            : edit kee.kee
+    tsck.exe kee check      : validate kee.kee
+    tsck.exe kee fmt        : format kee.kee
+    tsck.exe kee sheet [md|html|json] [file]
+                            : export a hotkey cheat sheet
+    tsck.exe kee schema [file]
+                            : export every scope and function as JSON
 "#
     );
 }
@@ -366,6 +455,8 @@ fn delete_cache() -> anyhow::Result<bool> {
     }
     Ok(false)
 }
+/// Open `app_name` in its editor, `conf.json` opens `conf.toml` or
+/// `conf.json5` when that is the one in use
 fn edit_config(app_name: &str) -> anyhow::Result<()> {
     let root = ConfigStore::<String>::get_file_path(DOTFILE_DIR, app_name)?;
     Command::new("pwsh")
@@ -375,20 +466,185 @@ fn edit_config(app_name: &str) -> anyhow::Result<()> {
     Ok(())
 }
 
+fn check_kee() -> anyhow::Result<bool> {
+    let path = ConfigStore::<String>::get_file_path(DOTFILE_DIR, "kee.kee")?;
+    let source = ConfigStore::<String>::read_file(DOTFILE_DIR, "kee.kee")?;
+    let diagnostics = kee_diagnostics(&source);
+    for diagnostic in &diagnostics {
+        eprintln!("{}\n", diagnostic.render(&path, &source));
+    }
+    let errors = diagnostics.iter().filter(|d| d.is_error()).count();
+    let warnings = diagnostics.len() - errors;
+    match (errors, warnings) {
+        (0, 0) => println!("{}: ok", path),
+        (0, w) => println!("{}: ok, {} warning(s)", path, w),
+        (e, w) => eprintln!("{}: {} error(s), {} warning(s)", path, e, w),
+    }
+    Ok(errors == 0)
+}
+
+/// Rewrite kee.kee in its canonical layout, comments and bindings are kept.
+fn fmt_kee() -> anyhow::Result<()> {
+    let path = ConfigStore::<String>::get_file_path(DOTFILE_DIR, "kee.kee")?;
+    let source = ConfigStore::<String>::read_file(DOTFILE_DIR, "kee.kee")?;
+    let formatted = KeeDocument::parse(&source).format();
+    if formatted == source {
+        println!("{}: already formatted", path);
+    } else {
+        write_atomic(Path::new(&path), &formatted, DEFAULT_BACKUPS)?;
+        println!("{}: formatted", path);
+    }
+    Ok(())
+}
+
+/// Render kee.kee as a cheat sheet, into `out` or on stdout.
+fn export_kee_sheet(format: Option<&str>, out: Option<&str>) -> anyhow::Result<()> {
+    let format = format.unwrap_or("md").parse::<CheatSheetFormat>()?;
+    let source = ConfigStore::<String>::read_file(DOTFILE_DIR, "kee.kee")?;
+    let sheet = cheat_sheet(&source).render(format);
+    match out {
+        Some(path) => {
+            std::fs::write(path, sheet)?;
+            println!("{}: written", path);
+        }
+        None => print!("{}", sheet),
+    }
+    Ok(())
+}
+
+/// Effective config, as JSON or one `key = value  (origin)` line per value.
+fn show_config(origin: bool) -> anyhow::Result<()> {
+    let handler = AppConfigHandler::new();
+    let layered = handler.layered();
+    match origin {
+        true => print!("{}", layered.show_origins()),
+        false => println!("{}", serde_json::to_string_pretty(layered.value())?),
+    }
+    Ok(())
+}
+
+/// Semantic check of the effective config and of tsck.json, by file. A file
+/// that doesn't load is an error, not a check of the defaults used instead.
+fn config_reports(handler: &AppConfigHandler) -> anyhow::Result<Vec<(String, Report)>> {
+    let conf = handler.config_store.path().display().to_string();
+    let mut conf_report = handler.layered().get().validate();
+    if let Some(err) = handler.load_error() {
+        conf_report.error("", format!("doesn't load, the defaults are used: {}", err));
+    }
+    let tsck = ConfigStore::<String>::get_file_path(DOTFILE_DIR, "tsck.json")?;
+    let tsck_report = match ConfigParser::try_load() {
+        Ok(config) => config.validate(),
+        Err(err) => {
+            let mut report = Report::new();
+            report.error(
+                "",
+                format!("doesn't load, the embedded one is used: {:#}", err),
+            );
+            report
+        }
+    };
+    Ok(vec![(conf, conf_report), (tsck, tsck_report)])
+}
+
+fn check_config() -> anyhow::Result<bool> {
+    let handler = AppConfigHandler::new();
+    let mut ok = true;
+    for (path, report) in config_reports(&handler)? {
+        for issue in report.errors().chain(report.warnings()) {
+            eprintln!("{}: {}", path, issue);
+        }
+        let errors = report.errors().count();
+        let warnings = report.warnings().count();
+        match (errors, warnings) {
+            (0, 0) => println!("{}: ok", path),
+            (0, w) => println!("{}: ok, {} warning(s)", path, w),
+            (e, w) => eprintln!("{}: {} error(s), {} warning(s)", path, e, w),
+        }
+        ok &= errors == 0;
+    }
+    Ok(ok)
+}
+
+/// Rewrite `from`, or the file in use in its place, in the format of `to`.
+/// `to` is picked up over `from` from then on, `conf.toml` is read instead of
+/// `conf.json`.
+fn convert_config(from: &str, to: &str) -> anyhow::Result<()> {
+    let dir = Dir::store_path(DOTFILE_DIR)?;
+    let (from, to) = (locate(&dir.join(from)), dir.join(to));
+    tsck_utils::convert(&from, &to)?;
+    println!("{} -> {}", from.display(), to.display());
+    Ok(())
+}
+
+/// Pull `--set key=value` and `--set=key=value` out of the arguments
+fn take_overrides(args: Vec<String>) -> (Vec<String>, Vec<String>) {
+    let mut rest = Vec::new();
+    let mut overrides = Vec::new();
+    let mut args = args.into_iter();
+    while let Some(arg) = args.next() {
+        if arg == "--set" {
+            overrides.extend(args.next());
+        } else if let Some(assignment) = arg.strip_prefix("--set=") {
+            overrides.push(assignment.to_string());
+        } else {
+            rest.push(arg);
+        }
+    }
+    (rest, overrides)
+}
+
+fn export_kee_schema(out: Option<&str>) -> anyhow::Result<()> {
+    let schema = kee_schema_json();
+    match out {
+        Some(path) => {
+            std::fs::write(path, schema)?;
+            println!("{}: written", path);
+        }
+        None => println!("{}", schema),
+    }
+    Ok(())
+}
+
 fn main() -> anyhow::Result<()> {
-    let args: Vec<_> = std::env::args().collect();
+    let (args, overrides) = take_overrides(std::env::args().collect());
+    set_cli_overrides(overrides);
     if args.len() > 1 {
         match args[1].as_str() {
             "delete" => {
                 let result = delete_cache();
                 println!("Deleted status {result:?}");
             }
-            "config" => {
-                _ = edit_config("conf.json");
-            }
-            "kee" => {
-                _ = edit_config("kee.kee");
-            }
+            "config" => match args.get(2).map(String::as_str) {
+                Some("show") => show_config(args.get(3).is_some_and(|a| a == "--origin"))?,
+                Some("check") => {
+                    if !check_config()? {
+                        std::process::exit(1);
+                    }
+                }
+                Some("convert") => match (args.get(3), args.get(4)) {
+                    (Some(from), Some(to)) => convert_config(from, to)?,
+                    _ => print_help(),
+                },
+                _ => {
+                    _ = edit_config("conf.json");
+                }
+            },
+            "kee" => match args.get(2).map(String::as_str) {
+                Some("check") => {
+                    if !check_kee()? {
+                        std::process::exit(1);
+                    }
+                }
+                Some("fmt") => fmt_kee()?,
+                Some("sheet") => export_kee_sheet(
+                    args.get(3).map(String::as_str),
+                    args.get(4).map(String::as_str),
+                )?,
+                Some("schema") => export_kee_schema(args.get(3).map(String::as_str))?,
+                _ => {
+                    _ = edit_config("kee.kee");
+                }
+            },
             _ => print_help(),
         }
         return Ok(());
@@ -1,12 +1,15 @@
 #![allow(unused)]
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
+use std::path::Path;
 use ts_rs::TS;
 use winit::dpi::{LogicalPosition, LogicalSize};
 use winit::dpi::{Position, Size};
 use wry::dpi::{PhysicalPosition, PhysicalSize};
 
 use crate::event::TS_PATH;
+use crate::{DOTFILE_DIR, log_warn};
+use tsck_utils::{ConfigStore, Report, read_config};
 
 #[derive(Deserialize, Serialize, Debug, Clone)]
 pub enum WindowSrc {
@@ -160,6 +163,34 @@ impl PluginConf {
         };
         result
     }
+    fn validate(&self) -> Report {
+        let mut report = Report::new();
+        let Some(window) = &self.window else {
+            if self.with_window {
+                report.error("window", "missing, `with_window` is set");
+            }
+            return report;
+        };
+        let size = &window.window_size;
+        if size.width <= 0 || size.height <= 0 {
+            report.error(
+                "window.window_size",
+                format!("{}x{} is not a size", size.width, size.height),
+            );
+        }
+        if window.webview_zoom_factor <= 0.0 {
+            report.error("window.webview_zoom_factor", "must be above 0");
+        }
+        if let WindowSrc::Local(page) = &window.window_src
+            && !page.starts_with('/')
+        {
+            report.error(
+                "window.window_src",
+                format!("`{}` should start with /", page),
+            );
+        }
+        report
+    }
 }
 
 #[derive(Debug, Deserialize, Serialize)]
@@ -168,8 +199,49 @@ pub struct ConfigParser {
     pub plugins: HashMap<String, PluginConf>,
 }
 impl ConfigParser {
+    /// tsck.json shipped with the binary
+    pub const EMBEDDED: &'static str = include_str!("../../tsck.json");
+
     pub fn parse(config: &str) -> Self {
-        serde_json::from_str::<ConfigParser>(config).expect("Failed to parse tsck.json")
+        Self::try_parse(config).expect("Failed to parse tsck.json")
+    }
+    pub fn try_parse(config: &str) -> anyhow::Result<Self> {
+        Ok(serde_json::from_str::<ConfigParser>(config)?)
+    }
+    /// tsck.json (or tsck.json5, tsck.toml) of the dotfile dir when there is
+    /// a valid one, the embedded copy otherwise
+    pub fn load() -> Self {
+        Self::try_load().unwrap_or_else(|err| {
+            log_warn!(
+                "tsck.json doesn't load, using the embedded one",
+                format!("{:#}", err)
+            );
+            Self::parse(Self::EMBEDDED)
+        })
+    }
+    /// tsck.json of the dotfile dir, the embedded copy when there is none
+    pub fn try_load() -> anyhow::Result<Self> {
+        let path = ConfigStore::<String>::get_file_path(DOTFILE_DIR, "tsck.json")?;
+        match Path::new(&path).exists() {
+            true => read_config(Path::new(&path)),
+            false => Ok(Self::parse(Self::EMBEDDED)),
+        }
+    }
+    /// Plugin windows that can't open as configured
+    pub fn validate(&self) -> Report {
+        let mut report = Report::new();
+        if !(self.dev_url.starts_with("http://") || self.dev_url.starts_with("https://")) {
+            report.error(
+                "dev_url",
+                format!("`{}` is not an http(s) url", self.dev_url),
+            );
+        }
+        let mut names: Vec<&String> = self.plugins.keys().collect();
+        names.sort();
+        for name in names {
+            report.nest(&format!("plugins.{}", name), self.plugins[name].validate());
+        }
+        report
     }
 }
 
@@ -179,7 +251,47 @@ mod test {
 
     #[test]
     fn test() {
-        let config = ConfigParser::parse(include_str!("../../tsck.json"));
+        let config = ConfigParser::parse(ConfigParser::EMBEDDED);
         log_error!(dp!(config));
     }
+
+    #[test]
+    fn test_validate() {
+        let config = ConfigParser::parse(ConfigParser::EMBEDDED);
+        assert!(config.validate().is_empty(), "{}", config.validate());
+
+        let config = ConfigParser::parse(
+            r#"{
+                "dev_url": "127.0.0.1:5566",
+                "plugins": {
+                    "toolbar": { "with_window": true },
+                    "main": {
+                        "with_window": true,
+                        "window": {
+                            "window_src": { "Local": "Workspace" },
+                            "window_size": { "width": 0, "height": 40 },
+                            "window_position": { "x": 0, "y": 0 }
+                        }
+                    }
+                }
+            }"#,
+        );
+        let report = config.validate();
+        let paths: Vec<&str> = report.issues().iter().map(|i| i.path.as_str()).collect();
+        assert_eq!(
+            paths,
+            [
+                "dev_url",
+                "plugins.main.window.window_size",
+                "plugins.main.window.window_src",
+                "plugins.toolbar.window",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_try_parse() {
+        assert!(ConfigParser::try_parse(ConfigParser::EMBEDDED).is_ok());
+        assert!(ConfigParser::try_parse("{ \"dev_url\": 1 }").is_err());
+    }
 }
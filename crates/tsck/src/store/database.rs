@@ -3,10 +3,43 @@ use anyhow::{Context, Result, bail};
 use parking_lot::Mutex;
 use rusqlite::{Connection, Row};
 use serde::{Deserialize, Serialize};
+use std::path::PathBuf;
 use std::sync::Arc;
 use tsck_utils::Dir;
 
-use crate::DOTFILE_DIR;
+use crate::{DOTFILE_DIR, log_info, log_warn};
+
+const DB_FILE: &str = "tsck-store.db";
+
+/// The database lives in the data dir, it used to sit next to conf.json and is
+/// moved over once. When the move fails the old one keeps being used.
+fn db_path() -> Result<PathBuf> {
+    let path = Dir::data_file(DOTFILE_DIR, DB_FILE)?;
+    let old = Dir::store_file(DOTFILE_DIR, DB_FILE)?;
+    if old == path || !old.exists() || path.exists() {
+        return Ok(path);
+    }
+    // the write-ahead log holds commits that aren't in the db file yet, it
+    // moves along or everything moves back
+    let mut moved: Vec<(PathBuf, PathBuf)> = Vec::new();
+    for suffix in ["", "-wal", "-shm"] {
+        let from = PathBuf::from(format!("{}{}", old.display(), suffix));
+        let to = PathBuf::from(format!("{}{}", path.display(), suffix));
+        if !from.exists() {
+            continue;
+        }
+        if let Err(err) = std::fs::rename(&from, &to) {
+            for (from, to) in moved.iter().rev() {
+                _ = std::fs::rename(to, from);
+            }
+            log_warn!("database stays in the config dir", err);
+            return Ok(old);
+        }
+        moved.push((from, to));
+    }
+    log_info!("database moved to", path.display());
+    Ok(path)
+}
 
 #[derive(Debug, Serialize, Deserialize)]
 pub struct PageChunk<T> {
@@ -61,7 +94,7 @@ pub struct Database {
 impl Database {
     /// Open database with optimized settings
     pub fn open() -> Result<Self> {
-        let path = Dir::store_file(DOTFILE_DIR, "tsck-store.db")?;
+        let path = db_path()?;
         let conn = Connection::open(path).context("Failed to open database")?;
 
         conn.execute_batch(
@@ -6,7 +6,7 @@ export type AnimationPayload = { label: string, duration: number, toSize?: [numb
 
 export type AppCommand = { name: string, pid: number, executable: string | null, work_dir: string | null, args: Array<string>, };
 
-export type AppConfig = { monitors: Array<[number, number]>, apps: Array<string>, pages: Array<string>, workspaces: Array<string>, version: string, store_root: string, http_server_port: number, whatsapp_bot_port: number, comfyui_url: string, comfyui_root: string, websocket_server_port: number, move_increment: number, resize_increment: number, rawfilter_template: Array<RawFilterTemplate>, command_config: CommandConfig, color_list: Array<string>, whatsapp_url: string, window_sizes: Array<WindowSize>, };
+export type AppConfig = { schema_version: number, monitors: Array<[number, number]>, apps: Array<string>, pages: Array<string>, workspaces: Array<string>, store_root: string, http_server_port: number, whatsapp_bot_port: number, comfyui_url: string, comfyui_root: string, websocket_server_port: number, rawfilter_template: Array<RawFilterTemplate>, command_config: CommandConfig, color_list: Array<string>, whatsapp_url: string, window_sizes: Array<WindowSize>, kee_sequence_timeout: number, kee_mode_timeout: number, watch_config: boolean, };
 
 export type Bounds = { top: number, bottom: number, left: number, right: number, };
 
@@ -14,12 +14,74 @@ export type CmdrLog = { log_type: LogType, app_name: string, content: string | n
 
 export type CommandConfig = { log_limit: number, commands: Array<AppCommand>, };
 
+export type ConfigIssueReport = { 
+/**
+ * `error` or `warning`
+ */
+severity: string, 
+/**
+ * `command_config.commands[0].executable`
+ */
+path: string, message: string, };
+
 export type CustomScripts = { script_list: Array<string>, };
 
 export type EventPayload = "tsck::event|EVENTPAYLOAD::FRONTEND" | "tsck::event|EVENTPAYLOAD::BACKEND" | "tsck::event|EVENTPAYLOAD::HOTKEE" | "tsck::event|EVENTPAYLOAD::COMMAND";
 
 export type FuncCallArgs = number | string | boolean;
 
+export type KeeArgSchema = { 
+/**
+ * Name accepted as `name=value`, null for positional only arguments
+ */
+name: string | null, 
+/**
+ * Rust type, `i32`, `Option<String>`, `(i32,i32)`
+ */
+ty: string, 
+/**
+ * Value used when left out, as written in kee.kee
+ */
+default: string | null, optional: boolean, doc: string | null, };
+
+export type KeeCheatEntry = { 
+/**
+ * Human-readable keys, `Win+Shift+←`
+ */
+keys: string, 
+/**
+ * Canonical form, `M-S-left`
+ */
+kee: string, func: string, app: string | null, description: string | null, line: number, };
+
+export type KeeCheatSection = { 
+/**
+ * Section name, `mode <name>` or `general`
+ */
+title: string, section: string | null, mode: string | null, description: string | null, entries: Array<KeeCheatEntry>, };
+
+export type KeeConflictReport = { 
+/**
+ * `duplicate`, `shadowed` or `reserved`
+ */
+kind: string, 
+/**
+ * Canonical form, `S-M-a` -> `M-S-a`
+ */
+key: string, line: number, 
+/**
+ * The binding it collides with
+ */
+other_key: string | null, other_line: number | null, message: string, };
+
+export type KeeFuncSchema = { name: string, aliases: Array<string>, doc: string | null, 
+/**
+ * `MOVEACTIVEWINDOW(dir: String, by: i32 = 80, duration: u64 = 150)`
+ */
+signature: string, args: Array<KeeArgSchema>, };
+
+export type KeeScopeSchema = { name: string, aliases: Array<string>, doc: string | null, funcs: Array<KeeFuncSchema>, };
+
 export type LogType = "Pid" | "Stdout" | "Stderr";
 
 export type PaginationItems = { page: number, total_page: number, current_items: Array<SmartObjectItem>, };
@@ -30,6 +92,16 @@ export type RawFilterTemplate = { name: string, value: RawFilterDataType, };
 
 export type RawFilterTextPipRange = { rawfilter_data: RawFilterDataType, text_layers_info: Array<TextLayerInfo>, pip_ranges: Array<number>, layer_kind: string, };
 
+export type ReadableHotkee = { section: string | null, 
+/**
+ * App the binding is limited to, `M-d [PHOTOSHOP]`
+ */
+app: string | null, 
+/**
+ * `mode name { ... }` block the key belongs to
+ */
+mode: string | null, key: string, meta: boolean, ctrl: boolean, shift: boolean, alt: boolean, func: string, };
+
 export type SelectionBound = { selection_mode: boolean, bounds: Bounds, };
 
 export type SmartObjectItem = { id: number, name: string, thumb: string, };
@@ -50,7 +122,7 @@ export type ToolbarPosition = "TopLeft" | "TopRight" | "BottomLeft" | "BottomRig
 
 export type UnListen = (() => void)|undefined;
 
-export type UserEvent = { "type": "GetReadableHotkee" } | { "type": "WindowFocusChange", "value": boolean } | { "type": "ReloadConfig" } | { "type": "Minimize" } | { "type": "Maximize" } | { "type": "DragWindow" } | { "type": "CloseWindow" } | { "type": "MouseDown", "value": [number, number] } | { "type": "MouseMove", "value": [number, number] } | { "type": "LaunchPlugin", "value": string } | { "type": "EvalJs", "value": string } | { "type": "LoadUrl", "value": string } | { "type": "IsOnTop" } | { "type": "NavigateWebview", "value": string } | { "type": "ZoomWebview", "value": number } | { "type": "UpdateToolbarPanel", "value": ToolbarPanel } | { "type": "SetWindowLevel", "value": [WinLevel, string] } | { "type": "SetWindowDecorated", "value": boolean } | { "type": "SetWindowShadow", "value": boolean } | { "type": "SetWindowSize", "value": WindowSize } | { "type": "SetWindowPosition", "value": WindowPosition } | { "type": "TransformWindow", "value": AnimationPayload } | { "type": "SetIgnoreCursorEvent", "value": boolean } | { "type": "GoogleDownloadImage", "value": string } | { "type": "ActivateWorkSpace", "value": number } | { "type": "CyclePages", "value": number } | { "type": "GetActiveWindows" } | { "type": "IncomingWebsocketMessage", "value": [number, string] } | { "type": "Template", "value": { template: Template, } } | { "type": "LoadingState", "value": { loading: boolean, } } | { "type": "UpdateTextureFavorite", "value": [number, boolean] } | { "type": "FetchTextures", "value": [string, number, number] } | { "type": "FetchTextureCategories" } | { "type": "SmartObjectDelete", "value": SmartObjectItem } | { "type": "SmartobjectThumbnailUpdate", "value": { name: string, thumb: string, } } | { "type": "FilterSmartObjectChunk", "value": { query: string, page: number, per_page: number, } } | { "type": "FunctionCall", "value": { func: string, args: Array<FuncCallArgs>, } } | { "type": "GetAppConfig" } | { "type": "SetAppConfig", "value": AppConfig } | { "type": "SelectionChanged", "value": SelectionBound } | { "type": "RawFilterDataUpdate", "value": RawFilterDataType } | { "type": "PerformSelectionToImage" } | { "type": "PerformLayerToImage" } | { "type": "GenerateImage" } | { "type": "FacerestorePreviewImage", "value": Array<string> } | { "type": "AppendComfyUIOutput", "value": { images: Array<string>, bounds: Bounds, } } | { "type": "RawFilterTextPipRange", "value": RawFilterTextPipRange } | { "type": "ApplyRawFilter", "value": RawFilterDataType } | { "type": "ApplyTriColor", "value": { tri_color: Array<string>, position: Array<number>, } } | { "type": "PipRanges", "value": Array<number> } | { "type": "RequestCommand" } | { "type": "RunCommand", "value": string } | { "type": "KillCommand", "value": string } | { "type": "ReloadCommandConfig" } | { "type": "UpdateRawfilterTemplates", "value": Array<RawFilterTemplate> } | { "type": "UpdateActiveApps" } | { "type": "ExecuteScript", "value": string } | { "type": "BroadcastToFrontEnd", "value": [string, string] } | { "type": "ToggleShadow" } | { "type": "YoutubeTitle", "value": string } | { "type": "YoutubeTitleWithApiKey", "value": [string, string] } | { "type": "ToggleCompactMode" } | { "type": "FocusPage", "value": number } | { "type": "FocusWindow", "value": string } | { "type": "ToggleWindowLevel" } | { "type": "WhatsappUpdate", "value": { type: string, } };
+export type UserEvent = { "type": "GetReadableHotkee" } | { "type": "GetKeeConflicts" } | { "type": "GetKeeCheatSheet" } | { "type": "GetKeeSchema" } | { "type": "WindowFocusChange", "value": boolean } | { "type": "ReloadConfig" } | { "type": "Minimize" } | { "type": "Maximize" } | { "type": "DragWindow" } | { "type": "CloseWindow" } | { "type": "MouseDown", "value": [number, number] } | { "type": "MouseMove", "value": [number, number] } | { "type": "LaunchPlugin", "value": string } | { "type": "EvalJs", "value": string } | { "type": "LoadUrl", "value": string } | { "type": "IsOnTop" } | { "type": "NavigateWebview", "value": string } | { "type": "ZoomWebview", "value": number } | { "type": "UpdateToolbarPanel", "value": ToolbarPanel } | { "type": "SetWindowLevel", "value": [WinLevel, string] } | { "type": "SetWindowDecorated", "value": boolean } | { "type": "SetWindowShadow", "value": boolean } | { "type": "SetWindowSize", "value": WindowSize } | { "type": "SetWindowPosition", "value": WindowPosition } | { "type": "TransformWindow", "value": AnimationPayload } | { "type": "SetIgnoreCursorEvent", "value": boolean } | { "type": "GoogleDownloadImage", "value": string } | { "type": "ActivateWorkSpace", "value": number } | { "type": "KeeModeChange", "value": string | null } | { "type": "CyclePages", "value": number } | { "type": "GetActiveWindows" } | { "type": "IncomingWebsocketMessage", "value": [number, string] } | { "type": "Template", "value": { template: Template, } } | { "type": "LoadingState", "value": { loading: boolean, } } | { "type": "UpdateTextureFavorite", "value": [number, boolean] } | { "type": "FetchTextures", "value": [string, number, number] } | { "type": "FetchTextureCategories" } | { "type": "SmartObjectDelete", "value": SmartObjectItem } | { "type": "SmartobjectThumbnailUpdate", "value": { name: string, thumb: string, } } | { "type": "FilterSmartObjectChunk", "value": { query: string, page: number, per_page: number, } } | { "type": "FunctionCall", "value": { func: string, args: Array<FuncCallArgs>, } } | { "type": "GetAppConfig" } | { "type": "SetAppConfig", "value": AppConfig } | { "type": "SelectionChanged", "value": SelectionBound } | { "type": "RawFilterDataUpdate", "value": RawFilterDataType } | { "type": "PerformSelectionToImage" } | { "type": "PerformLayerToImage" } | { "type": "GenerateImage" } | { "type": "FacerestorePreviewImage", "value": Array<string> } | { "type": "AppendComfyUIOutput", "value": { images: Array<string>, bounds: Bounds, } } | { "type": "RawFilterTextPipRange", "value": RawFilterTextPipRange } | { "type": "ApplyRawFilter", "value": RawFilterDataType } | { "type": "ApplyTriColor", "value": { tri_color: Array<string>, position: Array<number>, } } | { "type": "PipRanges", "value": Array<number> } | { "type": "RequestCommand" } | { "type": "RunCommand", "value": string } | { "type": "KillCommand", "value": string } | { "type": "ReloadCommandConfig" } | { "type": "UpdateRawfilterTemplates", "value": Array<RawFilterTemplate> } | { "type": "UpdateActiveApps" } | { "type": "ExecuteScript", "value": string } | { "type": "BroadcastToFrontEnd", "value": [string, string] } | { "type": "ToggleShadow" } | { "type": "YoutubeTitle", "value": string } | { "type": "YoutubeTitleWithApiKey", "value": [string, string] } | { "type": "ToggleCompactMode" } | { "type": "FocusPage", "value": number } | { "type": "FocusWindow", "value": string } | { "type": "ToggleWindowLevel" } | { "type": "WhatsappUpdate", "value": { type: string, } };
 
 export type WinLevel = "Normal" | "Top" | "Bottom";
 